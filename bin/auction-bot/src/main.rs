@@ -1,12 +1,25 @@
-use clap::Parser;
-use sommelier_auction_order_engine::{config::Config, engine::OrderEngine};
-use tracing::{debug, error};
+use clap::{Parser, Subcommand};
+use sommelier_auction_order_engine::{backtest, config, engine::OrderEngine};
+use tracing::{debug, error, info};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     #[arg(short, long)]
     config: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Replay the configured orders against every ended auction and report fills, spend and PnL
+    /// instead of running the bot
+    Backtest {
+        /// How much the auction price decrease rate grows each interval, as a decimal
+        #[arg(long, default_value_t = 0.0)]
+        acceleration_rate: f64,
+    },
 }
 
 #[tokio::main]
@@ -15,14 +28,43 @@ async fn main() {
 
     let args = Args::parse();
     if args.config.is_empty() {
-        panic!("config file path is required");
+        error!("config file path is required");
+        std::process::exit(1);
     }
 
-    let config: Config = confy::load_path(&args.config).expect("failed to load config");
+    let config = match config::load(&args.config) {
+        Ok(config) => config,
+        Err(err) => {
+            error!("failed to load config: {err}");
+            std::process::exit(1);
+        }
+    };
     debug!("config: {config:?}");
 
+    if let Some(Command::Backtest { acceleration_rate }) = args.command {
+        match backtest::backtest(config, acceleration_rate).await {
+            Ok(report) => {
+                for fill in &report.fills {
+                    info!("{fill:?}");
+                }
+                info!("backtest complete: {report}");
+            }
+            Err(e) => {
+                error!("error running backtest: {e}");
+                std::process::exit(1);
+            }
+        }
+
+        return;
+    }
+
+    let watch_orders = config.watch_orders.unwrap_or_default();
     let mut engine = OrderEngine::new(config);
+    if watch_orders {
+        engine.orders_path = Some(args.config);
+    }
     if let Err(e) = engine.start().await {
         error!("error running engine: {e}");
+        std::process::exit(1);
     }
 }
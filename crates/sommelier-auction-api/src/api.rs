@@ -0,0 +1,227 @@
+use async_graphql_axum::GraphQL;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use eyre::Result;
+use serde::Deserialize;
+use tracing::info;
+use utoipa::{IntoParams, OpenApi};
+
+use crate::{
+    graphql,
+    store::{AuctionStatus, AuctionView, BidView, Snapshot, Stats, Store, TokenStats},
+};
+
+/// Page size when a request doesn't give one
+pub const DEFAULT_LIMIT: usize = 100;
+/// Largest page a request may ask for
+pub const MAX_LIMIT: usize = 1_000;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Sommelier auction API",
+        description = "Fee auctions and bids on Sommelier"
+    ),
+    paths(list_auctions, auction_bids, stats),
+    components(schemas(AuctionStatus, AuctionView, BidView, Stats, TokenStats))
+)]
+pub struct ApiDoc;
+
+/// Filters and paging for the auctions route
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuctionQuery {
+    /// Only auctions with this status
+    pub status: Option<AuctionStatus>,
+    /// Only auctions selling this denom or symbol, e.g. `USDC`
+    pub token: Option<String>,
+    /// Maximum number of auctions to return. Defaults to 100, at most 1000.
+    pub limit: Option<usize>,
+    /// Number of matching auctions to skip
+    pub offset: Option<usize>,
+}
+
+/// Paging for the bids route
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageQuery {
+    /// Maximum number of bids to return. Defaults to 100, at most 1000.
+    pub limit: Option<usize>,
+    /// Number of bids to skip
+    pub offset: Option<usize>,
+}
+
+type ApiResult<T> = std::result::Result<Json<T>, (StatusCode, String)>;
+
+/// The API's routes, reading from `store`
+pub fn router(store: Store) -> Router {
+    Router::new()
+        .route("/v1/auctions", get(list_auctions))
+        .route("/v1/auctions/:id/bids", get(auction_bids))
+        .route("/v1/stats", get(stats))
+        .route("/v1/openapi.json", get(openapi))
+        .route_service("/v1/graphql", GraphQL::new(graphql::schema(store.clone())))
+        .with_state(store)
+}
+
+/// Serves the API on `listen_addr` until the task is aborted
+pub async fn serve(listen_addr: String, store: Store) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(&listen_addr).await?;
+    info!("api listening on {listen_addr}");
+    axum::serve(listener, router(store)).await?;
+
+    Ok(())
+}
+
+/// The auctions matching `query`, newest first
+pub fn auctions(snapshot: &Snapshot, query: &AuctionQuery) -> Vec<AuctionView> {
+    snapshot
+        .auctions
+        .values()
+        .rev()
+        .filter(|a| query.status.map_or(true, |status| a.status == status))
+        .filter(|a| {
+            query.token.as_ref().map_or(true, |token| {
+                a.denom == *token
+                    || a.symbol
+                        .as_ref()
+                        .is_some_and(|s| s.eq_ignore_ascii_case(token))
+            })
+        })
+        .skip(query.offset.unwrap_or_default())
+        .take(limit(query.limit))
+        .cloned()
+        .collect()
+}
+
+pub(crate) fn limit(limit: Option<usize>) -> usize {
+    limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT)
+}
+
+/// Lists auctions, newest first
+#[utoipa::path(
+    get,
+    path = "/v1/auctions",
+    params(AuctionQuery),
+    responses((status = 200, description = "Matching auctions", body = [AuctionView]))
+)]
+async fn list_auctions(
+    State(store): State<Store>,
+    Query(query): Query<AuctionQuery>,
+) -> Json<Vec<AuctionView>> {
+    Json(auctions(&store.snapshot(), &query))
+}
+
+/// Lists an auction's bids, oldest first
+#[utoipa::path(
+    get,
+    path = "/v1/auctions/{id}/bids",
+    params(("id" = u32, Path, description = "Auction ID"), PageQuery),
+    responses(
+        (status = 200, description = "The auction's bids", body = [BidView]),
+        (status = 404, description = "No auction has the ID")
+    )
+)]
+async fn auction_bids(
+    State(store): State<Store>,
+    Path(id): Path<u32>,
+    Query(query): Query<PageQuery>,
+) -> ApiResult<Vec<BidView>> {
+    let snapshot = store.snapshot();
+    if !snapshot.auctions.contains_key(&id) {
+        return Err((StatusCode::NOT_FOUND, format!("no auction with id {id}")));
+    }
+    let bids = snapshot
+        .bids
+        .get(&id)
+        .into_iter()
+        .flatten()
+        .skip(query.offset.unwrap_or_default())
+        .take(limit(query.limit))
+        .cloned()
+        .collect();
+
+    Ok(Json(bids))
+}
+
+/// Totals over every auction, overall and by token
+#[utoipa::path(
+    get,
+    path = "/v1/stats",
+    responses((status = 200, description = "Auction and bid totals", body = Stats))
+)]
+async fn stats(State(store): State<Store>) -> Json<Stats> {
+    Json(store.snapshot().stats())
+}
+
+async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use sommelier_auction::auction::Auction;
+
+    use super::*;
+
+    #[test]
+    fn test_auctions() {
+        let mut snapshot = Snapshot::default();
+        for (id, status) in [
+            (1, AuctionStatus::Ended),
+            (2, AuctionStatus::Ended),
+            (3, AuctionStatus::Active),
+        ] {
+            let auction = Auction {
+                id,
+                ..Default::default()
+            };
+            snapshot
+                .auctions
+                .insert(id, AuctionView::new(&auction, status));
+        }
+        let ids = |query: AuctionQuery| {
+            auctions(&snapshot, &query)
+                .iter()
+                .map(|a| a.id)
+                .collect::<Vec<u32>>()
+        };
+
+        assert_eq!(ids(AuctionQuery::default()), vec![3, 2, 1]);
+        assert_eq!(
+            ids(AuctionQuery {
+                status: Some(AuctionStatus::Ended),
+                ..Default::default()
+            }),
+            vec![2, 1]
+        );
+        assert_eq!(
+            ids(AuctionQuery {
+                limit: Some(1),
+                offset: Some(1),
+                ..Default::default()
+            }),
+            vec![2]
+        );
+        assert!(ids(AuctionQuery {
+            token: Some("WETH".to_string()),
+            ..Default::default()
+        })
+        .is_empty());
+    }
+
+    #[test]
+    fn test_openapi() {
+        let doc = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        for path in ["/v1/auctions", "/v1/auctions/{id}/bids", "/v1/stats"] {
+            assert!(
+                doc["paths"][path]["get"].is_object(),
+                "{path} is documented"
+            );
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use std::{str::FromStr, sync::Arc};
+
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Object, Result, Schema};
+use sommelier_auction::denom::Denom;
+
+use crate::{
+    api::{self, AuctionQuery},
+    store::{AuctionStatus, AuctionView, BidView, Snapshot, Store},
+};
+
+/// The GraphQL schema over a [`Store`], served at `/v1/graphql`
+pub type AuctionSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+pub fn schema(store: Store) -> AuctionSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(store)
+        .finish()
+}
+
+// Every object resolved in a query reads from the snapshot the query started with, so results are
+// consistent even if the store refreshes meanwhile
+fn snapshot(ctx: &Context<'_>) -> Result<Arc<Snapshot>> {
+    Ok(ctx.data::<Store>()?.snapshot())
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Auctions, newest first. `first` defaults to 100, at most 1000.
+    async fn auctions(
+        &self,
+        ctx: &Context<'_>,
+        status: Option<AuctionStatus>,
+        #[graphql(desc = "Denom or symbol of the token sold, e.g. USDC")] token: Option<String>,
+        first: Option<usize>,
+        offset: Option<usize>,
+    ) -> Result<Vec<Auction>> {
+        let snapshot = snapshot(ctx)?;
+        let query = AuctionQuery {
+            status,
+            token,
+            limit: first,
+            offset,
+        };
+
+        Ok(api::auctions(&snapshot, &query)
+            .into_iter()
+            .map(|view| Auction {
+                view,
+                snapshot: snapshot.clone(),
+            })
+            .collect())
+    }
+
+    async fn auction(&self, ctx: &Context<'_>, id: u32) -> Result<Option<Auction>> {
+        let snapshot = snapshot(ctx)?;
+
+        Ok(snapshot.auctions.get(&id).cloned().map(|view| Auction {
+            view,
+            snapshot: snapshot.clone(),
+        }))
+    }
+
+    /// Bids across every auction, newest first. `first` defaults to 100, at most 1000.
+    async fn bids(
+        &self,
+        ctx: &Context<'_>,
+        auction_id: Option<u32>,
+        bidder: Option<String>,
+        first: Option<usize>,
+        offset: Option<usize>,
+    ) -> Result<Vec<Bid>> {
+        let snapshot = snapshot(ctx)?;
+        let mut bids = snapshot
+            .bids
+            .iter()
+            .filter(|(id, _)| auction_id.map_or(true, |auction_id| **id == auction_id))
+            .flat_map(|(_, bids)| bids.iter())
+            .filter(|b| bidder.as_ref().map_or(true, |bidder| b.bidder == *bidder))
+            .collect::<Vec<&BidView>>();
+        bids.sort_by_key(|b| std::cmp::Reverse((b.block_height, b.auction_id, b.id)));
+
+        Ok(bids
+            .into_iter()
+            .skip(offset.unwrap_or_default())
+            .take(api::limit(first))
+            .map(|view| Bid {
+                view: view.clone(),
+                snapshot: snapshot.clone(),
+            })
+            .collect())
+    }
+
+    /// Every token that has been auctioned, by denom
+    async fn tokens(&self, ctx: &Context<'_>) -> Result<Vec<Token>> {
+        let snapshot = snapshot(ctx)?;
+        let mut denoms = snapshot
+            .auctions
+            .values()
+            .map(|a| a.denom.clone())
+            .collect::<Vec<String>>();
+        denoms.sort();
+        denoms.dedup();
+
+        Ok(denoms
+            .into_iter()
+            .map(|denom| Token {
+                denom,
+                snapshot: snapshot.clone(),
+            })
+            .collect())
+    }
+}
+
+pub struct Auction {
+    view: AuctionView,
+    snapshot: Arc<Snapshot>,
+}
+
+/// A fee auction. Token and usomm amounts are integer strings.
+#[Object]
+impl Auction {
+    async fn id(&self) -> u32 {
+        self.view.id
+    }
+
+    async fn status(&self) -> AuctionStatus {
+        self.view.status
+    }
+
+    /// The token being sold
+    async fn token(&self) -> Token {
+        Token {
+            denom: self.view.denom.clone(),
+            snapshot: self.snapshot.clone(),
+        }
+    }
+
+    async fn start_block(&self) -> u64 {
+        self.view.start_block
+    }
+
+    async fn end_block(&self) -> u64 {
+        self.view.end_block
+    }
+
+    async fn starting_tokens_for_sale(&self) -> &str {
+        &self.view.starting_tokens_for_sale
+    }
+
+    async fn remaining_tokens_for_sale(&self) -> &str {
+        &self.view.remaining_tokens_for_sale
+    }
+
+    /// Price in usomm per base unit of the token when the auction started, as a decimal string
+    async fn initial_unit_price_in_usomm(&self) -> &str {
+        &self.view.initial_unit_price_in_usomm
+    }
+
+    /// Price in usomm per base unit of the token now, as a decimal string
+    async fn current_unit_price_in_usomm(&self) -> &str {
+        &self.view.current_unit_price_in_usomm
+    }
+
+    async fn current_price_decrease_rate(&self) -> &str {
+        &self.view.current_price_decrease_rate
+    }
+
+    async fn price_decrease_block_interval(&self) -> u64 {
+        self.view.price_decrease_block_interval
+    }
+
+    /// The auction's bids, oldest first. `first` defaults to 100, at most 1000.
+    async fn bids(&self, first: Option<usize>, offset: Option<usize>) -> Vec<Bid> {
+        self.snapshot
+            .bids
+            .get(&self.view.id)
+            .into_iter()
+            .flatten()
+            .skip(offset.unwrap_or_default())
+            .take(api::limit(first))
+            .map(|view| Bid {
+                view: view.clone(),
+                snapshot: self.snapshot.clone(),
+            })
+            .collect()
+    }
+}
+
+pub struct Bid {
+    view: BidView,
+    snapshot: Arc<Snapshot>,
+}
+
+/// A bid on a fee auction. Token and usomm amounts are integer strings.
+#[Object]
+impl Bid {
+    async fn id(&self) -> u64 {
+        self.view.id
+    }
+
+    async fn auction(&self) -> Option<Auction> {
+        self.snapshot
+            .auctions
+            .get(&self.view.auction_id)
+            .cloned()
+            .map(|view| Auction {
+                view,
+                snapshot: self.snapshot.clone(),
+            })
+    }
+
+    async fn bidder(&self) -> &str {
+        &self.view.bidder
+    }
+
+    async fn max_bid_in_usomm(&self) -> &str {
+        &self.view.max_bid_in_usomm
+    }
+
+    async fn sale_token_minimum_amount(&self) -> &str {
+        &self.view.sale_token_minimum_amount
+    }
+
+    async fn total_fulfilled_sale_tokens(&self) -> &str {
+        &self.view.total_fulfilled_sale_tokens
+    }
+
+    /// Price the bid was fulfilled at in usomm per base unit of the token, as a decimal string
+    async fn sale_token_unit_price_in_usomm(&self) -> &str {
+        &self.view.sale_token_unit_price_in_usomm
+    }
+
+    async fn total_usomm_paid(&self) -> &str {
+        &self.view.total_usomm_paid
+    }
+
+    async fn block_height(&self) -> u64 {
+        self.view.block_height
+    }
+}
+
+pub struct Token {
+    denom: String,
+    snapshot: Arc<Snapshot>,
+}
+
+/// A token sold in fee auctions
+#[Object]
+impl Token {
+    async fn denom(&self) -> &str {
+        &self.denom
+    }
+
+    /// Symbol of the token, if it's one this bot knows
+    async fn symbol(&self) -> Option<String> {
+        Denom::from_str(&self.denom).ok().map(|d| d.symbol())
+    }
+
+    /// Decimal places of the token, if it's one this bot knows
+    async fn decimals(&self) -> Option<u8> {
+        Denom::from_str(&self.denom).ok().map(|d| d.decimals())
+    }
+
+    /// Auctions of the token, newest first. `first` defaults to 100, at most 1000.
+    async fn auctions(
+        &self,
+        status: Option<AuctionStatus>,
+        first: Option<usize>,
+        offset: Option<usize>,
+    ) -> Vec<Auction> {
+        let query = AuctionQuery {
+            status,
+            token: Some(self.denom.clone()),
+            limit: first,
+            offset,
+        };
+
+        api::auctions(&self.snapshot, &query)
+            .into_iter()
+            .map(|view| Auction {
+                view,
+                snapshot: self.snapshot.clone(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sommelier_auction::{
+        auction::Auction as ChainAuction, cosmos_sdk_proto::cosmos::base::v1beta1::Coin, BidResult,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_schema() {
+        let usdc = Denom::USDC.to_string();
+        let coin = |amount: &str| {
+            Some(Coin {
+                denom: usdc.clone(),
+                amount: amount.to_string(),
+            })
+        };
+        let mut snapshot = Snapshot::default();
+        for (id, status) in [(1, AuctionStatus::Ended), (2, AuctionStatus::Active)] {
+            let auction = ChainAuction {
+                id,
+                starting_tokens_for_sale: coin("1000"),
+                ..Default::default()
+            };
+            snapshot
+                .auctions
+                .insert(id, AuctionView::new(&auction, status));
+            let bid = BidResult {
+                id: 1,
+                auction_id: id,
+                bidder: format!("somm{id}"),
+                total_fulfilled_sale_tokens: coin("10"),
+                block_height: id as u64,
+                ..Default::default()
+            };
+            snapshot.bids.insert(id, vec![BidView::from(&bid)]);
+        }
+        let store = Store::default();
+        store.replace(snapshot);
+
+        let response = schema(store)
+            .execute(
+                r#"{
+                    auctions(status: ACTIVE) { id token { symbol decimals } bids { bidder } }
+                    bids(first: 1) { blockHeight auction { id status } }
+                    tokens { auctions { id } }
+                }"#,
+            )
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(
+            response.data.into_json().unwrap(),
+            serde_json::json!({
+                "auctions": [{
+                    "id": 2,
+                    "token": { "symbol": "USDC", "decimals": 6 },
+                    "bids": [{ "bidder": "somm2" }],
+                }],
+                "bids": [{ "blockHeight": 2, "auction": { "id": 2, "status": "ACTIVE" } }],
+                "tokens": [{ "auctions": [{ "id": 2 }, { "id": 1 }] }],
+            })
+        );
+    }
+}
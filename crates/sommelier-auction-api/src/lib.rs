@@ -0,0 +1,10 @@
+//! A read-only JSON API over Sommelier's fee auctions and their bids, for UIs built on this bot's
+//! view of the chain.
+//!
+//! Auctions and bids are kept in memory and refreshed from chain on an interval, so requests never
+//! wait on a node. The OpenAPI document for the routes is served at `/v1/openapi.json`, and the
+//! same data can be queried with GraphQL at `/v1/graphql`.
+
+pub mod api;
+pub mod graphql;
+pub mod store;
@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use clap::Parser;
+use sommelier_auction::client::{Client, DEFAULT_GRPC_ENDPOINT, DEFAULT_RPC_ENDPOINT};
+use sommelier_auction_api::{api, store::Store};
+use sommelier_auction_metrics::Registry;
+use tracing::{error, Level};
+
+/// Serves auctions and bids from a Sommelier node as a JSON API
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen_addr: String,
+    #[arg(long, default_value = DEFAULT_RPC_ENDPOINT)]
+    rpc_endpoint: String,
+    #[arg(long, default_value = DEFAULT_GRPC_ENDPOINT)]
+    grpc_endpoint: String,
+    /// Seconds between refreshes of auctions and bids from chain
+    #[arg(long, default_value_t = 30)]
+    refresh_interval_secs: u64,
+    /// Serve Prometheus metrics at /metrics on this address
+    #[arg(long)]
+    metrics_listen_addr: Option<String>,
+    #[arg(long, default_value_t = Level::INFO)]
+    log_level: Level,
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    tracing_subscriber::fmt()
+        .with_max_level(args.log_level)
+        .init();
+
+    let client = match Client::with_endpoints(args.rpc_endpoint, args.grpc_endpoint).await {
+        Ok(client) => client,
+        Err(err) => {
+            error!("failed to connect to the node: {err}");
+            std::process::exit(1);
+        }
+    };
+    let registry = Registry::default();
+    let store = Store::default().with_metrics(&registry);
+    if let Some(listen_addr) = args.metrics_listen_addr {
+        tokio::spawn(async move {
+            if let Err(err) = sommelier_auction_metrics::serve(listen_addr, registry).await {
+                error!("metrics server returned an error: {err:?}");
+            }
+        });
+    }
+    tokio::spawn(store.clone().refresh_every(
+        client,
+        Duration::from_secs(args.refresh_interval_secs.max(1)),
+    ));
+
+    if let Err(err) = api::serve(args.listen_addr, store).await {
+        error!("api server returned an error: {err}");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,420 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use sommelier_auction::{
+    auction::Auction, client::Client, cosmos_sdk_proto::cosmos::base::v1beta1::Coin, denom::Denom,
+    BidResult,
+};
+use sommelier_auction_metrics::{Gauge, Registry, ServiceMetrics};
+use tracing::{debug, warn};
+use utoipa::ToSchema;
+
+/// Whether an auction is still selling
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, async_graphql::Enum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum AuctionStatus {
+    Active,
+    Ended,
+}
+
+/// An auction as served by the API. Token and usomm amounts are integer strings, since they may
+/// not fit in a JSON number.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AuctionView {
+    pub id: u32,
+    pub status: AuctionStatus,
+    /// Denom of the token being sold
+    pub denom: String,
+    /// Symbol of the token, if it's one this bot knows
+    pub symbol: Option<String>,
+    pub start_block: u64,
+    pub end_block: u64,
+    pub starting_tokens_for_sale: String,
+    pub remaining_tokens_for_sale: String,
+    /// Price in usomm per base unit of the token when the auction started, as a decimal string
+    pub initial_unit_price_in_usomm: String,
+    /// Price in usomm per base unit of the token now, as a decimal string
+    pub current_unit_price_in_usomm: String,
+    pub current_price_decrease_rate: String,
+    pub price_decrease_block_interval: u64,
+}
+
+impl AuctionView {
+    pub fn new(auction: &Auction, status: AuctionStatus) -> Self {
+        let denom = auction
+            .starting_tokens_for_sale
+            .as_ref()
+            .map(|c| c.denom.clone())
+            .unwrap_or_default();
+
+        Self {
+            id: auction.id,
+            status,
+            symbol: Denom::from_str(&denom).ok().map(|d| d.symbol()),
+            denom,
+            start_block: auction.start_block,
+            end_block: auction.end_block,
+            starting_tokens_for_sale: amount(&auction.starting_tokens_for_sale),
+            remaining_tokens_for_sale: amount(&auction.remaining_tokens_for_sale),
+            initial_unit_price_in_usomm: auction.initial_unit_price_in_usomm.clone(),
+            current_unit_price_in_usomm: auction.current_unit_price_in_usomm.clone(),
+            current_price_decrease_rate: auction.current_price_decrease_rate.clone(),
+            price_decrease_block_interval: auction.price_decrease_block_interval,
+        }
+    }
+}
+
+/// A bid as served by the API. Token and usomm amounts are integer strings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BidView {
+    pub id: u64,
+    pub auction_id: u32,
+    pub bidder: String,
+    pub max_bid_in_usomm: String,
+    pub sale_token_minimum_amount: String,
+    pub total_fulfilled_sale_tokens: String,
+    /// Price the bid was fulfilled at in usomm per base unit of the token, as a decimal string
+    pub sale_token_unit_price_in_usomm: String,
+    pub total_usomm_paid: String,
+    pub block_height: u64,
+}
+
+impl From<&BidResult> for BidView {
+    fn from(bid: &BidResult) -> Self {
+        Self {
+            id: bid.id,
+            auction_id: bid.auction_id,
+            bidder: bid.bidder.clone(),
+            max_bid_in_usomm: amount(&bid.max_bid_in_usomm),
+            sale_token_minimum_amount: amount(&bid.sale_token_minimum_amount),
+            total_fulfilled_sale_tokens: amount(&bid.total_fulfilled_sale_tokens),
+            sale_token_unit_price_in_usomm: bid.sale_token_unit_price_in_usomm.clone(),
+            total_usomm_paid: amount(&bid.total_usomm_paid),
+            block_height: bid.block_height,
+        }
+    }
+}
+
+/// Totals over every auction the API knows of
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Stats {
+    pub auctions: usize,
+    pub active_auctions: usize,
+    pub bids: usize,
+    /// usomm paid by every bid
+    pub usomm_paid: String,
+    /// Totals for each token sold, by denom
+    pub tokens: Vec<TokenStats>,
+    /// Block height of the last refresh from chain
+    pub height: u64,
+    /// Unix time of the last refresh from chain
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TokenStats {
+    pub denom: String,
+    pub symbol: Option<String>,
+    pub auctions: usize,
+    pub bids: usize,
+    pub usomm_paid: String,
+    /// Base units of the token bought by every bid
+    pub tokens_sold: String,
+}
+
+/// Every auction the API knows of and their bids, as of the last refresh
+#[derive(Debug, Default, Clone)]
+pub struct Snapshot {
+    pub auctions: BTreeMap<u32, AuctionView>,
+    /// Bids of each auction, oldest first
+    pub bids: HashMap<u32, Vec<BidView>>,
+    pub height: u64,
+    pub updated_at: u64,
+    // ended auctions whose final bids have been fetched, so they aren't fetched again
+    archived: HashSet<u32>,
+}
+
+impl Snapshot {
+    pub fn stats(&self) -> Stats {
+        let mut tokens = BTreeMap::<String, (TokenStats, u128, u128)>::new();
+        let mut usomm_paid = 0u128;
+        for auction in self.auctions.values() {
+            let bids = self.bids.get(&auction.id).map_or(&[][..], |b| b.as_slice());
+            let (stats, paid, sold) = tokens.entry(auction.denom.clone()).or_insert_with(|| {
+                (
+                    TokenStats {
+                        denom: auction.denom.clone(),
+                        symbol: auction.symbol.clone(),
+                        ..Default::default()
+                    },
+                    0,
+                    0,
+                )
+            });
+            stats.auctions += 1;
+            stats.bids += bids.len();
+            for bid in bids {
+                let bid_paid = bid.total_usomm_paid.parse::<u128>().unwrap_or_default();
+                *paid += bid_paid;
+                *sold += bid
+                    .total_fulfilled_sale_tokens
+                    .parse::<u128>()
+                    .unwrap_or_default();
+                usomm_paid += bid_paid;
+            }
+        }
+
+        Stats {
+            auctions: self.auctions.len(),
+            active_auctions: self
+                .auctions
+                .values()
+                .filter(|a| a.status == AuctionStatus::Active)
+                .count(),
+            bids: self.bids.values().map(Vec::len).sum(),
+            usomm_paid: usomm_paid.to_string(),
+            tokens: tokens
+                .into_values()
+                .map(|(stats, paid, sold)| TokenStats {
+                    usomm_paid: paid.to_string(),
+                    tokens_sold: sold.to_string(),
+                    ..stats
+                })
+                .collect(),
+            height: self.height,
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+/// Prefix of every API metric
+pub const METRICS_PREFIX: &str = "auction_api";
+
+#[derive(Debug, Default, Clone)]
+struct StoreMetrics {
+    service: ServiceMetrics,
+    auctions: Gauge,
+    bids: Gauge,
+}
+
+/// The latest [`Snapshot`], shared between the refresher and the API handlers
+#[derive(Debug, Default, Clone)]
+pub struct Store {
+    snapshot: Arc<RwLock<Arc<Snapshot>>>,
+    metrics: StoreMetrics,
+}
+
+impl Store {
+    /// Reports chain queries and the number of auctions and bids stored in `registry`
+    pub fn with_metrics(mut self, registry: &Registry) -> Self {
+        self.metrics = StoreMetrics {
+            service: ServiceMetrics::register(registry, METRICS_PREFIX),
+            auctions: registry.gauge("auction_api_auctions", "Auctions stored"),
+            bids: registry.gauge("auction_api_bids", "Bids stored"),
+        };
+
+        self
+    }
+
+    pub fn snapshot(&self) -> Arc<Snapshot> {
+        self.snapshot
+            .read()
+            .map(|s| s.clone())
+            .unwrap_or_else(|poisoned| poisoned.into_inner().clone())
+    }
+
+    pub(crate) fn replace(&self, snapshot: Snapshot) {
+        let mut current = self
+            .snapshot
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *current = Arc::new(snapshot);
+    }
+
+    /// Refreshes the store from chain every `interval` until the task is aborted. Bids of ended
+    /// auctions can't change, so they're fetched once and kept; bids of active auctions are
+    /// fetched on every refresh. An auction whose bids fail to fetch keeps the bids it had and is
+    /// fetched again on the next refresh. If the auctions themselves can't be listed the refresh
+    /// fails, which is logged and leaves the last snapshot in place.
+    pub async fn refresh_every(self, mut client: Client, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match self.refresh(&mut client).await {
+                Ok(()) => debug!("refreshed auctions from chain"),
+                Err(err) => warn!("failed to refresh auctions: {err:?}"),
+            }
+        }
+    }
+
+    async fn refresh(&self, client: &mut Client) -> Result<()> {
+        let previous = self.snapshot();
+        let height = self.counted(client.latest_block_height().await)?;
+        self.metrics.service.chain_height.set(height);
+        let active = self.counted(client.active_auctions().await)?;
+        let ended = self.counted(client.ended_auctions().await)?;
+
+        let mut snapshot = Snapshot {
+            height,
+            updated_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+        for auction in &ended {
+            snapshot
+                .auctions
+                .insert(auction.id, AuctionView::new(auction, AuctionStatus::Ended));
+            let archived = previous
+                .bids
+                .get(&auction.id)
+                .filter(|_| previous.archived.contains(&auction.id));
+            if let Some(bids) = archived {
+                snapshot.bids.insert(auction.id, bids.clone());
+                snapshot.archived.insert(auction.id);
+            } else if self
+                .refresh_bids(client, auction.id, &previous, &mut snapshot)
+                .await
+            {
+                snapshot.archived.insert(auction.id);
+            }
+        }
+        for auction in &active {
+            snapshot
+                .auctions
+                .insert(auction.id, AuctionView::new(auction, AuctionStatus::Active));
+            self.refresh_bids(client, auction.id, &previous, &mut snapshot)
+                .await;
+        }
+        self.metrics.auctions.set(snapshot.auctions.len() as u64);
+        self.metrics
+            .bids
+            .set(snapshot.bids.values().map(Vec::len).sum::<usize>() as u64);
+        self.replace(snapshot);
+
+        Ok(())
+    }
+
+    // Fetches an auction's bids into `snapshot`, returning whether it succeeded. On failure the
+    // auction keeps the bids it had in `previous`, if any.
+    async fn refresh_bids(
+        &self,
+        client: &mut Client,
+        auction_id: u32,
+        previous: &Snapshot,
+        snapshot: &mut Snapshot,
+    ) -> bool {
+        match self.counted(client.auction_bids(auction_id).await) {
+            Ok(bids) => {
+                snapshot.bids.insert(auction_id, views(&bids));
+
+                true
+            }
+            Err(err) => {
+                warn!(
+                    "failed to fetch bids of auction {auction_id}, retrying on the next refresh: \
+                     {err:?}"
+                );
+                if let Some(bids) = previous.bids.get(&auction_id) {
+                    snapshot.bids.insert(auction_id, bids.clone());
+                }
+
+                false
+            }
+        }
+    }
+
+    fn counted<T>(&self, result: Result<T>) -> Result<T> {
+        self.metrics.service.record_query(&result);
+
+        result
+    }
+}
+
+fn views(bids: &[BidResult]) -> Vec<BidView> {
+    let mut views = bids.iter().map(BidView::from).collect::<Vec<BidView>>();
+    views.sort_by_key(|b| (b.block_height, b.id));
+
+    views
+}
+
+fn amount(coin: &Option<Coin>) -> String {
+    coin.as_ref()
+        .map(|c| c.amount.clone())
+        .unwrap_or_else(|| "0".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coin(denom: &str, amount: &str) -> Option<Coin> {
+        Some(Coin {
+            denom: denom.to_string(),
+            amount: amount.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_stats() {
+        let usdc = Denom::USDC.to_string();
+        let auction = |id, status| {
+            AuctionView::new(
+                &Auction {
+                    id,
+                    starting_tokens_for_sale: coin(&usdc, "1000"),
+                    ..Default::default()
+                },
+                status,
+            )
+        };
+        let bid = |id, auction_id, paid: &str, sold: &str| {
+            BidView::from(&BidResult {
+                id,
+                auction_id,
+                total_usomm_paid: coin("usomm", paid),
+                total_fulfilled_sale_tokens: coin(&usdc, sold),
+                ..Default::default()
+            })
+        };
+        let snapshot = Snapshot {
+            auctions: BTreeMap::from([
+                (1, auction(1, AuctionStatus::Ended)),
+                (2, auction(2, AuctionStatus::Active)),
+            ]),
+            bids: HashMap::from([
+                (1, vec![bid(1, 1, "500", "100"), bid(2, 1, "250", "40")]),
+                (2, vec![bid(3, 2, "100", "10")]),
+            ]),
+            height: 10,
+            updated_at: 20,
+            ..Default::default()
+        };
+
+        let stats = snapshot.stats();
+        assert_eq!(stats.auctions, 2);
+        assert_eq!(stats.active_auctions, 1);
+        assert_eq!(stats.bids, 3);
+        assert_eq!(stats.usomm_paid, "850");
+        assert_eq!(
+            stats.tokens,
+            vec![TokenStats {
+                denom: usdc.clone(),
+                symbol: Some("USDC".to_string()),
+                auctions: 2,
+                bids: 3,
+                usomm_paid: "850".to_string(),
+                tokens_sold: "150".to_string(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,212 @@
+//! Prometheus metrics shared by the services in this workspace.
+//!
+//! Each service registers its metrics in a [`Registry`], which renders them in the Prometheus text
+//! exposition format and can be served at `/metrics` with [`serve`]. [`ServiceMetrics`] defines
+//! the metrics every service reports, so they're named and described the same way everywhere.
+
+use std::{
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{extract::State, routing::get, Router};
+use eyre::Result;
+use tracing::info;
+
+/// A count that only goes up
+#[derive(Debug, Default, Clone)]
+pub struct Counter(Arc<AtomicU64>);
+
+impl Counter {
+    pub fn inc(&self) {
+        self.inc_by(1);
+    }
+
+    pub fn inc_by(&self, n: u64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A value that can go up and down
+#[derive(Debug, Default, Clone)]
+pub struct Gauge(Arc<AtomicU64>);
+
+impl Gauge {
+    pub fn set(&self, value: u64) {
+        self.0.store(value, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug)]
+struct Metric {
+    name: String,
+    help: String,
+    kind: &'static str,
+    value: Arc<AtomicU64>,
+}
+
+/// The metrics a service reports, in the order they were registered. Clones share the same
+/// metrics.
+#[derive(Debug, Default, Clone)]
+pub struct Registry {
+    metrics: Arc<RwLock<Vec<Metric>>>,
+}
+
+impl Registry {
+    /// Registers a counter, or returns the one already registered under `name`
+    pub fn counter(&self, name: &str, help: &str) -> Counter {
+        Counter(self.register(name, help, "counter"))
+    }
+
+    /// Registers a gauge, or returns the one already registered under `name`
+    pub fn gauge(&self, name: &str, help: &str) -> Gauge {
+        Gauge(self.register(name, help, "gauge"))
+    }
+
+    fn register(&self, name: &str, help: &str, kind: &'static str) -> Arc<AtomicU64> {
+        let mut metrics = self
+            .metrics
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(metric) = metrics.iter().find(|m| m.name == name) {
+            return metric.value.clone();
+        }
+
+        let value = Arc::new(AtomicU64::new(0));
+        metrics.push(Metric {
+            name: name.to_string(),
+            help: help.to_string(),
+            kind,
+            value: value.clone(),
+        });
+
+        value
+    }
+
+    /// Renders every metric in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let metrics = self
+            .metrics
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut output = String::new();
+        for metric in metrics.iter() {
+            let name = &metric.name;
+            let _ = writeln!(output, "# HELP {name} {}", metric.help);
+            let _ = writeln!(output, "# TYPE {name} {}", metric.kind);
+            let _ = writeln!(output, "{name} {}", metric.value.load(Ordering::Relaxed));
+        }
+
+        output
+    }
+}
+
+/// Metrics every service reports, named with the service's prefix, e.g. `auction_engine`
+#[derive(Debug, Default, Clone)]
+pub struct ServiceMetrics {
+    pub started_at: Gauge,
+    pub chain_queries: Counter,
+    pub chain_query_failures: Counter,
+    pub chain_height: Gauge,
+}
+
+impl ServiceMetrics {
+    /// Registers the metrics under `prefix` and records the service's start time
+    pub fn register(registry: &Registry, prefix: &str) -> Self {
+        let metrics = Self {
+            started_at: registry.gauge(
+                &format!("{prefix}_started_at_seconds"),
+                "Unix time the service started",
+            ),
+            chain_queries: registry.counter(
+                &format!("{prefix}_chain_queries_total"),
+                "Queries made to a Sommelier node",
+            ),
+            chain_query_failures: registry.counter(
+                &format!("{prefix}_chain_query_failures_total"),
+                "Queries to a Sommelier node that failed or timed out",
+            ),
+            chain_height: registry.gauge(
+                &format!("{prefix}_chain_height"),
+                "Latest block height seen on chain",
+            ),
+        };
+        metrics.started_at.set(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        );
+
+        metrics
+    }
+
+    /// Counts a chain query and whether it failed
+    pub fn record_query<T, E>(&self, result: &std::result::Result<T, E>) {
+        self.chain_queries.inc();
+        if result.is_err() {
+            self.chain_query_failures.inc();
+        }
+    }
+}
+
+/// Serves `registry` at `/metrics` on `listen_addr` until the task is aborted
+pub async fn serve(listen_addr: String, registry: Registry) -> Result<()> {
+    let app = Router::new()
+        .route("/metrics", get(render))
+        .with_state(registry);
+
+    let listener = tokio::net::TcpListener::bind(&listen_addr).await?;
+    info!("metrics listening on {listen_addr}");
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+async fn render(State(registry): State<Registry>) -> String {
+    registry.render()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let registry = Registry::default();
+        let bids = registry.counter("test_bids_total", "Bids made");
+        let auctions = registry.gauge("test_auctions", "Active auctions");
+        bids.inc_by(3);
+        auctions.set(2);
+        registry.counter("test_bids_total", "Bids made").inc();
+
+        assert_eq!(
+            registry.render(),
+            "# HELP test_bids_total Bids made\n\
+             # TYPE test_bids_total counter\n\
+             test_bids_total 4\n\
+             # HELP test_auctions Active auctions\n\
+             # TYPE test_auctions gauge\n\
+             test_auctions 2\n"
+        );
+
+        let service = ServiceMetrics::register(&registry, "test");
+        service.record_query(&Ok::<(), ()>(()));
+        service.record_query(&Err::<(), ()>(()));
+        assert_eq!(service.chain_queries.get(), 2);
+        assert_eq!(service.chain_query_failures.get(), 1);
+        assert!(service.started_at.get() > 0);
+    }
+}
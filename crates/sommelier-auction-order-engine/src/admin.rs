@@ -0,0 +1,429 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use sommelier_auction::{auction::Auction, denom::Denom};
+use tokio::sync::{mpsc::UnboundedSender, oneshot};
+use tracing::info;
+
+use crate::{
+    config,
+    decision::Decision,
+    order::Order,
+    pnl::{self, Pnl, PnlReport},
+    reload::OrderChanges,
+    util::constant_time_eq,
+};
+
+/// A request from the admin API to the watcher, which owns the orders
+#[derive(Debug)]
+pub enum AdminCommand {
+    /// Creates an order, or replaces the order with the given ID. Replies with the order as stored,
+    /// or `None` if there is no order with the given ID.
+    UpsertOrder {
+        id: Option<usize>,
+        order: Order,
+        reply: oneshot::Sender<Option<Order>>,
+    },
+    /// Removes an order. Replies with whether the order existed.
+    CancelOrder {
+        id: usize,
+        reply: oneshot::Sender<bool>,
+    },
+    /// Stops evaluating orders until resumed
+    Pause,
+    /// Resumes evaluating orders
+    Resume,
+    /// Applies changes to the orders in the config file all at once. Replies with the IDs given to
+    /// the added orders, in order.
+    ReloadOrders {
+        changes: OrderChanges,
+        reply: oneshot::Sender<Vec<usize>>,
+    },
+}
+
+/// The current state of the engine as published by the watcher
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct EngineState {
+    pub paused: bool,
+    pub orders: Vec<Order>,
+    /// IDs of orders with a bid in flight
+    pub pending_orders: Vec<usize>,
+    pub active_auctions: Vec<AuctionSummary>,
+    /// USD price of one whole token for each denom
+    pub prices: HashMap<Denom, f64>,
+    /// Unix time of the last completed evaluation pass
+    pub last_evaluation: Option<u64>,
+    /// The most recent order decisions, oldest first
+    pub decisions: Vec<Decision>,
+}
+
+/// Filters for the decisions route
+#[derive(Debug, Default, Deserialize)]
+pub struct DecisionQuery {
+    pub order_id: Option<usize>,
+    pub auction_id: Option<u32>,
+}
+
+/// The parts of an auction relevant to order evaluation
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct AuctionSummary {
+    pub id: u32,
+    pub denom: String,
+    pub end_block: u64,
+    pub current_unit_price_in_usomm: String,
+    pub remaining_tokens_for_sale: String,
+}
+
+impl From<&Auction> for AuctionSummary {
+    fn from(auction: &Auction) -> Self {
+        Self {
+            id: auction.id,
+            denom: auction
+                .starting_tokens_for_sale
+                .as_ref()
+                .map(|c| c.denom.clone())
+                .unwrap_or_default(),
+            end_block: auction.end_block,
+            current_unit_price_in_usomm: auction.current_unit_price_in_usomm.clone(),
+            remaining_tokens_for_sale: auction
+                .remaining_tokens_for_sale
+                .as_ref()
+                .map(|c| c.amount.clone())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// The admin API's view of the engine: a command channel into the watcher and the state it
+/// publishes
+#[derive(Debug, Clone)]
+pub struct AdminHandle {
+    pub commands: UnboundedSender<AdminCommand>,
+    pub state: Arc<RwLock<EngineState>>,
+    /// Fills recorded by the engine, for PnL reports
+    pub pnl: Arc<RwLock<Pnl>>,
+    /// Names of the configured wallets and strategies, which orders may refer to
+    pub wallets: Vec<String>,
+    pub strategies: Vec<String>,
+}
+
+impl AdminHandle {
+    /// Returns the state last published by the watcher
+    pub fn state(&self) -> EngineState {
+        self.state
+            .read()
+            .map(|s| s.clone())
+            .unwrap_or_else(|poisoned| poisoned.into_inner().clone())
+    }
+
+    /// Reports PnL by order and by wallet at the latest prices
+    pub fn pnl(&self) -> PnlReport {
+        pnl::report(&self.pnl)
+    }
+
+    /// Every problem with an order that would keep it from bidding, as checked for configured
+    /// orders
+    pub fn order_problems(&self, order: &Order) -> Vec<String> {
+        config::order_problems(order, &self.wallets, &self.strategies)
+    }
+
+    /// Creates an order, or replaces the order with the given ID. Returns the order as stored, or
+    /// `None` if there is no order with the given ID.
+    pub async fn upsert_order(&self, id: Option<usize>, order: Order) -> Result<Option<Order>> {
+        let (reply, response) = oneshot::channel();
+        self.send(AdminCommand::UpsertOrder { id, order, reply })?;
+
+        response
+            .await
+            .map_err(|_| eyre!("the watcher stopped before handling the request"))
+    }
+
+    /// Removes an order. Returns whether the order existed.
+    pub async fn cancel_order(&self, id: usize) -> Result<bool> {
+        let (reply, response) = oneshot::channel();
+        self.send(AdminCommand::CancelOrder { id, reply })?;
+
+        response
+            .await
+            .map_err(|_| eyre!("the watcher stopped before handling the request"))
+    }
+
+    pub fn pause(&self) -> Result<()> {
+        self.send(AdminCommand::Pause)
+    }
+
+    pub fn resume(&self) -> Result<()> {
+        self.send(AdminCommand::Resume)
+    }
+
+    fn send(&self, command: AdminCommand) -> Result<()> {
+        self.commands
+            .send(command)
+            .map_err(|_| eyre!("the watcher is not running"))
+    }
+}
+
+type ApiResult<T> = std::result::Result<Json<T>, (StatusCode, String)>;
+
+/// Serves the REST admin API on `listen_addr` until the task is aborted. If `token` is set, every
+/// request must carry it as a bearer token.
+pub async fn serve(listen_addr: String, handle: AdminHandle, token: Option<String>) -> Result<()> {
+    let app = Router::new()
+        .route("/status", get(status))
+        .route("/orders", get(list_orders).post(create_order))
+        .route(
+            "/orders/:id",
+            get(get_order).put(update_order).delete(cancel_order),
+        )
+        .route("/decisions", get(decisions))
+        .route("/pnl", get(pnl_report))
+        .route("/pause", post(pause))
+        .route("/resume", post(resume))
+        .layer(middleware::from_fn_with_state(token, authorize))
+        .with_state(handle);
+
+    let listener = tokio::net::TcpListener::bind(&listen_addr).await?;
+    info!("admin api listening on {listen_addr}");
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+/// Refuses to serve an admin API on `listen_addr` without a token unless only this machine can
+/// reach it
+pub fn check_listen_addr(listen_addr: &str, token: Option<&str>) -> Result<()> {
+    let host = listen_addr
+        .rsplit_once(':')
+        .map_or(listen_addr, |(host, _)| host)
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    let loopback = host == "localhost"
+        || host
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback());
+    if token.is_none() && !loopback {
+        return Err(eyre!(
+            "refusing to serve the admin api on {listen_addr} without a token. set admin_token_env \
+             or listen on a loopback address."
+        ));
+    }
+
+    Ok(())
+}
+
+/// Whether an `Authorization` header value carries `token` as a bearer token. Always true if no
+/// token is set.
+pub fn authorized(token: Option<&str>, authorization: Option<&str>) -> bool {
+    match token {
+        Some(token) => authorization
+            .and_then(|auth| auth.strip_prefix("Bearer "))
+            .is_some_and(|given| constant_time_eq(given.trim().as_bytes(), token.as_bytes())),
+        None => true,
+    }
+}
+
+async fn authorize(State(token): State<Option<String>>, request: Request, next: Next) -> Response {
+    if let Err(rejection) = check_request(token.as_deref(), request.headers()) {
+        return rejection.into_response();
+    }
+
+    next.run(request).await
+}
+
+/// Checks a request carries the admin token, if one is set. Requests from web pages, which have an
+/// `Origin` header, are refused: the API is for scripts and tools, and a page mustn't be able to
+/// drive it through an operator's browser.
+pub fn check_request(
+    token: Option<&str>,
+    headers: &HeaderMap,
+) -> std::result::Result<(), (StatusCode, String)> {
+    let authorization = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok());
+    if !authorized(token, authorization) {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            "the admin token is required".to_string(),
+        ));
+    }
+    if headers.contains_key(header::ORIGIN) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "requests from web pages are refused".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn unavailable(err: eyre::Report) -> (StatusCode, String) {
+    (StatusCode::SERVICE_UNAVAILABLE, err.to_string())
+}
+
+fn invalid(problems: Vec<String>) -> (StatusCode, String) {
+    (StatusCode::BAD_REQUEST, problems.join("; "))
+}
+
+fn not_found(id: usize) -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, format!("no order with id {id}"))
+}
+
+async fn status(State(handle): State<AdminHandle>) -> Json<EngineState> {
+    Json(handle.state())
+}
+
+async fn list_orders(State(handle): State<AdminHandle>) -> Json<Vec<Order>> {
+    Json(handle.state().orders)
+}
+
+async fn get_order(State(handle): State<AdminHandle>, Path(id): Path<usize>) -> ApiResult<Order> {
+    handle
+        .state()
+        .orders
+        .into_iter()
+        .find(|o| o.id == id)
+        .map(Json)
+        .ok_or_else(|| not_found(id))
+}
+
+async fn create_order(
+    State(handle): State<AdminHandle>,
+    Json(order): Json<Order>,
+) -> ApiResult<Order> {
+    let problems = handle.order_problems(&order);
+    if !problems.is_empty() {
+        return Err(invalid(problems));
+    }
+
+    handle
+        .upsert_order(None, order)
+        .await
+        .map_err(unavailable)?
+        .map(Json)
+        .ok_or_else(|| unavailable(eyre!("the watcher did not create the order")))
+}
+
+async fn update_order(
+    State(handle): State<AdminHandle>,
+    Path(id): Path<usize>,
+    Json(order): Json<Order>,
+) -> ApiResult<Order> {
+    let problems = handle.order_problems(&order);
+    if !problems.is_empty() {
+        return Err(invalid(problems));
+    }
+
+    handle
+        .upsert_order(Some(id), order)
+        .await
+        .map_err(unavailable)?
+        .map(Json)
+        .ok_or_else(|| not_found(id))
+}
+
+async fn cancel_order(
+    State(handle): State<AdminHandle>,
+    Path(id): Path<usize>,
+) -> ApiResult<usize> {
+    if handle.cancel_order(id).await.map_err(unavailable)? {
+        Ok(Json(id))
+    } else {
+        Err(not_found(id))
+    }
+}
+
+async fn decisions(
+    State(handle): State<AdminHandle>,
+    Query(query): Query<DecisionQuery>,
+) -> Json<Vec<Decision>> {
+    Json(
+        handle
+            .state()
+            .decisions
+            .into_iter()
+            .filter(|d| query.order_id.is_none() || query.order_id == Some(d.order_id))
+            .filter(|d| query.auction_id.is_none() || query.auction_id == Some(d.auction_id))
+            .collect(),
+    )
+}
+
+async fn pnl_report(State(handle): State<AdminHandle>) -> Json<PnlReport> {
+    Json(handle.pnl())
+}
+
+async fn pause(State(handle): State<AdminHandle>) -> ApiResult<bool> {
+    handle.pause().map_err(unavailable)?;
+
+    Ok(Json(true))
+}
+
+async fn resume(State(handle): State<AdminHandle>) -> ApiResult<bool> {
+    handle.resume().map_err(unavailable)?;
+
+    Ok(Json(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_listen_addr() {
+        assert!(check_listen_addr("127.0.0.1:9091", None).is_ok());
+        assert!(check_listen_addr("localhost:9091", None).is_ok());
+        assert!(check_listen_addr("[::1]:9091", None).is_ok());
+        assert!(check_listen_addr("0.0.0.0:9091", None).is_err());
+        assert!(check_listen_addr("0.0.0.0:9091", Some("secret")).is_ok());
+    }
+
+    #[test]
+    fn test_check_request() {
+        let headers = |pairs: &[(header::HeaderName, &str)]| {
+            pairs
+                .iter()
+                .map(|(name, value)| (name.clone(), value.parse().unwrap()))
+                .collect::<HeaderMap>()
+        };
+        let status = |token, headers: HeaderMap| check_request(token, &headers).err().map(|r| r.0);
+        let bearer = (header::AUTHORIZATION, "Bearer secret");
+
+        assert_eq!(status(Some("secret"), headers(&[bearer.clone()])), None);
+        assert_eq!(
+            status(Some("secret"), headers(&[])),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            status(
+                Some("secret"),
+                headers(&[(header::AUTHORIZATION, "Bearer wrong")])
+            ),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(status(None, headers(&[])), None);
+
+        // a form posted from another site carries its origin
+        assert_eq!(
+            status(
+                Some("secret"),
+                headers(&[bearer, (header::ORIGIN, "https://example.com")])
+            ),
+            Some(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            status(None, headers(&[(header::ORIGIN, "https://example.com")])),
+            Some(StatusCode::FORBIDDEN)
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use eyre::Result;
+use sommelier_auction::{denom::Denom, fee::TxFee};
+use sommelier_auction_proto::admin::{
+    self as proto,
+    admin_server::{Admin, AdminServer},
+};
+use tonic::{Request, Response, Status};
+use tracing::info;
+
+use crate::{
+    admin::{self, AdminHandle, AuctionSummary},
+    order::{Compete, Dca, GroupMember, Order, Snipe, Split, StopConditions},
+};
+
+/// The gRPC admin service. Commands go to the watcher through the same handle as the REST API.
+pub struct AdminService {
+    handle: AdminHandle,
+}
+
+/// Serves the gRPC admin API on `listen_addr` until the task is aborted. If `token` is set, every
+/// call must carry it as a bearer token in its `authorization` metadata, as with the REST API.
+pub async fn serve(listen_addr: String, handle: AdminHandle, token: Option<String>) -> Result<()> {
+    let addr = listen_addr.parse::<std::net::SocketAddr>()?;
+    info!("admin grpc api listening on {listen_addr}");
+    let service =
+        AdminServer::with_interceptor(AdminService { handle }, move |request: Request<()>| {
+            authorize(token.as_deref(), request)
+        });
+    tonic::transport::Server::builder()
+        .add_service(service)
+        .serve(addr)
+        .await?;
+
+    Ok(())
+}
+
+fn authorize(token: Option<&str>, request: Request<()>) -> Result<Request<()>, Status> {
+    let authorization = request
+        .metadata()
+        .get("authorization")
+        .and_then(|v| v.to_str().ok());
+    if !admin::authorized(token, authorization) {
+        return Err(Status::unauthenticated("the admin token is required"));
+    }
+
+    Ok(request)
+}
+
+#[tonic::async_trait]
+impl Admin for AdminService {
+    async fn list_orders(
+        &self,
+        _request: Request<proto::ListOrdersRequest>,
+    ) -> Result<Response<proto::ListOrdersResponse>, Status> {
+        let orders = self.handle.state().orders.iter().map(to_proto).collect();
+
+        Ok(Response::new(proto::ListOrdersResponse { orders }))
+    }
+
+    async fn upsert_order(
+        &self,
+        request: Request<proto::UpsertOrderRequest>,
+    ) -> Result<Response<proto::UpsertOrderResponse>, Status> {
+        let request = request.into_inner();
+        let order = request
+            .order
+            .ok_or_else(|| Status::invalid_argument("missing order"))
+            .and_then(from_proto)?;
+        let problems = self.handle.order_problems(&order);
+        if !problems.is_empty() {
+            return Err(Status::invalid_argument(problems.join("; ")));
+        }
+        let id = request.id.map(|id| id as usize);
+
+        match self.handle.upsert_order(id, order).await {
+            Ok(Some(order)) => Ok(Response::new(proto::UpsertOrderResponse {
+                order: Some(to_proto(&order)),
+            })),
+            Ok(None) => Err(Status::not_found(format!(
+                "no order with id {}",
+                id.unwrap_or_default()
+            ))),
+            Err(err) => Err(Status::unavailable(err.to_string())),
+        }
+    }
+
+    async fn cancel_order(
+        &self,
+        request: Request<proto::CancelOrderRequest>,
+    ) -> Result<Response<proto::CancelOrderResponse>, Status> {
+        let id = request.into_inner().id;
+        match self.handle.cancel_order(id as usize).await {
+            Ok(true) => Ok(Response::new(proto::CancelOrderResponse {})),
+            Ok(false) => Err(Status::not_found(format!("no order with id {id}"))),
+            Err(err) => Err(Status::unavailable(err.to_string())),
+        }
+    }
+
+    async fn engine_status(
+        &self,
+        _request: Request<proto::EngineStatusRequest>,
+    ) -> Result<Response<proto::EngineStatusResponse>, Status> {
+        let state = self.handle.state();
+
+        Ok(Response::new(proto::EngineStatusResponse {
+            paused: state.paused,
+            orders: state.orders.iter().map(to_proto).collect(),
+            pending_orders: state.pending_orders.iter().map(|id| *id as u64).collect(),
+            active_auctions: state
+                .active_auctions
+                .into_iter()
+                .map(summary_to_proto)
+                .collect(),
+            prices: state
+                .prices
+                .into_iter()
+                .map(|(denom, price)| (denom.to_string(), price))
+                .collect(),
+            last_evaluation: state.last_evaluation,
+        }))
+    }
+}
+
+fn to_proto(order: &Order) -> proto::Order {
+    proto::Order {
+        id: order.id as u64,
+        fee_token: order.fee_token.to_string(),
+        maximum_usomm_in: order.maximum_usomm_in,
+        minimum_usd_value_out: order.minimum_usd_value_out,
+        max_somm_per_token: order.max_somm_per_token,
+        max_usd_per_token: order.max_usd_per_token,
+        expires_at: order.expires_at,
+        expires_at_height: order.expires_at_height,
+        dca: order.dca.as_ref().map(|dca| proto::Dca {
+            usomm_per_bid: dca.usomm_per_bid,
+            interval_secs: dca.interval_secs,
+        }),
+        stop: order.stop.as_ref().map(|stop| proto::StopConditions {
+            max_price_age_secs: stop.max_price_age_secs,
+            max_somm_price_change_pct: stop.max_somm_price_change_pct,
+            max_consecutive_failures: stop.max_consecutive_failures,
+        }),
+        wallet: order.wallet.clone().unwrap_or_default(),
+        slippage_margin_bps: order.slippage_margin_bps,
+        priority: order.priority,
+        weight: order.weight,
+        snipe: order.snipe.as_ref().map(|snipe| proto::Snipe {
+            blocks_before_end: snipe.blocks_before_end,
+            max_remaining_decline_bps: snipe.max_remaining_decline_bps,
+        }),
+        compete: order.compete.as_ref().map(|compete| proto::Compete {
+            sellout_blocks: compete.sellout_blocks,
+            max_premium_bps: compete.max_premium_bps,
+        }),
+        tx_fee: order.tx_fee.as_ref().map(|fee| proto::TxFee {
+            amount: fee.amount.to_string(),
+            denom: fee.denom.clone(),
+        }),
+        group: order
+            .group
+            .iter()
+            .flatten()
+            .map(|member| proto::GroupMember {
+                wallet: member.wallet.clone(),
+                weight: member.weight,
+            })
+            .collect(),
+        strategy: order.strategy.clone().unwrap_or_default(),
+        twap_window_secs: order.twap_window_secs,
+        split: order.split.as_ref().map(|split| proto::Split {
+            parts: split.parts,
+            blocks_between: split.blocks_between,
+        }),
+    }
+}
+
+// The order's ID is ignored since the engine assigns it
+fn from_proto(order: proto::Order) -> Result<Order, Status> {
+    let fee_token = Denom::try_from(order.fee_token)
+        .map_err(|err| Status::invalid_argument(format!("invalid fee_token: {err}")))?;
+    let tx_fee = match order.tx_fee {
+        Some(fee) => Some(TxFee {
+            amount: fee
+                .amount
+                .parse()
+                .map_err(|err| Status::invalid_argument(format!("invalid tx_fee amount: {err}")))?,
+            denom: fee.denom,
+        }),
+        None => None,
+    };
+
+    Ok(Order {
+        id: 0,
+        fee_token,
+        maximum_usomm_in: order.maximum_usomm_in,
+        minimum_usd_value_out: order.minimum_usd_value_out,
+        max_somm_per_token: order.max_somm_per_token,
+        max_usd_per_token: order.max_usd_per_token,
+        expires_at: order.expires_at,
+        expires_at_height: order.expires_at_height,
+        dca: order.dca.map(|dca| Dca {
+            usomm_per_bid: dca.usomm_per_bid,
+            interval_secs: dca.interval_secs,
+        }),
+        stop: order.stop.map(|stop| StopConditions {
+            max_price_age_secs: stop.max_price_age_secs,
+            max_somm_price_change_pct: stop.max_somm_price_change_pct,
+            max_consecutive_failures: stop.max_consecutive_failures,
+        }),
+        wallet: Some(order.wallet).filter(|w| !w.is_empty()),
+        slippage_margin_bps: order.slippage_margin_bps,
+        priority: order.priority,
+        weight: order.weight,
+        snipe: order.snipe.map(|snipe| Snipe {
+            blocks_before_end: snipe.blocks_before_end,
+            max_remaining_decline_bps: snipe.max_remaining_decline_bps,
+        }),
+        compete: order.compete.map(|compete| Compete {
+            sellout_blocks: compete.sellout_blocks,
+            max_premium_bps: compete.max_premium_bps,
+        }),
+        tx_fee,
+        group: Some(order.group)
+            .filter(|group| !group.is_empty())
+            .map(|group| {
+                group
+                    .into_iter()
+                    .map(|member| GroupMember {
+                        wallet: member.wallet,
+                        weight: member.weight,
+                    })
+                    .collect()
+            }),
+        strategy: Some(order.strategy).filter(|s| !s.is_empty()),
+        twap_window_secs: order.twap_window_secs,
+        split: order.split.map(|split| Split {
+            parts: split.parts,
+            blocks_between: split.blocks_between,
+        }),
+    })
+}
+
+fn summary_to_proto(auction: AuctionSummary) -> proto::AuctionSummary {
+    proto::AuctionSummary {
+        id: auction.id,
+        denom: auction.denom,
+        end_block: auction.end_block,
+        current_unit_price_in_usomm: auction.current_unit_price_in_usomm,
+        remaining_tokens_for_sale: auction.remaining_tokens_for_sale,
+    }
+}
@@ -0,0 +1,163 @@
+use std::cmp::Reverse;
+
+use serde::{Deserialize, Serialize};
+use sommelier_auction::bid::Bid;
+
+use crate::order::Order;
+
+/// How an auction's supply is shared when more than one order qualifies for it. Orders are always
+/// considered highest `priority` first, then by ID.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AllocationStrategy {
+    /// Each order is evaluated against the supply left after the bids of higher priority orders
+    #[default]
+    Priority,
+    /// Every qualifying order bids, and if together they ask for more than the remaining supply
+    /// each bid is scaled down to its share by `weight`
+    ProRata,
+    /// Only the first qualifying order bids
+    FirstMatch,
+}
+
+/// Sorts orders into the order they're allocated in
+pub fn sort_by_priority(orders: &mut [&Order]) {
+    orders.sort_by_key(|o| (Reverse(o.priority.unwrap_or_default()), o.id));
+}
+
+/// Scales bids down so together they ask for at most `remaining` tokens, giving each a share of the
+/// supply in proportion to its weight. Bids keep their unit price, and bids scaled to nothing are
+/// dropped. Each bid is paired with its weight.
+pub fn pro_rata<T>(bids: Vec<(T, u32, Bid)>, remaining: u128) -> Vec<(T, Bid)> {
+    let requested = bids
+        .iter()
+        .map(|(_, _, b)| b.minimum_tokens_out)
+        .sum::<u128>();
+    if requested <= remaining {
+        return bids.into_iter().map(|(t, _, b)| (t, b)).collect();
+    }
+
+    let total_weight = bids.iter().map(|(_, w, _)| *w as u128).sum::<u128>().max(1);
+    bids.into_iter()
+        .filter_map(|(t, weight, mut bid)| {
+            let share = remaining
+                .checked_mul(weight as u128)
+                .map(|s| s / total_weight)
+                .unwrap_or(remaining / total_weight * weight as u128);
+            if share < bid.minimum_tokens_out {
+                scale_to(&mut bid, share);
+            }
+
+            (bid.minimum_tokens_out > 0 && bid.maximum_usomm_in > 0).then_some((t, bid))
+        })
+        .collect()
+}
+
+/// Splits a bid into one per weight, each asking for its weighted share of the bid's tokens at the
+/// bid's unit price. Rounding leftovers aren't bid, and shares of nothing are dropped.
+pub fn split<T>(bid: &Bid, weights: Vec<(T, u32)>) -> Vec<(T, Bid)> {
+    let total_weight = weights.iter().map(|(_, w)| *w as u128).sum::<u128>().max(1);
+    weights
+        .into_iter()
+        .filter_map(|(t, weight)| {
+            let share = bid
+                .minimum_tokens_out
+                .checked_mul(weight as u128)
+                .map(|s| s / total_weight)
+                .unwrap_or(bid.minimum_tokens_out / total_weight * weight as u128);
+            let mut bid = bid.clone();
+            scale_to(&mut bid, share);
+
+            (bid.minimum_tokens_out > 0 && bid.maximum_usomm_in > 0).then_some((t, bid))
+        })
+        .collect()
+}
+
+// Scales a bid down to ask for `tokens`, keeping its unit price
+fn scale_to(bid: &mut Bid, tokens: u128) {
+    if bid.minimum_tokens_out == 0 {
+        return;
+    }
+    bid.maximum_usomm_in = bid
+        .maximum_usomm_in
+        .checked_mul(tokens)
+        .map(|u| u / bid.minimum_tokens_out)
+        .unwrap_or(bid.maximum_usomm_in / bid.minimum_tokens_out * tokens);
+    bid.minimum_tokens_out = tokens;
+}
+
+#[cfg(test)]
+mod tests {
+    use sommelier_auction::denom::Denom;
+
+    use super::*;
+
+    fn bid(maximum_usomm_in: u128, minimum_tokens_out: u128) -> Bid {
+        Bid {
+            auction_id: 1,
+            fee_token: Denom::USDC,
+            maximum_usomm_in,
+            minimum_tokens_out,
+        }
+    }
+
+    #[test]
+    fn test_sort_by_priority() {
+        let orders = [
+            Order {
+                id: 0,
+                ..Default::default()
+            },
+            Order {
+                id: 1,
+                priority: Some(5),
+                ..Default::default()
+            },
+            Order {
+                id: 2,
+                priority: Some(-1),
+                ..Default::default()
+            },
+            Order {
+                id: 3,
+                ..Default::default()
+            },
+        ];
+        let mut sorted = orders.iter().collect::<Vec<&Order>>();
+        sort_by_priority(&mut sorted);
+
+        let ids = sorted.iter().map(|o| o.id).collect::<Vec<usize>>();
+        assert_eq!(ids, vec![1, 0, 3, 2]);
+    }
+
+    #[test]
+    fn test_pro_rata() {
+        // enough supply for everyone
+        let bids = pro_rata(vec![(0, 1, bid(100, 10)), (1, 1, bid(100, 10))], 20);
+        assert_eq!(bids[0].1.minimum_tokens_out, 10);
+        assert_eq!(bids[1].1.minimum_tokens_out, 10);
+
+        // 3:1 split of 8 tokens
+        let bids = pro_rata(vec![(0, 3, bid(100, 10)), (1, 1, bid(100, 10))], 8);
+        assert_eq!(bids[0].1.minimum_tokens_out, 6);
+        assert_eq!(bids[0].1.maximum_usomm_in, 60);
+        assert_eq!(bids[1].1.minimum_tokens_out, 2);
+        assert_eq!(bids[1].1.maximum_usomm_in, 20);
+
+        // a zero share drops the bid
+        let bids = pro_rata(vec![(0, 1, bid(100, 10)), (1, 0, bid(100, 10))], 5);
+        assert_eq!(bids.len(), 1);
+        assert_eq!(bids[0].0, 0);
+    }
+
+    #[test]
+    fn test_split() {
+        // 2:1 split of 10 tokens for 100 usomm, the leftover token isn't bid
+        let bids = split(&bid(100, 10), vec![("a", 2), ("b", 1), ("c", 0)]);
+        assert_eq!(bids.len(), 2);
+        assert_eq!((bids[0].0, bids[0].1.minimum_tokens_out), ("a", 6));
+        assert_eq!(bids[0].1.maximum_usomm_in, 60);
+        assert_eq!((bids[1].0, bids[1].1.minimum_tokens_out), ("b", 3));
+        assert_eq!(bids[1].1.maximum_usomm_in, 30);
+    }
+}
@@ -0,0 +1,74 @@
+use eyre::{eyre, Result};
+use sommelier_auction::{authz::Grant, client::Client};
+use tracing::{info, warn};
+
+use crate::wallet::{Wallet, Wallets};
+
+const GENERIC_AUTHORIZATION_TYPE_URL: &str = "/cosmos.authz.v1beta1.GenericAuthorization";
+
+/// Checks that every wallet bidding on behalf of a granter holds a grant to do so, recording when
+/// each grant expires. Fails if a grant is missing or has already expired.
+pub async fn validate_grants(client: &mut Client, wallets: &mut Wallets, now: u64) -> Result<()> {
+    for wallet in wallets.iter_mut() {
+        let granter = match &wallet.granter {
+            Some(granter) => granter.clone(),
+            None => continue,
+        };
+        let grantee = wallet.signer.address()?;
+        let grants = client.bid_grants(&granter, &grantee).await?;
+        if grants.is_empty() {
+            return Err(eyre!(
+                "wallet {} ({grantee}) has no authz grant to bid on behalf of {granter}",
+                wallet.name
+            ));
+        }
+        for grant in &grants {
+            if let Some(authorization) = &grant.authorization {
+                if authorization.type_url != GENERIC_AUTHORIZATION_TYPE_URL {
+                    warn!(
+                        "wallet {} holds a {} grant from {granter}, which the engine can't check",
+                        wallet.name, authorization.type_url
+                    );
+                }
+            }
+        }
+
+        wallet.grant_expires_at = latest_expiration(&grants);
+        if matches!(wallet.grant_expires_at, Some(expires_at) if expires_at <= now) {
+            return Err(eyre!(
+                "the authz grant from {granter} to wallet {} has expired",
+                wallet.name
+            ));
+        }
+        info!(
+            "wallet {} bids on behalf of {granter}, grant expires at {:?}",
+            wallet.name, wallet.grant_expires_at
+        );
+    }
+
+    Ok(())
+}
+
+/// Checks that a grantee wallet's grant hasn't expired. Returns the reason the bid should be
+/// skipped, if any.
+pub fn preflight(wallet: &Wallet, now: u64) -> Option<String> {
+    let granter = wallet.granter.as_ref()?;
+    if matches!(wallet.grant_expires_at, Some(expires_at) if expires_at <= now) {
+        return Some(format!(
+            "the authz grant from {granter} to wallet {} has expired",
+            wallet.name
+        ));
+    }
+
+    None
+}
+
+// The unix time the last of the grants expires, or None if any never does
+fn latest_expiration(grants: &[Grant]) -> Option<u64> {
+    grants
+        .iter()
+        .map(|g| g.expiration.as_ref().map(|t| t.seconds.max(0) as u64))
+        .collect::<Option<Vec<u64>>>()?
+        .into_iter()
+        .max()
+}
@@ -0,0 +1,370 @@
+use std::{cmp::Reverse, collections::HashMap, fmt::Display};
+
+use eyre::{eyre, Result};
+use rust_decimal::{
+    prelude::{FromPrimitive, ToPrimitive},
+    Decimal,
+};
+use serde::{Deserialize, Serialize};
+use sommelier_auction::{
+    auction::Auction, client::Client, cosmos_sdk_proto::cosmos::base::v1beta1::Coin, denom::Denom,
+    BidResult,
+};
+use tracing::{debug, info, warn};
+
+use crate::{config::Config, evaluate, order::Order, price, strategy::Strategies};
+
+/// Used to turn block heights into the timestamps DCA intervals are measured in
+pub const SECONDS_PER_BLOCK: u64 = 6;
+
+/// An ended auction and the bids that were placed on it
+#[derive(Debug, Clone)]
+pub struct ArchivedAuction {
+    pub auction: Auction,
+    pub bids: Vec<BidResult>,
+}
+
+/// A simulated fill of an order's bid
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BacktestFill {
+    pub order_id: usize,
+    pub auction_id: u32,
+    pub block_height: u64,
+    pub denom: Denom,
+    pub usomm_spent: u128,
+    pub tokens_received: u128,
+}
+
+/// The outcome of replaying a set of orders against archived auctions
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BacktestReport {
+    pub auctions: usize,
+    pub fills: Vec<BacktestFill>,
+    pub usomm_spent: u128,
+    /// USD value of the tokens received at the prices the backtest was run with
+    pub usd_value_received: f64,
+    /// USD value of the SOMM spent. Unknown without a SOMM price.
+    pub usd_spent: Option<f64>,
+    pub pnl_usd: Option<f64>,
+}
+
+impl Display for BacktestReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} fills across {} auctions, {} usomm spent for ${:.2} of tokens",
+            self.fills.len(),
+            self.auctions,
+            self.usomm_spent,
+            self.usd_value_received
+        )?;
+        if let (Some(spent), Some(pnl)) = (self.usd_spent, self.pnl_usd) {
+            write!(f, ", ${spent:.2} spent, PnL ${pnl:.2}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Backtests the configured orders against every ended auction, valuing tokens at current prices
+/// from the configured price source
+pub async fn backtest(config: Config, acceleration_rate: f64) -> Result<BacktestReport> {
+    let rpc_endpoint = config
+        .rpc_endpoint
+        .unwrap_or_else(|| sommelier_auction::client::DEFAULT_RPC_ENDPOINT.to_string());
+    let grpc_endpoint = config
+        .grpc_endpoint
+        .unwrap_or_else(|| sommelier_auction::client::DEFAULT_GRPC_ENDPOINT.to_string());
+    let acceleration_rate = Decimal::from_f64(acceleration_rate)
+        .ok_or_else(|| eyre!("invalid acceleration rate {acceleration_rate}"))?;
+    let orders = config
+        .orders
+        .into_iter()
+        .enumerate()
+        .map(|(id, order)| Order { id, ..order })
+        .collect::<Vec<Order>>();
+    let strategies = Strategies::load(config.strategies.as_deref().unwrap_or_default())?;
+
+    let mut client = Client::with_endpoints(rpc_endpoint, grpc_endpoint.clone()).await?;
+    let archive = load_archive(&mut client).await?;
+    info!("loaded {} ended auctions", archive.len());
+
+    let mut denoms = vec![Denom::USOMM];
+    for order in &orders {
+        if !denoms.contains(&order.fee_token) {
+            denoms.push(order.fee_token);
+        }
+    }
+    let price_source = price::price_source(
+        config.price_source.unwrap_or_default(),
+        grpc_endpoint,
+        config.osmosis,
+    )?;
+    let prices = price_source.usd_prices(&denoms).await?;
+
+    run(orders, &archive, &prices, &strategies, acceleration_rate)
+}
+
+/// Fetches every ended auction along with its bids
+pub async fn load_archive(client: &mut Client) -> Result<Vec<ArchivedAuction>> {
+    let mut archive = Vec::new();
+    for auction in client.ended_auctions().await? {
+        let bids = client.auction_bids(auction.id).await?;
+        archive.push(ArchivedAuction { auction, bids });
+    }
+
+    Ok(archive)
+}
+
+/// Reconstructs an auction's price in usomm per base unit at each block it changed. Mirrors the
+/// auction module: every `price_decrease_block_interval` blocks the price drops by the initial price
+/// times the current decrease rate, then the rate grows by `acceleration_rate`. The curve ends at
+/// the auction's end block or when the price would reach zero.
+pub fn price_curve(auction: &Auction, acceleration_rate: Decimal) -> Result<Vec<(u64, Decimal)>> {
+    let initial_price = evaluate::parse_dec(&auction.initial_unit_price_in_usomm)?;
+    let mut rate = evaluate::parse_dec(&auction.initial_price_decrease_rate)?;
+    let interval = auction.price_decrease_block_interval.max(1);
+
+    let mut curve = vec![(auction.start_block, initial_price)];
+    let mut price = initial_price;
+    let mut block = auction.start_block + interval;
+    while block <= auction.end_block {
+        price = initial_price
+            .checked_mul(rate)
+            .and_then(|d| price.checked_sub(d))
+            .ok_or_else(|| eyre!("price out of range for auction {}", auction.id))?;
+        if price <= Decimal::ZERO {
+            break;
+        }
+
+        curve.push((block, price));
+        rate += acceleration_rate;
+        block += interval;
+    }
+
+    Ok(curve)
+}
+
+/// Replays `orders` against `archive`, evaluating them at every step of each auction's price curve.
+/// Historical bids consume supply at their block height before the orders bid at that height.
+/// `prices` are USD prices of one whole token, including SOMM for PnL and USD limit prices. Archived
+/// prices aren't available, so they're applied to every auction. Orders naming a strategy are
+/// evaluated with it from `strategies`.
+pub fn run(
+    mut orders: Vec<Order>,
+    archive: &[ArchivedAuction],
+    prices: &HashMap<Denom, f64>,
+    strategies: &Strategies,
+    acceleration_rate: Decimal,
+) -> Result<BacktestReport> {
+    // orders bid in priority order, each seeing the supply left by the ones before it
+    orders.sort_by_key(|o| (Reverse(o.priority.unwrap_or_default()), o.id));
+    let mut archive = archive.iter().collect::<Vec<&ArchivedAuction>>();
+    archive.sort_by_key(|a| a.auction.start_block);
+    let somm_usd_price = prices.get(&Denom::USOMM).cloned();
+    let mut last_dca_bids = HashMap::<usize, (u32, u64)>::new();
+    let mut report = BacktestReport::default();
+
+    for ArchivedAuction { auction, bids } in archive {
+        let starting_tokens = match auction.starting_tokens_for_sale.as_ref() {
+            Some(coin) => coin,
+            None => {
+                warn!("auction {} has no tokens for sale, skipping it", auction.id);
+
+                continue;
+            }
+        };
+        let denom = match Denom::try_from(&starting_tokens.denom) {
+            Ok(denom) => denom,
+            Err(err) => {
+                debug!("skipping auction {}: {err}", auction.id);
+
+                continue;
+            }
+        };
+        let usd_unit_value = match prices.get(&denom) {
+            Some(p) => *p,
+            None => {
+                warn!("no USD price for {denom}, skipping auction {}", auction.id);
+
+                continue;
+            }
+        };
+        report.auctions += 1;
+
+        let mut remaining = evaluate::parse_amount(&starting_tokens.amount)?;
+        let mut bids = bids.iter().collect::<Vec<&BidResult>>();
+        bids.sort_by_key(|b| b.block_height);
+        let mut bids = bids.into_iter().peekable();
+
+        for (block_height, price) in price_curve(auction, acceleration_rate)? {
+            while let Some(bid) = bids.next_if(|b| b.block_height <= block_height) {
+                let filled = bid
+                    .total_fulfilled_sale_tokens
+                    .as_ref()
+                    .map(|c| evaluate::parse_amount(&c.amount))
+                    .transpose()?
+                    .unwrap_or_default();
+                remaining = remaining.saturating_sub(filled);
+            }
+            if remaining == 0 {
+                break;
+            }
+
+            let snapshot = Auction {
+                current_unit_price_in_usomm: evaluate::encode_dec(price)?,
+                remaining_tokens_for_sale: Some(Coin {
+                    denom: starting_tokens.denom.clone(),
+                    amount: remaining.to_string(),
+                }),
+                ..auction.clone()
+            };
+            let now = block_height * SECONDS_PER_BLOCK;
+
+            for order in orders.iter_mut().filter(|o| o.fee_token == denom) {
+                if order.maximum_usomm_in == 0
+                    || order.is_expired(0, Some(block_height))
+                    || !order.dca_ready(auction.id, now, last_dca_bids.get(&order.id).cloned())
+                {
+                    continue;
+                }
+
+                let result = match &order.strategy {
+                    Some(_) => {
+                        strategies
+                            .evaluate(
+                                order,
+                                &snapshot,
+                                usd_unit_value,
+                                somm_usd_price,
+                                Some(block_height),
+                                now,
+                            )
+                            .0
+                    }
+                    None => {
+                        evaluate::evaluate_bid(order, usd_unit_value, somm_usd_price, &snapshot)
+                    }
+                };
+                let bid = match result? {
+                    Some(bid) => bid,
+                    None => continue,
+                };
+
+                // the auction fills the bid at its current price, so we pay for exactly the tokens we
+                // get
+                let tokens_received = Decimal::from_u128(bid.maximum_usomm_in)
+                    .and_then(|u| u.checked_div(price))
+                    .and_then(|t| t.floor().to_u128())
+                    .ok_or_else(|| eyre!("token amount out of range for auction {}", auction.id))?
+                    .min(remaining);
+                if tokens_received == 0 {
+                    continue;
+                }
+                let usomm_spent = Decimal::from_u128(tokens_received)
+                    .and_then(|t| t.checked_mul(price))
+                    .and_then(|u| u.ceil().to_u128())
+                    .ok_or_else(|| eyre!("usomm amount out of range for auction {}", auction.id))?
+                    .min(bid.maximum_usomm_in);
+
+                remaining -= tokens_received;
+                order.maximum_usomm_in = order
+                    .maximum_usomm_in
+                    .saturating_sub(u64::try_from(usomm_spent).unwrap_or(u64::MAX));
+                last_dca_bids.insert(order.id, (auction.id, now));
+                info!(
+                    "order {} filled {tokens_received} {denom} for {usomm_spent} usomm at block {block_height} of auction {}",
+                    order.id, auction.id
+                );
+
+                report.usomm_spent += usomm_spent;
+                report.usd_value_received +=
+                    tokens_received as f64 / 10f64.powi(denom.decimals() as i32) * usd_unit_value;
+                report.fills.push(BacktestFill {
+                    order_id: order.id,
+                    auction_id: auction.id,
+                    block_height,
+                    denom,
+                    usomm_spent,
+                    tokens_received,
+                });
+            }
+        }
+    }
+
+    if let Some(somm_usd_price) = somm_usd_price {
+        let usd_spent = report.usomm_spent as f64 / 1_000_000f64 * somm_usd_price;
+        report.usd_spent = Some(usd_spent);
+        report.pnl_usd = Some(report.usd_value_received - usd_spent);
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 10 usomm per base unit of USDC, dropping by 1 usomm every 10 blocks
+    fn auction() -> Auction {
+        Auction {
+            id: 1,
+            starting_tokens_for_sale: Some(Coin {
+                denom: Denom::USDC.to_string(),
+                amount: "100000000".to_string(),
+            }),
+            start_block: 100,
+            end_block: 150,
+            initial_price_decrease_rate: "100000000000000000".to_string(),
+            price_decrease_block_interval: 10,
+            initial_unit_price_in_usomm: "10000000000000000000".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_price_curve() {
+        let curve = price_curve(&auction(), Decimal::ZERO).unwrap();
+        let prices = curve
+            .iter()
+            .map(|(block, price)| (*block, price.to_u64().unwrap()))
+            .collect::<Vec<(u64, u64)>>();
+
+        assert_eq!(
+            prices,
+            vec![(100, 10), (110, 9), (120, 8), (130, 7), (140, 6), (150, 5)]
+        );
+    }
+
+    #[test]
+    fn test_run() {
+        // wants at least 1 USD per 7 SOMM, so it first qualifies at 7 usomm per base unit
+        let order = Order {
+            id: 0,
+            fee_token: Denom::USDC,
+            maximum_usomm_in: 70_000_000,
+            minimum_usd_value_out: 10.0,
+            ..Default::default()
+        };
+        let archive = vec![ArchivedAuction {
+            auction: auction(),
+            bids: Vec::new(),
+        }];
+        let prices = HashMap::from([(Denom::USDC, 1.0), (Denom::USOMM, 0.1)]);
+
+        let report = run(
+            vec![order],
+            &archive,
+            &prices,
+            &Strategies::default(),
+            Decimal::ZERO,
+        )
+        .unwrap();
+
+        assert_eq!(report.fills.len(), 1);
+        assert_eq!(report.fills[0].block_height, 130);
+        assert_eq!(report.fills[0].tokens_received, 10_000_000);
+        assert_eq!(report.usomm_spent, 70_000_000);
+        assert!((report.pnl_usd.unwrap() - 3.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+use sommelier_auction::denom::Denom;
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+const WEEK_SECS: u64 = 7 * DAY_SECS;
+
+/// Limits on how much usomm the engine may spend. Unset limits are not enforced.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BudgetConfig {
+    /// Maximum usomm spent in any rolling 24 hour window
+    pub daily_usomm: Option<u64>,
+    /// Maximum usomm spent in any rolling 7 day window
+    pub weekly_usomm: Option<u64>,
+    /// Maximum usomm spent in a single auction across all orders
+    pub per_auction_usomm: Option<u64>,
+}
+
+/// Tracks usomm spent by the engine against a [`BudgetConfig`]. Times are unix seconds.
+#[derive(Debug, Default, Clone)]
+pub struct Budget {
+    config: BudgetConfig,
+    // (time, usomm) for every spend in the last week
+    spends: VecDeque<(u64, u128)>,
+    // (auction ID, usomm) spent in the latest auction of each denom. A denom only has one auction
+    // at a time, so once a later one is bid on the earlier one has ended and is dropped.
+    spent_by_auction: HashMap<Denom, (u32, u128)>,
+}
+
+/// A snapshot of budget consumption
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetUsage {
+    pub daily_spent: u128,
+    pub daily_limit: Option<u64>,
+    pub weekly_spent: u128,
+    pub weekly_limit: Option<u64>,
+    /// The most spent in any one auction still being tracked
+    pub auction_spent: u128,
+    pub auction_limit: Option<u64>,
+}
+
+impl Budget {
+    pub fn new(config: BudgetConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// The most usomm that may be spent on a bid in `auction_id` of `denom` right now, or `None`
+    /// if no limit applies
+    pub fn allowance(&mut self, auction_id: u32, denom: Denom, now: u64) -> Option<u128> {
+        self.expire(now);
+
+        let usage = self.usage(now);
+        let remaining =
+            |limit: Option<u64>, spent: u128| limit.map(|l| (l as u128).saturating_sub(spent));
+        let per_auction_spent = match self.spent_by_auction.get(&denom) {
+            Some((id, spent)) if *id == auction_id => *spent,
+            _ => 0,
+        };
+
+        [
+            remaining(self.config.daily_usomm, usage.daily_spent),
+            remaining(self.config.weekly_usomm, usage.weekly_spent),
+            remaining(self.config.per_auction_usomm, per_auction_spent),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+    }
+
+    /// Records usomm spent on a bid in `auction_id` of `denom`
+    pub fn record(&mut self, auction_id: u32, denom: Denom, usomm: u128, now: u64) {
+        self.spends.push_back((now, usomm));
+        let (id, spent) = self.spent_by_auction.entry(denom).or_default();
+        if *id != auction_id {
+            *id = auction_id;
+            *spent = 0;
+        }
+        *spent += usomm;
+        self.expire(now);
+    }
+
+    /// Current consumption of the daily, weekly and per-auction budgets
+    pub fn usage(&self, now: u64) -> BudgetUsage {
+        let spent_since = |window: u64| {
+            self.spends
+                .iter()
+                .filter(|(t, _)| now.saturating_sub(*t) < window)
+                .map(|(_, s)| s)
+                .sum::<u128>()
+        };
+
+        BudgetUsage {
+            daily_spent: spent_since(DAY_SECS),
+            daily_limit: self.config.daily_usomm,
+            weekly_spent: spent_since(WEEK_SECS),
+            weekly_limit: self.config.weekly_usomm,
+            auction_spent: self
+                .spent_by_auction
+                .values()
+                .map(|(_, spent)| *spent)
+                .max()
+                .unwrap_or_default(),
+            auction_limit: self.config.per_auction_usomm,
+        }
+    }
+
+    fn expire(&mut self, now: u64) {
+        while matches!(self.spends.front(), Some((t, _)) if now.saturating_sub(*t) >= WEEK_SECS) {
+            self.spends.pop_front();
+        }
+    }
+}
+
+impl Display for BudgetUsage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let limit = |l: Option<u64>| l.map(|l| l.to_string()).unwrap_or("unlimited".to_string());
+        write!(
+            f,
+            "daily {}/{} usomm, weekly {}/{} usomm, per auction {}/{} usomm",
+            self.daily_spent,
+            limit(self.daily_limit),
+            self.weekly_spent,
+            limit(self.weekly_limit),
+            self.auction_spent,
+            limit(self.auction_limit)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_limits() {
+        let mut budget = Budget::new(BudgetConfig::default());
+        budget.record(1, Denom::USDC, 1_000_000, 0);
+
+        assert_eq!(budget.allowance(1, Denom::USDC, 0), None);
+    }
+
+    #[test]
+    fn test_smallest_limit_applies() {
+        let mut budget = Budget::new(BudgetConfig {
+            daily_usomm: Some(100),
+            weekly_usomm: Some(500),
+            per_auction_usomm: Some(60),
+        });
+        budget.record(1, Denom::USDC, 50, 0);
+
+        assert_eq!(budget.allowance(1, Denom::USDC, 10), Some(10));
+        assert_eq!(budget.allowance(2, Denom::WETH, 10), Some(50));
+    }
+
+    #[test]
+    fn test_ended_auctions_are_dropped() {
+        let mut budget = Budget::new(BudgetConfig {
+            per_auction_usomm: Some(100),
+            ..Default::default()
+        });
+        budget.record(1, Denom::USDC, 60, 0);
+        budget.record(2, Denom::WETH, 30, 0);
+        assert_eq!(budget.usage(0).auction_spent, 60);
+
+        // auction 3 of USDC means auction 1 has ended
+        budget.record(3, Denom::USDC, 10, 0);
+        assert_eq!(budget.spent_by_auction.len(), 2);
+        assert_eq!(budget.allowance(3, Denom::USDC, 0), Some(90));
+        assert_eq!(budget.allowance(2, Denom::WETH, 0), Some(70));
+        assert_eq!(budget.usage(0).auction_spent, 30);
+    }
+
+    #[test]
+    fn test_rolling_windows() {
+        let mut budget = Budget::new(BudgetConfig {
+            daily_usomm: Some(100),
+            weekly_usomm: Some(150),
+            per_auction_usomm: None,
+        });
+        budget.record(1, Denom::USDC, 100, 0);
+
+        assert_eq!(budget.allowance(2, Denom::USDC, DAY_SECS - 1), Some(0));
+        assert_eq!(budget.allowance(2, Denom::USDC, DAY_SECS), Some(50));
+        assert_eq!(budget.allowance(2, Denom::USDC, WEEK_SECS), Some(100));
+        assert_eq!(budget.usage(WEEK_SECS).weekly_spent, 0);
+    }
+}
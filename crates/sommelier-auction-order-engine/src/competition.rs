@@ -0,0 +1,115 @@
+use std::collections::{HashMap, VecDeque};
+
+use sommelier_auction::auction::Auction;
+
+use crate::evaluate;
+
+/// How many observations of each auction's supply are kept to measure how fast it's selling
+const SUPPLY_SAMPLES: usize = 10;
+
+/// Follows how fast other bidders are buying up each active auction's supply
+#[derive(Debug, Default, Clone)]
+pub struct SupplyTracker {
+    // (block height, remaining tokens plus those the engine bought) of each auction, oldest first
+    samples: HashMap<u32, VecDeque<(u64, u128)>>,
+    // tokens the engine's own bids have received from each auction
+    own: HashMap<u32, u128>,
+}
+
+impl SupplyTracker {
+    /// Records the remaining supply of each auction at `height`, forgetting auctions that have
+    /// ended
+    pub fn record(&mut self, auctions: &[Auction], height: u64) {
+        self.samples
+            .retain(|id, _| auctions.iter().any(|a| a.id == *id));
+        self.own
+            .retain(|id, _| auctions.iter().any(|a| a.id == *id));
+
+        for auction in auctions {
+            let remaining = match evaluate::remaining_tokens_for_sale(auction) {
+                Ok(r) => r,
+                Err(_) => continue,
+            };
+            // the engine's own purchases don't count as competition
+            let remaining = remaining + self.own.get(&auction.id).copied().unwrap_or_default();
+            let samples = self.samples.entry(auction.id).or_default();
+            if matches!(samples.back(), Some((h, _)) if *h >= height) {
+                continue;
+            }
+            samples.push_back((height, remaining));
+            if samples.len() > SUPPLY_SAMPLES {
+                samples.pop_front();
+            }
+        }
+    }
+
+    /// Records tokens one of the engine's bids received from an auction
+    pub fn record_fill(&mut self, auction_id: u32, tokens_received: u128) {
+        *self.own.entry(auction_id).or_default() += tokens_received;
+    }
+
+    /// How many blocks until other bidders buy out the auction at the rate they have been. None if
+    /// nothing has sold while it was observed.
+    pub fn blocks_until_sellout(&self, auction_id: u32) -> Option<u64> {
+        let samples = self.samples.get(&auction_id)?;
+        let (first_height, first_remaining) = *samples.front()?;
+        let (last_height, last_remaining) = *samples.back()?;
+        let sold = first_remaining.saturating_sub(last_remaining);
+        let blocks = u128::from(last_height - first_height);
+        if sold == 0 || blocks == 0 {
+            return None;
+        }
+        let own = self.own.get(&auction_id).copied().unwrap_or_default();
+        let left = last_remaining.saturating_sub(own);
+
+        Some(u64::try_from(left.saturating_mul(blocks) / sold).unwrap_or(u64::MAX))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sommelier_auction::cosmos_sdk_proto::cosmos::base::v1beta1::Coin;
+
+    use super::*;
+    use crate::order::Compete;
+
+    fn auction(remaining: u128) -> Auction {
+        Auction {
+            id: 1,
+            remaining_tokens_for_sale: Some(Coin {
+                denom: "gravity0xdac17f958d2ee523a2206206994597c13d831ec7".to_string(),
+                amount: remaining.to_string(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_supply_tracker() {
+        let mut tracker = SupplyTracker::default();
+        tracker.record(&[auction(1000)], 100);
+        assert_eq!(tracker.blocks_until_sellout(1), None);
+
+        // others bought 100 tokens a block
+        tracker.record(&[auction(800)], 102);
+        assert_eq!(tracker.blocks_until_sellout(1), Some(8));
+
+        // the engine's own purchases aren't counted as selling
+        tracker.record_fill(1, 200);
+        tracker.record(&[auction(500)], 103);
+        assert_eq!(tracker.blocks_until_sellout(1), Some(5));
+
+        // ended auctions are forgotten
+        tracker.record(&[], 104);
+        assert_eq!(tracker.blocks_until_sellout(1), None);
+
+        let compete = Compete {
+            sellout_blocks: 10,
+            max_premium_bps: 200,
+        };
+        assert_eq!(compete.premium_bps(None), 0);
+        assert_eq!(compete.premium_bps(Some(10)), 0);
+        assert_eq!(compete.premium_bps(Some(5)), 100);
+        assert_eq!(compete.premium_bps(Some(0)), 200);
+    }
+}
@@ -1,15 +1,613 @@
-use crate::order::Order;
+use std::{collections::HashSet, str::FromStr};
+
+use eyre::{eyre, Result};
+use sommelier_auction::{denom::Denom, fee::GasPrice};
+
+use crate::{
+    allocation::AllocationStrategy,
+    budget::BudgetConfig,
+    engine::DEFAULT_WALLET_NAME,
+    filter::DenomFilter,
+    health::HealthConfig,
+    notify::{NotificationConfig, EVENT_KINDS},
+    order::Order,
+    paper::PaperConfig,
+    pnl::PnlConfig,
+    price::{OsmosisConfig, PriceSourceKind},
+    ratelimit::RateLimitConfig,
+    risk::RiskConfig,
+    strategy::StrategyConfig,
+    submit::TxFeeConfig,
+    wallet::WalletConfig,
+};
 
 #[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
 pub struct Config {
     /// Optional gRPC endpoint. Used for querying auction data.
     pub grpc_endpoint: Option<String>,
+    /// Optional gRPC endpoints the auction watcher fails over to when `grpc_endpoint` errors or
+    /// times out
+    pub fallback_grpc_endpoints: Option<Vec<String>>,
+    /// Optional number of seconds the auction watcher waits for each gRPC call. Defaults to 10.
+    pub grpc_timeout_secs: Option<u64>,
     /// Optional RPC endpoint. Used for submitting bids.
     pub rpc_endpoint: Option<String>,
-    /// The maximum amount of USOMM that can be spent on bids
-    //    pub total_usomm_budget: u64,
+    /// Optional limits on the amount of USOMM that can be spent on bids
+    pub budget: Option<BudgetConfig>,
     /// The orders loaded in from a orderfile
     pub orders: Vec<Order>,
-    /// Key for signing and spending wallet
+    /// Key for signing and spending wallet. Ignored if `wallets` is set.
     pub key_path: Option<String>,
+    /// Optional signing accounts to bid from. Orders name the wallet they bid from, or rotate
+    /// through them round-robin if they don't.
+    pub wallets: Option<Vec<WalletConfig>>,
+    /// Optional number of seconds to wait between auction evaluations. Defaults to 6, roughly one
+    /// block.
+    pub refresh_interval_secs: Option<u64>,
+    /// Optional number of times to attempt submitting a bid before giving up. Defaults to 3.
+    pub max_bid_attempts: Option<u32>,
+    /// Optional source of USD prices for auctioned tokens. Defaults to coingecko.
+    pub price_source: Option<PriceSourceKind>,
+    /// Optional number of seconds between price refreshes. Defaults to 24, which keeps CoinGecko
+    /// under its free tier rate limit.
+    pub price_refresh_interval_secs: Option<u64>,
+    /// Optional number of seconds without a fresh price before a denom is alerted on. Defaults to
+    /// 600.
+    pub price_stale_after_secs: Option<u64>,
+    /// Pools used when `price_source` is osmosis
+    pub osmosis: Option<OsmosisConfig>,
+    /// Optional address to serve the REST admin API on, e.g. "127.0.0.1:9090". The API is disabled
+    /// if not set.
+    pub admin_listen_addr: Option<String>,
+    /// Optional address to serve the gRPC admin API on, e.g. "127.0.0.1:9092". The API is disabled
+    /// if not set.
+    pub admin_grpc_listen_addr: Option<String>,
+    /// Optional environment variable holding a token the admin APIs require as a bearer token.
+    /// Without one they can only listen on a loopback address.
+    pub admin_token_env: Option<String>,
+    /// Optional address to serve the browser dashboard on, e.g. "127.0.0.1:9095". The dashboard is
+    /// disabled if not set.
+    pub dashboard_listen_addr: Option<String>,
+    /// Optional environment variable holding a password that enables the dashboard's forms. The
+    /// dashboard is read-only if not set.
+    pub dashboard_password_env: Option<String>,
+    /// Optional number of recent order decisions to keep for the admin API. Defaults to 100.
+    pub decision_log_size: Option<usize>,
+    /// Optional strategy for sharing an auction between orders that qualify for it. Defaults to
+    /// priority.
+    pub allocation: Option<AllocationStrategy>,
+    /// Optional `/healthz` endpoint for orchestrators. Disabled if not set.
+    pub health: Option<HealthConfig>,
+    /// Optional path the remaining orders are saved to on shutdown. If the file exists on startup,
+    /// its orders are resumed in place of the ones configured here.
+    pub state_path: Option<String>,
+    /// Optional path each bid is recorded to before it's broadcast. Bids a crash left unrecorded
+    /// are looked up on chain at startup and their fills applied, so they aren't placed again.
+    pub intent_log_path: Option<String>,
+    /// Optional flag to reload `[[orders]]` when this file changes, applying added, removed and
+    /// edited orders to the running engine. Defaults to false.
+    pub watch_orders: Option<bool>,
+    /// Optional address to serve Prometheus metrics on at `/metrics`, e.g. "0.0.0.0:9094".
+    /// Disabled if not set.
+    pub metrics_listen_addr: Option<String>,
+    /// Optional flag to log the bids the engine would submit instead of submitting them. Defaults
+    /// to false.
+    pub dry_run: Option<bool>,
+    /// Optional chats, email recipients and webhooks notified when bids are submitted,
+    /// filled or fail, and when orders are exhausted
+    pub notifications: Option<NotificationConfig>,
+    /// Optional upper bound in seconds on how long the engine sleeps when auction price projections
+    /// show no order can bid sooner. Defaults to 60. Set it to `refresh_interval_secs` or lower to
+    /// poll every interval instead.
+    pub max_projected_wait_secs: Option<u64>,
+    /// Optional paper trading. Bids fill against a simulated usomm balance at the auction's price
+    /// instead of being submitted, and the engine logs a running paper PnL.
+    pub paper: Option<PaperConfig>,
+    /// Optional allowlist and blocklist of auctioned denoms, enforced over every order
+    pub denoms: Option<DenomFilter>,
+    /// Optional caps on how many bids are submitted per block and per minute across all orders
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Optional PnL reporting. The engine always tracks PnL for the admin API, and can send a
+    /// periodic summary notification.
+    pub pnl: Option<PnlConfig>,
+    /// Optional risk limits enforced over every bid: exposure per denom, daily USD spend, single
+    /// bid size, and a kill switch for when price feeds disagree
+    pub risk: Option<RiskConfig>,
+    /// Optional gas prices bid fees are paid at. Defaults to the node's minimum gas prices.
+    pub tx_fee: Option<TxFeeConfig>,
+    /// Optional WebAssembly strategy modules orders can name to decide their bids. Needs the
+    /// engine to be built with the wasm feature.
+    pub strategies: Option<Vec<StrategyConfig>>,
+}
+
+impl Config {
+    /// Every problem with the config that would otherwise only surface once the engine is running
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        for (key, endpoint) in [
+            ("grpc_endpoint", &self.grpc_endpoint),
+            ("rpc_endpoint", &self.rpc_endpoint),
+        ] {
+            if let Some(problem) = endpoint.as_deref().and_then(url_problem) {
+                problems.push(format!("{key}: {problem}"));
+            }
+        }
+        for (key, addr) in [
+            ("admin_listen_addr", self.admin_listen_addr.as_deref()),
+            (
+                "admin_grpc_listen_addr",
+                self.admin_grpc_listen_addr.as_deref(),
+            ),
+            (
+                "dashboard_listen_addr",
+                self.dashboard_listen_addr.as_deref(),
+            ),
+            ("metrics_listen_addr", self.metrics_listen_addr.as_deref()),
+            (
+                "health.listen_addr",
+                self.health.as_ref().map(|h| h.listen_addr.as_str()),
+            ),
+        ] {
+            if let Some(problem) = addr.and_then(listen_addr_problem) {
+                problems.push(format!("{key}: {problem}"));
+            }
+        }
+        for (i, url) in self.fallback_grpc_endpoints.iter().flatten().enumerate() {
+            if let Some(problem) = url_problem(url) {
+                problems.push(format!("fallback_grpc_endpoints[{i}]: {problem}"));
+            }
+        }
+        for (i, url) in self
+            .notifications
+            .iter()
+            .flat_map(|n| n.webhooks.iter().flatten())
+            .enumerate()
+        {
+            if let Some(problem) = url_problem(url) {
+                problems.push(format!("notifications.webhooks[{i}]: {problem}"));
+            }
+        }
+        if let Some(email) = self.notifications.as_ref().and_then(|n| n.email.as_ref()) {
+            if !email.from.contains('@') {
+                problems.push(format!(
+                    "notifications.email.from: invalid email address {}",
+                    email.from
+                ));
+            }
+            if email.recipients.is_empty() {
+                problems.push("notifications.email.recipients: no one to email".to_string());
+            }
+            for (i, recipient) in email.recipients.iter().enumerate() {
+                let key = format!("notifications.email.recipients[{i}]");
+                if !recipient.address.contains('@') {
+                    problems.push(format!(
+                        "{key}.address: invalid email address {}",
+                        recipient.address
+                    ));
+                }
+                for event in recipient.events.iter().flatten() {
+                    if !EVENT_KINDS.contains(&event.as_str()) {
+                        problems.push(format!("{key}.events: unknown event {event}"));
+                    }
+                }
+            }
+        }
+
+        if let Some(budget) = &self.budget {
+            problems.extend(budget_problems("budget", budget));
+        }
+        if matches!(&self.paper, Some(paper) if paper.starting_usomm == 0) {
+            problems.push("paper.starting_usomm: a zero balance can't fill any bid".to_string());
+        }
+        if let Some(rate_limit) = &self.rate_limit {
+            for (key, limit) in [
+                ("max_bids_per_block", rate_limit.max_bids_per_block),
+                ("max_bids_per_minute", rate_limit.max_bids_per_minute),
+            ] {
+                if limit == Some(0) {
+                    problems.push(format!("rate_limit.{key}: a limit of 0 blocks every bid"));
+                }
+            }
+        }
+
+        for (i, price) in self
+            .tx_fee
+            .iter()
+            .flat_map(|f| f.gas_prices.iter().flatten())
+            .enumerate()
+        {
+            if let Err(err) = price.parse::<GasPrice>() {
+                problems.push(format!("tx_fee.gas_prices[{i}]: {err}"));
+            }
+        }
+
+        let wallets = self.wallets.as_deref().unwrap_or_default();
+        let mut wallet_names = HashSet::new();
+        for (i, wallet) in wallets.iter().enumerate() {
+            if wallet.name.is_empty() {
+                problems.push(format!("wallets[{i}]: name is empty"));
+            } else if !wallet_names.insert(wallet.name.as_str()) {
+                problems.push(format!(
+                    "wallets[{i}]: another wallet is already named {}",
+                    wallet.name
+                ));
+            }
+            if let Some(budget) = &wallet.budget {
+                problems.extend(budget_problems(&format!("wallets[{i}].budget"), budget));
+            }
+        }
+
+        let strategies = self.strategies.as_deref().unwrap_or_default();
+        let mut strategy_names = HashSet::new();
+        for (i, strategy) in strategies.iter().enumerate() {
+            if strategy.name.is_empty() {
+                problems.push(format!("strategies[{i}]: name is empty"));
+            } else if !strategy_names.insert(strategy.name.as_str()) {
+                problems.push(format!(
+                    "strategies[{i}]: another strategy is already named {}",
+                    strategy.name
+                ));
+            }
+            if strategy.path.is_empty() {
+                problems.push(format!("strategies[{i}].path: is empty"));
+            }
+        }
+
+        let wallets = wallets
+            .iter()
+            .map(|w| w.name.clone())
+            .collect::<Vec<String>>();
+        let strategies = strategies
+            .iter()
+            .map(|s| s.name.clone())
+            .collect::<Vec<String>>();
+        for (i, order) in self.orders.iter().enumerate() {
+            problems.extend(
+                order_problems(order, &wallets, &strategies)
+                    .into_iter()
+                    .map(|problem| format!("orders[{i}].{problem}")),
+            );
+        }
+
+        problems.extend(self.price_problems());
+        if let Some(risk) = &self.risk {
+            if risk.max_price_divergence_pct.is_some() && risk.reference_price_source.is_none() {
+                problems.push(
+                    "risk.max_price_divergence_pct: needs a reference_price_source to compare with"
+                        .to_string(),
+                );
+            }
+        }
+
+        problems
+    }
+
+    // Checks every denom the engine needs a USD price for can be priced by the configured sources
+    fn price_problems(&self) -> Vec<String> {
+        let sources = [
+            ("price_source", self.price_source.unwrap_or_default()),
+            (
+                "risk.reference_price_source",
+                self.risk
+                    .as_ref()
+                    .and_then(|r| r.reference_price_source)
+                    .unwrap_or_default(),
+            ),
+        ];
+        let osmosis_sources = sources
+            .iter()
+            .filter(|(_, kind)| *kind == PriceSourceKind::Osmosis)
+            .map(|(key, _)| *key)
+            .collect::<Vec<&str>>();
+        if osmosis_sources.is_empty() {
+            return Vec::new();
+        }
+        let osmosis = match &self.osmosis {
+            Some(osmosis) => osmosis,
+            None => {
+                return osmosis_sources
+                    .into_iter()
+                    .map(|key| format!("{key}: osmosis needs an [osmosis] section"))
+                    .collect()
+            }
+        };
+
+        let mut problems = Vec::new();
+        if let Some(problem) = url_problem(&osmosis.lcd_endpoint) {
+            problems.push(format!("osmosis.lcd_endpoint: {problem}"));
+        }
+        let mut denoms = self
+            .orders
+            .iter()
+            .map(|o| o.fee_token)
+            .collect::<Vec<Denom>>();
+        if self.orders.iter().any(|o| {
+            o.max_usd_per_token.is_some()
+                || matches!(&o.stop, Some(s) if s.max_somm_price_change_pct.is_some())
+        }) {
+            denoms.push(Denom::USOMM);
+        }
+        let mut seen = HashSet::new();
+        for denom in denoms {
+            if seen.insert(denom) && !osmosis.pools.iter().any(|p| p.denom == denom) {
+                problems.push(format!(
+                    "osmosis.pools: no pool prices {}, which the orders need a USD price for",
+                    denom.symbol()
+                ));
+            }
+        }
+
+        problems
+    }
+}
+
+/// Reads the config at `path`, failing with every problem found in it at once rather than the
+/// first
+pub fn load(path: &str) -> Result<Config> {
+    let contents =
+        std::fs::read_to_string(path).map_err(|err| eyre!("failed to read {path}: {err}"))?;
+    let value = toml::from_str::<toml::Value>(&contents)
+        .map_err(|err| eyre!("{path} is not valid TOML: {err}"))?;
+
+    // unknown denoms fail deserialization one at a time, so collect them all first
+    let mut problems = denom_problems(&value);
+    if problems.is_empty() {
+        match value.try_into::<Config>() {
+            Ok(config) => {
+                problems = config.validate();
+                if problems.is_empty() {
+                    return Ok(config);
+                }
+            }
+            Err(err) => problems.push(err.to_string()),
+        }
+    }
+
+    Err(eyre!(
+        "found {} problems in {path}:\n  - {}",
+        problems.len(),
+        problems.join("\n  - ")
+    ))
+}
+
+// Every denom in the raw config that isn't one the engine knows
+fn denom_problems(value: &toml::Value) -> Vec<String> {
+    let mut denoms = Vec::new();
+    let tables = |key: &str| {
+        value
+            .get(key)
+            .and_then(|v| v.as_array())
+            .map(|a| a.as_slice())
+            .unwrap_or_default()
+    };
+    for (i, order) in tables("orders").iter().enumerate() {
+        denoms.push((format!("orders[{i}].fee_token"), order.get("fee_token")));
+    }
+    for list in ["allow", "block"] {
+        let entries = value
+            .get("denoms")
+            .and_then(|d| d.get(list))
+            .and_then(|l| l.as_array());
+        for (i, denom) in entries.into_iter().flatten().enumerate() {
+            denoms.push((format!("denoms.{list}[{i}]"), Some(denom)));
+        }
+    }
+    let pools = value
+        .get("osmosis")
+        .and_then(|o| o.get("pools"))
+        .and_then(|p| p.as_array());
+    for (i, pool) in pools.into_iter().flatten().enumerate() {
+        denoms.push((format!("osmosis.pools[{i}].denom"), pool.get("denom")));
+    }
+
+    denoms
+        .into_iter()
+        .filter_map(|(key, denom)| match denom?.as_str() {
+            Some(denom) if Denom::from_str(denom).is_ok() => None,
+            Some(denom) => Some(format!("{key}: unknown denom \"{denom}\"")),
+            None => Some(format!("{key}: expected a denom string")),
+        })
+        .collect()
+}
+
+/// Every problem with an order that would keep it from bidding, given the names of the configured
+/// wallets and strategies. Checked for configured orders and for orders created through the admin
+/// APIs and the dashboard. Each problem starts with the field it's about.
+pub fn order_problems(order: &Order, wallets: &[String], strategies: &[String]) -> Vec<String> {
+    let mut problems = Vec::new();
+    let wallet_problem = |key: String, name: &str| match wallets.is_empty() {
+        false if !wallets.iter().any(|w| w == name) => {
+            Some(format!("{key}: no wallet in [[wallets]] is named {name}"))
+        }
+        true if name != DEFAULT_WALLET_NAME => Some(format!(
+            "{key}: names wallet {name} but no [[wallets]] are configured"
+        )),
+        _ => None,
+    };
+
+    if order.fee_token == Denom::USOMM {
+        problems.push("fee_token: usomm isn't auctioned".to_string());
+    }
+    if order.maximum_usomm_in == 0 {
+        problems.push("maximum_usomm_in: a zero budget can't bid".to_string());
+    }
+    if matches!(&order.dca, Some(dca) if dca.usomm_per_bid == 0) {
+        problems.push("dca.usomm_per_bid: a zero bid size can't bid".to_string());
+    }
+    if matches!(&order.split, Some(split) if split.parts == 0) {
+        problems.push("split.parts: zero parts can't bid".to_string());
+    }
+    if order.twap_window_secs == Some(0) {
+        problems.push("twap_window_secs: a zero window can't be averaged over".to_string());
+    }
+    if matches!(&order.tx_fee, Some(fee) if fee.denom.is_empty()) {
+        problems.push("tx_fee.denom: is empty".to_string());
+    }
+    if let Some(name) = &order.wallet {
+        problems.extend(wallet_problem("wallet".to_string(), name));
+    }
+    if let Some(group) = &order.group {
+        if order.wallet.is_some() {
+            problems.push("group: can't be set along with wallet".to_string());
+        }
+        if group.iter().all(|m| m.weight == Some(0)) {
+            problems.push("group: needs a member with a nonzero weight".to_string());
+        }
+        for (j, member) in group.iter().enumerate() {
+            problems.extend(wallet_problem(format!("group[{j}].wallet"), &member.wallet));
+        }
+    }
+    if let Some(name) = &order.strategy {
+        if !strategies.contains(name) {
+            problems.push(format!("strategy: no [[strategies]] entry is named {name}"));
+        }
+    }
+
+    problems
+}
+
+fn budget_problems(key: &str, budget: &BudgetConfig) -> Vec<String> {
+    [
+        ("daily_usomm", budget.daily_usomm),
+        ("weekly_usomm", budget.weekly_usomm),
+        ("per_auction_usomm", budget.per_auction_usomm),
+    ]
+    .into_iter()
+    .filter(|(_, limit)| *limit == Some(0))
+    .map(|(limit, _)| format!("{key}.{limit}: a zero budget blocks every bid"))
+    .collect()
+}
+
+fn url_problem(url: &str) -> Option<String> {
+    match reqwest::Url::parse(url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => None,
+        Ok(url) => Some(format!(
+            "expected an http or https URL, got {}",
+            url.scheme()
+        )),
+        Err(err) => Some(format!("invalid URL {url}: {err}")),
+    }
+}
+
+fn listen_addr_problem(addr: &str) -> Option<String> {
+    match addr.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => None,
+        _ => Some(format!("expected host:port, got {addr}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let config = toml::from_str::<Config>(
+            r#"
+            grpc_endpoint = "sommelier.example.com:9090"
+            admin_listen_addr = "127.0.0.1"
+
+            [budget]
+            daily_usomm = 0
+
+            [tx_fee]
+            gas_prices = ["0.025"]
+
+            [notifications.email]
+            smtp_host = "smtp.example.com"
+            from = "bot@example.com"
+            recipients = [{ address = "ops", events = ["bid_filled", "fill"] }]
+
+            [[orders]]
+            fee_token = "gravity0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"
+            maximum_usomm_in = 0
+            wallet = "treasury"
+
+            [[orders]]
+            fee_token = "usomm"
+            maximum_usomm_in = 1000
+            strategy = "momentum"
+
+            [[strategies]]
+            name = "momentum"
+            path = ""
+
+            [[strategies]]
+            name = "momentum"
+            path = "momentum.wasm"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.validate(),
+            vec![
+                "grpc_endpoint: expected an http or https URL, got sommelier.example.com",
+                "admin_listen_addr: expected host:port, got 127.0.0.1",
+                "notifications.email.recipients[0].address: invalid email address ops",
+                "notifications.email.recipients[0].events: unknown event fill",
+                "budget.daily_usomm: a zero budget blocks every bid",
+                "tx_fee.gas_prices[0]: gas price 0.025 has no denom",
+                "strategies[0].path: is empty",
+                "strategies[1]: another strategy is already named momentum",
+                "orders[0].maximum_usomm_in: a zero budget can't bid",
+                "orders[0].wallet: names wallet treasury but no [[wallets]] are configured",
+                "orders[1].fee_token: usomm isn't auctioned",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_denom_problems() {
+        let value = toml::from_str::<toml::Value>(
+            r#"
+            [denoms]
+            block = ["gravity0xnope"]
+
+            [[orders]]
+            fee_token = "usdc"
+            maximum_usomm_in = 1000
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            denom_problems(&value),
+            vec![
+                "orders[0].fee_token: unknown denom \"usdc\"",
+                "denoms.block[0]: unknown denom \"gravity0xnope\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_order_problems() {
+        let wallets = vec!["treasury".to_string()];
+        let order = Order {
+            fee_token: Denom::USDC,
+            maximum_usomm_in: 1_000,
+            wallet: Some("treasury".to_string()),
+            ..Default::default()
+        };
+        assert!(order_problems(&order, &wallets, &[]).is_empty());
+
+        let order = Order {
+            maximum_usomm_in: 0,
+            wallet: Some("savings".to_string()),
+            split: Some(crate::order::Split {
+                parts: 0,
+                blocks_between: None,
+            }),
+            ..order
+        };
+        assert_eq!(
+            order_problems(&order, &wallets, &[]),
+            vec![
+                "maximum_usomm_in: a zero budget can't bid",
+                "split.parts: zero parts can't bid",
+                "wallet: no wallet in [[wallets]] is named savings",
+            ]
+        );
+    }
 }
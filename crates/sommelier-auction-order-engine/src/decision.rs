@@ -0,0 +1,156 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+use sommelier_auction::denom::Denom;
+use tracing::debug;
+
+use crate::{order::Order, util::unix_now};
+
+pub const DEFAULT_DECISION_LOG_SIZE: usize = 100;
+
+/// The outcome of considering an order for an auction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    /// The order qualified and a bid was sent for submission
+    Bid,
+    /// The order was evaluated and didn't qualify
+    Pass,
+    /// The order wasn't evaluated, e.g. because a bid is already in flight
+    Skip,
+    /// Evaluation failed, usually because of malformed chain data
+    Error,
+}
+
+/// A record of why an order did or didn't bid on an auction, with the inputs and computed values
+/// the verdict was based on. Values that weren't reached before the verdict are left unset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Decision {
+    /// Unix time of the evaluation
+    pub timestamp: u64,
+    pub order_id: usize,
+    pub auction_id: u32,
+    pub denom: Denom,
+    pub verdict: Verdict,
+    pub reason: Option<String>,
+    /// USD price of one whole token
+    pub usd_price: Option<f64>,
+    /// USD price of one SOMM
+    pub somm_usd_price: Option<f64>,
+    /// The auction's price in usomm per base unit of the token
+    pub auction_unit_price_in_usomm: Option<String>,
+    pub remaining_tokens_for_sale: Option<u128>,
+    pub usomm_offer: Option<u64>,
+    pub tokens_out: Option<u128>,
+    /// `tokens_out` less the order's slippage margin
+    pub minimum_tokens_out: Option<u128>,
+    pub usd_value_out: Option<String>,
+    pub minimum_usd_value_out: f64,
+    pub max_somm_per_token: Option<f64>,
+    pub max_usd_per_token: Option<f64>,
+}
+
+impl Decision {
+    pub fn new(order: &Order, auction_id: u32) -> Self {
+        Self {
+            timestamp: unix_now(),
+            order_id: order.id,
+            auction_id,
+            denom: order.fee_token,
+            verdict: Verdict::Pass,
+            reason: None,
+            usd_price: None,
+            somm_usd_price: None,
+            auction_unit_price_in_usomm: None,
+            remaining_tokens_for_sale: None,
+            usomm_offer: None,
+            tokens_out: None,
+            minimum_tokens_out: None,
+            usd_value_out: None,
+            minimum_usd_value_out: order.minimum_usd_value_out,
+            max_somm_per_token: order.max_somm_per_token,
+            max_usd_per_token: order.max_usd_per_token,
+        }
+    }
+
+    /// A decision not to evaluate the order at all
+    pub fn skip(order: &Order, auction_id: u32, reason: impl Into<String>) -> Self {
+        Self {
+            verdict: Verdict::Skip,
+            reason: Some(reason.into()),
+            ..Self::new(order, auction_id)
+        }
+    }
+
+    /// Emits the decision as a structured debug event
+    pub fn log(&self) {
+        debug!(
+            order_id = self.order_id,
+            auction_id = self.auction_id,
+            denom = ?self.denom,
+            verdict = ?self.verdict,
+            reason = ?self.reason,
+            usd_price = ?self.usd_price,
+            somm_usd_price = ?self.somm_usd_price,
+            auction_unit_price_in_usomm = ?self.auction_unit_price_in_usomm,
+            remaining_tokens_for_sale = ?self.remaining_tokens_for_sale,
+            usomm_offer = ?self.usomm_offer,
+            tokens_out = ?self.tokens_out,
+            minimum_tokens_out = ?self.minimum_tokens_out,
+            usd_value_out = ?self.usd_value_out,
+            minimum_usd_value_out = self.minimum_usd_value_out,
+            max_somm_per_token = ?self.max_somm_per_token,
+            max_usd_per_token = ?self.max_usd_per_token,
+            "order decision"
+        );
+    }
+}
+
+/// The most recent decisions, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct DecisionLog {
+    capacity: usize,
+    decisions: VecDeque<Decision>,
+}
+
+impl DecisionLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            decisions: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Logs the decision and retains it, dropping the oldest decision if the log is full
+    pub fn record(&mut self, decision: Decision) {
+        decision.log();
+        if self.capacity == 0 {
+            return;
+        }
+        if self.decisions.len() == self.capacity {
+            self.decisions.pop_front();
+        }
+
+        self.decisions.push_back(decision);
+    }
+
+    pub fn decisions(&self) -> impl Iterator<Item = &Decision> {
+        self.decisions.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_retains_latest() {
+        let mut log = DecisionLog::new(2);
+        for auction_id in 0..3 {
+            log.record(Decision::skip(&Order::default(), auction_id, "test"));
+        }
+
+        let ids = log.decisions().map(|d| d.auction_id).collect::<Vec<u32>>();
+        assert_eq!(ids, vec![1, 2]);
+    }
+}
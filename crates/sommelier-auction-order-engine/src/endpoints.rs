@@ -0,0 +1,218 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use eyre::{eyre, Result};
+use sommelier_auction::client::Client;
+use sommelier_auction_metrics::ServiceMetrics;
+use tracing::{debug, info, warn};
+
+pub const DEFAULT_CALL_TIMEOUT_SECS: u64 = 10;
+
+// how much each call's outcome moves an endpoint's score
+const SCORE_WEIGHT: f64 = 0.3;
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// A gRPC endpoint and how well it has been answering
+#[derive(Debug, Clone)]
+struct Endpoint {
+    grpc: String,
+    client: Option<Client>,
+    // moving average of call outcomes, 1.0 when every call succeeds
+    score: f64,
+    consecutive_failures: u32,
+    // the endpoint isn't preferred again until then after failing
+    retry_at: Option<Instant>,
+}
+
+/// The gRPC endpoints the watcher queries. Each call goes to the healthiest endpoint, scored by how
+/// its recent calls went, and fails over to the others in turn if it errors or times out. A failed
+/// endpoint is reconnected on its next use and backs off exponentially until it answers again.
+#[derive(Debug, Clone)]
+pub struct EndpointPool {
+    rpc: String,
+    endpoints: Vec<Endpoint>,
+    call_timeout: Duration,
+    metrics: ServiceMetrics,
+}
+
+impl EndpointPool {
+    /// Endpoints are preferred in the order given until their scores differ
+    pub fn new(rpc: String, grpc_endpoints: Vec<String>, call_timeout: Duration) -> Self {
+        let endpoints = grpc_endpoints
+            .into_iter()
+            .map(|grpc| Endpoint {
+                grpc,
+                client: None,
+                score: 1.0,
+                consecutive_failures: 0,
+                retry_at: None,
+            })
+            .collect();
+
+        Self {
+            rpc,
+            endpoints,
+            call_timeout,
+            metrics: ServiceMetrics::default(),
+        }
+    }
+
+    /// Counts every call attempt, and each that fails or times out, in `metrics`
+    pub fn with_metrics(mut self, metrics: ServiceMetrics) -> Self {
+        self.metrics = metrics;
+
+        self
+    }
+
+    /// Runs `call` with a client for the healthiest endpoint, failing over to the next on an error
+    /// or timeout. Returns the last error if every endpoint fails.
+    pub async fn call<T, F, Fut>(&mut self, call: F) -> Result<T>
+    where
+        F: Fn(Client) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut last_err = eyre!("no grpc endpoints are configured");
+        for index in self.ranked(Instant::now()) {
+            let client = match self.connect(index).await {
+                Ok(client) => client,
+                Err(err) => {
+                    last_err = err;
+                    continue;
+                }
+            };
+
+            self.metrics.chain_queries.inc();
+            match tokio::time::timeout(self.call_timeout, call(client)).await {
+                Ok(Ok(value)) => {
+                    self.record_success(index);
+
+                    return Ok(value);
+                }
+                Ok(Err(err)) => last_err = err,
+                Err(_) => {
+                    last_err = eyre!(
+                        "call to {} timed out after {:?}",
+                        self.endpoints[index].grpc,
+                        self.call_timeout
+                    )
+                }
+            }
+            warn!(
+                endpoint = self.endpoints[index].grpc.as_str(),
+                "grpc call failed: {last_err:?}"
+            );
+            self.metrics.chain_query_failures.inc();
+            self.record_failure(index, Instant::now());
+        }
+
+        Err(last_err)
+    }
+
+    // A client for the endpoint, connecting if it has none
+    async fn connect(&mut self, index: usize) -> Result<Client> {
+        if let Some(client) = &self.endpoints[index].client {
+            return Ok(client.clone());
+        }
+
+        let grpc = self.endpoints[index].grpc.clone();
+        debug!("connecting to {grpc}");
+        let connected = tokio::time::timeout(
+            self.call_timeout,
+            Client::with_endpoints(self.rpc.clone(), grpc.clone()),
+        )
+        .await
+        .map_err(|_| {
+            eyre!(
+                "connecting to {grpc} timed out after {:?}",
+                self.call_timeout
+            )
+        })
+        .and_then(|result| result);
+        match connected {
+            Ok(client) => {
+                self.endpoints[index].client = Some(client.clone());
+
+                Ok(client)
+            }
+            Err(err) => {
+                warn!(endpoint = grpc.as_str(), "failed to connect: {err:?}");
+                self.record_failure(index, Instant::now());
+
+                Err(err)
+            }
+        }
+    }
+
+    // Indexes of the endpoints in the order to try them: those not backing off first, then by
+    // score, then in configured order
+    fn ranked(&self, now: Instant) -> Vec<usize> {
+        let mut indexes = (0..self.endpoints.len()).collect::<Vec<usize>>();
+        indexes.sort_by(|a, b| {
+            let (a, b) = (&self.endpoints[*a], &self.endpoints[*b]);
+            let backing_off = |e: &Endpoint| e.retry_at.is_some_and(|at| at > now);
+            backing_off(a)
+                .cmp(&backing_off(b))
+                .then(b.score.total_cmp(&a.score))
+        });
+
+        indexes
+    }
+
+    fn record_success(&mut self, index: usize) {
+        let endpoint = &mut self.endpoints[index];
+        if endpoint.consecutive_failures > 0 {
+            info!(endpoint = endpoint.grpc.as_str(), "grpc endpoint recovered");
+        }
+        endpoint.score += SCORE_WEIGHT * (1.0 - endpoint.score);
+        endpoint.consecutive_failures = 0;
+        endpoint.retry_at = None;
+    }
+
+    fn record_failure(&mut self, index: usize, now: Instant) {
+        let endpoint = &mut self.endpoints[index];
+        endpoint.score -= SCORE_WEIGHT * endpoint.score;
+        endpoint.consecutive_failures += 1;
+        // reconnect on the next use, in case the channel itself is broken
+        endpoint.client = None;
+        let backoff = MIN_BACKOFF
+            .saturating_mul(2u32.saturating_pow(endpoint.consecutive_failures - 1))
+            .min(MAX_BACKOFF);
+        endpoint.retry_at = Some(now + backoff);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranked() {
+        let mut pool = EndpointPool::new(
+            "http://localhost:26657".to_string(),
+            vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            Duration::from_secs(1),
+        );
+        let now = Instant::now();
+        assert_eq!(pool.ranked(now), vec![0, 1, 2]);
+
+        // a failing endpoint drops behind the others, and stays behind them once its backoff ends
+        pool.record_failure(0, now);
+        assert_eq!(pool.ranked(now), vec![1, 2, 0]);
+        assert_eq!(pool.ranked(now + MIN_BACKOFF), vec![1, 2, 0]);
+
+        // while every endpoint is backing off, the best scoring goes first
+        pool.record_failure(1, now);
+        pool.record_failure(1, now);
+        pool.record_failure(2, now);
+        pool.record_failure(2, now);
+        pool.record_failure(2, now);
+        assert_eq!(pool.ranked(now), vec![0, 1, 2]);
+
+        pool.record_success(2);
+        assert_eq!(pool.ranked(now), vec![2, 0, 1]);
+        assert_eq!(pool.endpoints[2].consecutive_failures, 0);
+    }
+}
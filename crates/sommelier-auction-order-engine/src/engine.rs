@@ -1,12 +1,68 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
-use eyre::Result;
+use eyre::{eyre, Result};
 use sommelier_auction::{
-    bid::Bid, client::Client, denom::Denom, parameters::AuctionParameters, AccountInfo,
+    bid::Bid,
+    client::{Client, BID_GAS_LIMIT},
+    denom::Denom,
+    fee::{GasPrice, TxFee},
+    parameters::AuctionParameters,
+    BidResult,
+};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    admin::{self, AdminHandle, EngineState},
+    admin_grpc,
+    allocation::AllocationStrategy,
+    authz,
+    budget::Budget,
+    config::Config,
+    dashboard,
+    decision::DEFAULT_DECISION_LOG_SIZE,
+    endpoints::{EndpointPool, DEFAULT_CALL_TIMEOUT_SECS},
+    evaluate,
+    filter::DenomFilter,
+    health::{self, Health, HealthConfig},
+    intent::{Intent, IntentLog},
+    metrics::{self, Metrics},
+    notify::{self, Event, NotificationConfig, Notifications},
+    order::{Fill, Order, OrderBid},
+    paper::PaperPortfolio,
+    pnl::{self, Pnl, PnlConfig},
+    price::{self, OsmosisConfig, PriceSource, PriceSourceKind},
+    price_updater::{
+        PriceUpdater, DEFAULT_PRICE_REFRESH_INTERVAL_SECS, DEFAULT_PRICE_STALE_AFTER_SECS,
+    },
+    projection::DEFAULT_MAX_PROJECTED_WAIT_SECS,
+    ratelimit::RateLimiter,
+    reconcile::{self, Receipt},
+    reload,
+    risk::RiskManager,
+    state::{self, OrderState},
+    strategy::{Strategies, StrategyConfig},
+    submit::{self, SubmitErrorKind, TxFeeConfig},
+    util::unix_now,
+    wallet::{self, Wallet, WalletConfig, WalletSigner, Wallets},
+    watcher::Watcher,
 };
-use tracing::{debug, error, info};
 
-use crate::{config::Config, order::Order, watcher::Watcher};
+/// Roughly one block
+pub const DEFAULT_REFRESH_INTERVAL_SECS: u64 = 6;
+
+pub const DEFAULT_MAX_BID_ATTEMPTS: u32 = 3;
+
+/// How long shutdown waits for a bid being submitted, and then for the watcher, before giving up on
+/// them
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Name of the wallet used when no wallets are configured
+pub const DEFAULT_WALLET_NAME: &str = "default";
 
 pub struct OrderEngine {
     pub orders: HashMap<Denom, Vec<Order>>,
@@ -14,14 +70,61 @@ pub struct OrderEngine {
     pub grpc_endpoint: String,
     // cache of USD prices of each denom
     pub prices: HashMap<Denom, f64>,
-    // max amount of usomm allowed to use on bids. if there are bids that haven't been submitted
-    // when this is reached, they will be cancelled.
     pub rpc_endpoint: String,
-    //    pub total_usomm_budget: u64,
-    // total amount of usomm that has been spent on bids. this value can never exceed total_usomm_budget
+    // tried in turn by the watcher when the grpc endpoint fails
+    pub fallback_grpc_endpoints: Vec<String>,
+    pub grpc_timeout: Duration,
+    // limits on usomm spent on bids. bids that would exceed them are shrunk or skipped.
+    pub budget: Budget,
+    // total amount of usomm that has been spent on bids
     pub total_usomm_spent: u128,
     pub auction_parameters: Option<AuctionParameters>,
     pub signer_key_path: Option<String>,
+    pub refresh_interval: Duration,
+    pub max_bid_attempts: u32,
+    pub price_source: PriceSourceKind,
+    pub price_refresh_interval: Duration,
+    // prices not refreshed for this long are alerted on
+    pub price_stale_after_secs: u64,
+    pub osmosis: Option<OsmosisConfig>,
+    pub admin_listen_addr: Option<String>,
+    pub admin_grpc_listen_addr: Option<String>,
+    // environment variable holding the token the admin apis require
+    pub admin_token_env: Option<String>,
+    pub dashboard_listen_addr: Option<String>,
+    // environment variable holding the password that enables the dashboard's forms
+    pub dashboard_password_env: Option<String>,
+    pub decision_log_size: usize,
+    pub wallets: Vec<WalletConfig>,
+    pub allocation: AllocationStrategy,
+    pub health: Option<HealthConfig>,
+    pub state_path: Option<String>,
+    pub intent_log_path: Option<String>,
+    // bids broadcast but whose fills aren't recorded yet, if an intent log is configured
+    pub intents: Option<IntentLog>,
+    // config file whose orders are reloaded when it changes, if watching is enabled
+    pub orders_path: Option<String>,
+    pub metrics_listen_addr: Option<String>,
+    pub metrics: Arc<Metrics>,
+    // evaluate orders and log the bids that would be submitted without submitting them
+    pub dry_run: bool,
+    pub notification_config: Option<NotificationConfig>,
+    pub notifications: Notifications,
+    pub max_projected_wait: Duration,
+    // fill bids against a simulated portfolio instead of submitting them
+    pub paper: Option<PaperPortfolio>,
+    pub denom_filter: DenomFilter,
+    pub rate_limiter: RateLimiter,
+    pub pnl_config: Option<PnlConfig>,
+    // spend and receipts of every fill by order and wallet
+    pub pnl: Arc<RwLock<Pnl>>,
+    pub risk: RiskManager,
+    // prices are checked against this source before each bid, if the risk limits ask for it
+    pub reference_prices: Option<Box<dyn PriceSource>>,
+    pub tx_fee: TxFeeConfig,
+    // gas prices bid fees are paid at, in order of preference. bids pay no fee if empty.
+    pub gas_prices: Vec<GasPrice>,
+    pub strategies: Vec<StrategyConfig>,
 }
 
 impl OrderEngine {
@@ -37,18 +140,14 @@ impl OrderEngine {
             sommelier_auction::client::DEFAULT_GRPC_ENDPOINT.to_string()
         };
 
-        // load orders
-        let mut orders = HashMap::<Denom, Vec<Order>>::new();
-        config
-            .orders
-            .into_iter()
-            .for_each(|order| match orders.get_mut(&order.fee_token) {
-                Some(v) => v.push(order),
-                None => {
-                    orders.insert(order.fee_token, vec![order]);
-                }
-            });
+        let refresh_interval = Duration::from_secs(
+            config
+                .refresh_interval_secs
+                .unwrap_or(DEFAULT_REFRESH_INTERVAL_SECS),
+        );
 
+        // load orders
+        let orders = group_orders(config.orders);
         debug!("loaded orders: {:?}", orders);
 
         Self {
@@ -57,24 +156,298 @@ impl OrderEngine {
             grpc_endpoint,
             prices: HashMap::new(),
             rpc_endpoint,
-            //            total_usomm_budget: config.total_usomm_budget,
+            fallback_grpc_endpoints: config.fallback_grpc_endpoints.unwrap_or_default(),
+            grpc_timeout: Duration::from_secs(
+                config
+                    .grpc_timeout_secs
+                    .unwrap_or(DEFAULT_CALL_TIMEOUT_SECS)
+                    .max(1),
+            ),
+            budget: Budget::new(config.budget.unwrap_or_default()),
             total_usomm_spent: 0,
             auction_parameters: None,
             signer_key_path: config.key_path,
+            refresh_interval,
+            max_bid_attempts: config
+                .max_bid_attempts
+                .unwrap_or(DEFAULT_MAX_BID_ATTEMPTS)
+                .max(1),
+            price_source: config.price_source.unwrap_or_default(),
+            price_refresh_interval: Duration::from_secs(
+                config
+                    .price_refresh_interval_secs
+                    .unwrap_or(DEFAULT_PRICE_REFRESH_INTERVAL_SECS)
+                    .max(1),
+            ),
+            price_stale_after_secs: config
+                .price_stale_after_secs
+                .unwrap_or(DEFAULT_PRICE_STALE_AFTER_SECS),
+            osmosis: config.osmosis,
+            admin_listen_addr: config.admin_listen_addr,
+            admin_grpc_listen_addr: config.admin_grpc_listen_addr,
+            admin_token_env: config.admin_token_env,
+            dashboard_listen_addr: config.dashboard_listen_addr,
+            dashboard_password_env: config.dashboard_password_env,
+            decision_log_size: config
+                .decision_log_size
+                .unwrap_or(DEFAULT_DECISION_LOG_SIZE),
+            wallets: config.wallets.unwrap_or_default(),
+            allocation: config.allocation.unwrap_or_default(),
+            health: config.health,
+            state_path: config.state_path,
+            intent_log_path: config.intent_log_path,
+            intents: None,
+            orders_path: None,
+            metrics_listen_addr: config.metrics_listen_addr,
+            metrics: Arc::new(Metrics::default()),
+            dry_run: config.dry_run.unwrap_or_default(),
+            notification_config: config.notifications,
+            notifications: Notifications::default(),
+            max_projected_wait: Duration::from_secs(
+                config
+                    .max_projected_wait_secs
+                    .unwrap_or(DEFAULT_MAX_PROJECTED_WAIT_SECS),
+            ),
+            paper: config.paper.map(PaperPortfolio::new),
+            denom_filter: config.denoms.unwrap_or_default(),
+            rate_limiter: RateLimiter::new(config.rate_limit.unwrap_or_default()),
+            pnl_config: config.pnl,
+            pnl: Arc::new(RwLock::new(Pnl::default())),
+            risk: RiskManager::new(config.risk.unwrap_or_default()),
+            reference_prices: None,
+            tx_fee: config.tx_fee.unwrap_or_default(),
+            gas_prices: Vec::new(),
+            strategies: config.strategies.unwrap_or_default(),
         }
     }
 
     pub async fn start(&mut self) -> Result<()> {
         info!("starting auction bot");
-        let mut watcher = Some(Watcher::new(
+        if let Some(path) = self.state_path.as_deref() {
+            if let Some(state) = state::load(path)? {
+                info!(
+                    "resuming {} orders saved to {path} at {}",
+                    state.orders.len(),
+                    state.saved_at
+                );
+                self.orders = group_orders(state.orders);
+                if self.orders_path.take().is_some() {
+                    warn!(
+                        "orders were resumed from {path}, so config file changes won't be watched"
+                    );
+                }
+            }
+        }
+        let configured_orders = self
+            .orders
+            .values()
+            .flatten()
+            .map(|o| (o.id, o.clone()))
+            .collect::<Vec<(usize, Order)>>();
+
+        // bids a crash left unrecorded are settled before the watcher sees the orders
+        let mut recovered = Vec::new();
+        if let Some(path) = self.intent_log_path.clone() {
+            self.intents = Some(IntentLog::open(&path)?);
+            recovered = self.recover_intents().await?;
+        }
+        self.metrics.record_budget(&self.budget.usage(unix_now()));
+
+        let price_source = price::price_source(
+            self.price_source,
+            self.grpc_endpoint.clone(),
+            self.osmosis.clone(),
+        )?;
+        if let Some(kind) = self.risk.reference_price_source()? {
+            self.reference_prices = Some(price::price_source(
+                kind,
+                self.grpc_endpoint.clone(),
+                self.osmosis.clone(),
+            )?);
+        }
+        let (price_updater, price_feed) = PriceUpdater::new(
+            price_source,
+            self.price_refresh_interval,
+            self.price_stale_after_secs,
+        );
+        let strategies = Strategies::load(&self.strategies)?;
+        let (fill_tx, fill_rx) = tokio::sync::mpsc::unbounded_channel::<Fill>();
+        let mut watcher = Watcher::new(
             self.orders.clone(),
+            self.rpc_endpoint.clone(),
             self.grpc_endpoint.clone(),
+            self.refresh_interval,
+            price_feed,
+            fill_rx,
+        )
+        .with_decision_log_size(self.decision_log_size)
+        .with_allocation(self.allocation)
+        .with_max_projected_wait(self.max_projected_wait)
+        .with_denom_filter(self.denom_filter.clone())
+        .with_strategies(strategies)
+        .with_endpoints(
+            EndpointPool::new(
+                self.rpc_endpoint.clone(),
+                std::iter::once(self.grpc_endpoint.clone())
+                    .chain(self.fallback_grpc_endpoints.iter().cloned())
+                    .collect(),
+                self.grpc_timeout,
+            )
+            .with_metrics(self.metrics.service.clone()),
+        );
+        for (denom, orders) in &self.orders {
+            if let Some(reason) = self.denom_filter.rejects(denom) {
+                warn!(
+                    "{denom:?} is {reason}, so its {} orders will never bid",
+                    orders.len()
+                );
+            }
+        }
+        let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+        watcher = watcher
+            .with_shutdown(shutdown_rx)
+            .with_metrics(self.metrics.clone())
+            .with_pnl(self.pnl.clone());
+        if let Some(paper) = &self.paper {
+            info!(
+                "paper trading enabled, bids will fill against a virtual balance of {} usomm",
+                paper.usomm_balance
+            );
+        } else if self.dry_run {
+            info!("dry run enabled, bids will be logged instead of submitted");
+        }
+
+        let notifiers = match self.notification_config.clone() {
+            Some(config) => Some(notify::notifiers(config)?),
+            None => None,
+        };
+        let admin_token = match &self.admin_token_env {
+            Some(env) => {
+                Some(std::env::var(env).map_err(|_| eyre!("admin token not found in {env}"))?)
+            }
+            None => None,
+        };
+        for listen_addr in [&self.admin_listen_addr, &self.admin_grpc_listen_addr]
+            .into_iter()
+            .flatten()
+        {
+            admin::check_listen_addr(listen_addr, admin_token.as_deref())?;
+        }
+        let dashboard_password = match &self.dashboard_password_env {
+            Some(env) => Some(
+                std::env::var(env).map_err(|_| eyre!("dashboard password not found in {env}"))?,
+            ),
+            None => None,
+        };
+
+        // admin api servers and dashboard, the health and metrics endpoints, notifications, price
+        // updates, and balance checks. all are aborted on shutdown.
+        let mut background_tasks = Vec::new();
+        let admin_enabled = self.admin_listen_addr.is_some()
+            || self.admin_grpc_listen_addr.is_some()
+            || self.dashboard_listen_addr.is_some();
+        if admin_enabled || self.orders_path.is_some() {
+            let (command_tx, command_rx) = tokio::sync::mpsc::unbounded_channel();
+            let state = Arc::new(RwLock::new(EngineState::default()));
+            watcher = watcher.with_admin(command_rx, state.clone());
+            if let Some(path) = self.orders_path.clone() {
+                let commands = command_tx.clone();
+                background_tasks.push(tokio::spawn(async move {
+                    if let Err(err) = reload::watch_orders(path, configured_orders, commands).await
+                    {
+                        error!("config file watcher returned an error: {:?}", err);
+                    }
+                }));
+            }
+            let handle = AdminHandle {
+                commands: command_tx,
+                state,
+                pnl: self.pnl.clone(),
+                wallets: self.wallets.iter().map(|w| w.name.clone()).collect(),
+                strategies: self.strategies.iter().map(|s| s.name.clone()).collect(),
+            };
+
+            if let Some(listen_addr) = self.admin_listen_addr.clone() {
+                let handle = handle.clone();
+                let token = admin_token.clone();
+                background_tasks.push(tokio::spawn(async move {
+                    if let Err(err) = admin::serve(listen_addr, handle, token).await {
+                        error!("admin api server returned an error: {:?}", err);
+                    }
+                }));
+            }
+            if let Some(listen_addr) = self.dashboard_listen_addr.clone() {
+                let handle = handle.clone();
+                background_tasks.push(tokio::spawn(async move {
+                    if let Err(err) =
+                        dashboard::serve(listen_addr, handle, dashboard_password).await
+                    {
+                        error!("dashboard returned an error: {:?}", err);
+                    }
+                }));
+            }
+            if let Some(listen_addr) = self.admin_grpc_listen_addr.clone() {
+                background_tasks.push(tokio::spawn(async move {
+                    if let Err(err) = admin_grpc::serve(listen_addr, handle, admin_token).await {
+                        error!("admin grpc server returned an error: {:?}", err);
+                    }
+                }));
+            }
+        }
+
+        // health endpoint
+        let health_state = Arc::new(RwLock::new(Health::new(unix_now())));
+        if let Some(config) = self.health.clone() {
+            watcher = watcher.with_health(health_state.clone());
+            let health_state = health_state.clone();
+            background_tasks.push(tokio::spawn(async move {
+                if let Err(err) = health::serve(config, health_state).await {
+                    error!("health endpoint returned an error: {:?}", err);
+                }
+            }));
+        }
+
+        if let Some(notifiers) = notifiers {
+            let (notifications, task) = Notifications::spawn(notifiers);
+            watcher = watcher.with_notifications(notifications.clone());
+            self.notifications = notifications;
+            background_tasks.push(task);
+        }
+        background_tasks.push(tokio::spawn(
+            price_updater
+                .with_notifications(self.notifications.clone())
+                .run(),
         ));
+        if let Some(interval) = self
+            .pnl_config
+            .as_ref()
+            .and_then(|c| c.summary_interval_secs)
+        {
+            if self.notification_config.is_none() {
+                warn!("pnl summaries are enabled but no notifications are configured");
+            }
+            background_tasks.push(tokio::spawn(pnl::send_summaries(
+                self.pnl.clone(),
+                self.notifications.clone(),
+                Duration::from_secs(interval.max(1)),
+            )));
+        }
 
-        let (tx, mut rx) = tokio::sync::mpsc::channel::<Bid>(self.orders.len());
+        if let Some(listen_addr) = self.metrics_listen_addr.clone() {
+            let metrics = self.metrics.clone();
+            background_tasks.push(tokio::spawn(async move {
+                if let Err(err) = metrics::serve(listen_addr, metrics).await {
+                    error!("metrics server returned an error: {:?}", err);
+                }
+            }));
+        }
+        let mut watcher = Some(watcher);
+
+        // orders can be added through the admin api, so there may be none to start with
+        let (tx, mut rx) = tokio::sync::mpsc::channel::<OrderBid>(self.orders.len().max(1));
 
         // auction monitoring thread
-        let handle = tokio::spawn(async move {
+        let mut handle = tokio::spawn(async move {
             info!("starting watcher thread");
             loop {
                 if let Err(err) = watcher.as_mut().unwrap().monitor_auctions(tx.clone()).await {
@@ -85,36 +458,740 @@ impl OrderEngine {
                 // intentional shutdown
                 break;
             }
+
+            watcher
         });
 
-        // bid submission service
-        let sender = if let Some(key_path) = self.signer_key_path.clone() {
-            AccountInfo::from_pem(&key_path).expect("failed to load key")
-        } else if let Ok(mnemonic) = std::env::var("SOMMELIER_AUCTION_MNEMONIC") {
-            AccountInfo::from_mnemonic(&mnemonic, "")
-                .expect("failed to construct signer from mnemonic")
+        // bid submission service. if it can't start, the watcher and background tasks are stopped
+        // before returning the error.
+        let setup = async {
+            let mut wallets = self.load_wallets()?;
+            let mut client =
+                Client::with_endpoints(self.rpc_endpoint.clone(), self.grpc_endpoint.clone())
+                    .await?;
+            authz::validate_grants(&mut client, &mut wallets, unix_now()).await?;
+            // addresses whose balances the health endpoint watches
+            let addresses = match self.health {
+                Some(_) => wallets
+                    .iter()
+                    .map(|w| Ok((w.name.clone(), w.bidder()?)))
+                    .collect::<Result<Vec<(String, String)>>>()?,
+                None => Vec::new(),
+            };
+
+            Ok::<_, eyre::Report>((wallets, client, addresses))
+        };
+        let (mut wallets, mut client, addresses) = match setup.await {
+            Ok(setup) => setup,
+            Err(err) => {
+                handle.abort();
+                background_tasks.iter().for_each(|s| s.abort());
+                return Err(err);
+            }
+        };
+        if self.health.is_some() {
+            background_tasks.push(tokio::spawn(health::watch_balances(
+                client.clone(),
+                addresses,
+                health_state,
+            )));
+        }
+        match client.auction_parameters().await {
+            Ok(parameters) => self.auction_parameters = Some(parameters),
+            Err(err) => warn!(
+                "failed to query auction parameters, minimum bid checks are disabled: {err:?}"
+            ),
+        }
+        for (name, denom, fill) in recovered {
+            if let Some(wallet) = wallets.iter_mut().find(|w| w.name == name) {
+                wallet
+                    .budget
+                    .record(fill.auction_id, denom, fill.usomm_spent, unix_now());
+            }
+        }
+        match submit::gas_prices(&mut client, &self.tx_fee).await {
+            Ok(gas_prices) => {
+                info!("paying bid fees at gas prices {gas_prices:?}");
+                self.gas_prices = gas_prices;
+            }
+            Err(err) => warn!("failed to get minimum gas prices, bids will pay no fee: {err:?}"),
+        }
+
+        // stop on ctrl-c or SIGTERM, letting a bid that's being submitted finish first
+        let mut shutdown = std::pin::pin!(shutdown_signal());
+        loop {
+            let order_bid = tokio::select! {
+                _ = &mut shutdown => {
+                    info!("shutdown requested");
+                    break;
+                }
+                order_bid = rx.recv() => match order_bid {
+                    Some(order_bid) => order_bid,
+                    None => break,
+                },
+            };
+
+            let submission =
+                std::pin::pin!(self.process_bid(&mut client, &mut wallets, &fill_tx, order_bid));
+            tokio::select! {
+                _ = &mut submission => {}
+                _ = &mut shutdown => {
+                    info!(
+                        "shutdown requested, waiting up to {} seconds for the bid being submitted",
+                        SHUTDOWN_TIMEOUT.as_secs()
+                    );
+                    let _ = shutdown_tx.send(true);
+                    if tokio::time::timeout(SHUTDOWN_TIMEOUT, submission).await.is_err() {
+                        warn!("bid submission did not finish before the shutdown timeout. it may still land on chain.");
+                    }
+
+                    break;
+                }
+            }
+        }
+
+        // stop the watcher and save what the orders have left to spend
+        let _ = shutdown_tx.send(true);
+        rx.close();
+        let watcher = match tokio::time::timeout(SHUTDOWN_TIMEOUT, &mut handle).await {
+            Ok(Ok(watcher)) => watcher,
+            Ok(Err(err)) => {
+                error!("watcher thread failed: {:?}", err);
+
+                None
+            }
+            Err(_) => {
+                warn!("watcher did not stop before the shutdown timeout");
+                handle.abort();
+
+                None
+            }
+        };
+        if let (Some(path), Some(watcher)) = (self.state_path.as_deref(), watcher) {
+            let state = OrderState {
+                saved_at: unix_now(),
+                orders: watcher.into_orders(),
+            };
+            match state::save(path, &state) {
+                Ok(()) => info!("saved {} orders to {path}", state.orders.len()),
+                Err(err) => error!("failed to save order state: {:?}", err),
+            }
+        }
+
+        background_tasks.iter().for_each(|s| s.abort());
+
+        if let Some(paper) = &self.paper {
+            info!("final paper portfolio: {paper}");
+        }
+        info!("shutdown complete");
+
+        Ok(())
+    }
+
+    // Submits a bid from the wallet it's routed to and reports the fill back to the watcher
+    async fn process_bid(
+        &mut self,
+        client: &mut Client,
+        wallets: &mut Wallets,
+        fill_tx: &UnboundedSender<Fill>,
+        order_bid: OrderBid,
+    ) {
+        let OrderBid {
+            order_id,
+            bid,
+            wallet,
+            usd_price,
+            somm_usd_price,
+            tx_fee,
+        } = order_bid;
+        let auction_id = bid.auction_id;
+        let wallet = match wallets.select(wallet.as_deref()) {
+            Ok(wallet) => wallet,
+            Err(err) => {
+                error!("skipping bid for order {order_id}: {err}");
+                self.report_fill(fill_tx, Fill::failed(order_id, auction_id));
+
+                return;
+            }
+        };
+        let bid = match self.apply_budget(bid, wallet) {
+            Some(bid) => bid,
+            None => {
+                self.report_fill(fill_tx, Fill::skipped(order_id, auction_id));
+
+                return;
+            }
+        };
+
+        if let Some(reason) = self.check_risk(&bid, usd_price, somm_usd_price).await {
+            warn!("skipping bid for order {order_id}: {reason}");
+            self.report_fill(fill_tx, Fill::skipped(order_id, bid.auction_id));
+
+            return;
+        }
+
+        match submit::preflight(client, &bid, self.auction_parameters.as_ref()).await {
+            Ok(Some(reason)) => {
+                warn!("skipping bid for order {order_id}: {reason}");
+                self.report_fill(fill_tx, Fill::skipped(order_id, bid.auction_id));
+
+                return;
+            }
+            Ok(None) => {}
+            Err(err) => warn!("pre-flight checks failed, submitting bid anyway: {err:?}"),
+        }
+        if let Some(reason) = authz::preflight(wallet, unix_now()) {
+            warn!("skipping bid for order {order_id}: {reason}");
+            self.report_fill(fill_tx, Fill::skipped(order_id, bid.auction_id));
+
+            return;
+        }
+        // paper fills spend a virtual balance, so only real bids need funds on chain
+        if self.paper.is_none() {
+            let fee = match tx_fee {
+                Some(fee) => fee,
+                None => match submit::select_fee(client, wallet, &bid, &self.gas_prices).await {
+                    Ok(Some(fee)) => fee,
+                    Ok(None) => {
+                        warn!(
+                            "skipping bid for order {order_id}: wallet {} can't pay a fee at any of the gas prices",
+                            wallet.name
+                        );
+                        self.report_fill(fill_tx, Fill::skipped(order_id, bid.auction_id));
+
+                        return;
+                    }
+                    Err(err) => {
+                        warn!("fee selection failed, paying at the first gas price: {err:?}");
+                        self.gas_prices
+                            .first()
+                            .map(|p| p.fee(BID_GAS_LIMIT))
+                            .unwrap_or_default()
+                    }
+                },
+            };
+            debug!("bid for order {order_id} pays a fee of {fee:?}");
+            match submit::check_funds(client, wallet, &bid, &fee).await {
+                Ok(Some(reason)) => {
+                    warn!("skipping bid for order {order_id}: {reason}");
+                    self.report_fill(fill_tx, Fill::skipped(order_id, bid.auction_id));
+
+                    return;
+                }
+                Ok(None) => {}
+                Err(err) => warn!("balance checks failed, submitting bid anyway: {err:?}"),
+            }
+            client.set_tx_fee(fee);
+        }
+
+        if self.dry_run && self.paper.is_none() {
+            info!(
+                "dry run, not submitting bid for order {order_id} from wallet {}: {bid:?}",
+                wallet.name
+            );
+            self.report_fill(fill_tx, Fill::skipped(order_id, bid.auction_id));
+
+            return;
+        }
+
+        let mut intent_key = None;
+        let fill = if let Some(paper) = self.paper.as_mut() {
+            let fill = paper_fill(paper, client, order_id, &bid, usd_price, somm_usd_price).await;
+            info!("paper fill for order {order_id}: {fill:?}");
+            info!("paper portfolio: {paper}");
+
+            fill
         } else {
-            handle.abort();
-            panic!("no signer key provided and no mnemonic found in environment. either provide a key_path in the config or set SOMMELIER_AUCTION_MNEMONIC in the environment to a 24 word phrase.");
+            let height = if self.rate_limiter.needs_height() {
+                client.latest_block_height().await.ok()
+            } else {
+                None
+            };
+            if let Some(reason) = self.rate_limiter.check(unix_now(), height) {
+                warn!("skipping bid for order {order_id}: {reason}");
+                self.report_fill(fill_tx, Fill::skipped(order_id, bid.auction_id));
+
+                return;
+            }
+            self.rate_limiter.record(unix_now(), height);
+
+            // bids already in the auction, so an identical earlier one isn't mistaken for this one
+            // when checking whether it landed
+            let after_bid_id = match submit::last_bid_id(client, bid.auction_id).await {
+                Ok(id) => id,
+                Err(err) => {
+                    warn!("skipping bid for order {order_id}, failed to query the auction's bids: {err:?}");
+                    self.report_fill(fill_tx, Fill::skipped(order_id, bid.auction_id));
+
+                    return;
+                }
+            };
+
+            // without a record of the bid, a crash before its fill is recorded could bid twice
+            if let Some(intents) = self.intents.as_mut() {
+                let intent = wallet.bidder().map(|bidder| {
+                    Intent::new(
+                        order_id,
+                        &wallet.name,
+                        bidder,
+                        bid.clone(),
+                        after_bid_id,
+                        unix_now(),
+                    )
+                });
+                if let Err(err) = intent.and_then(|intent| {
+                    intent_key = Some(intent.key.clone());
+                    intents.record(intent)
+                }) {
+                    error!(
+                        "skipping bid for order {order_id}, failed to record its intent: {err:?}"
+                    );
+                    self.report_fill(fill_tx, Fill::skipped(order_id, bid.auction_id));
+
+                    return;
+                }
+            }
+
+            self.notifications.send(Event::BidSubmitted {
+                order_id,
+                auction_id: bid.auction_id,
+                wallet: wallet.name.clone(),
+                maximum_usomm_in: bid.maximum_usomm_in,
+                minimum_tokens_out: bid.minimum_tokens_out,
+            });
+            let result = submit::submit_bid_with_retry(
+                client,
+                wallet,
+                &bid,
+                after_bid_id,
+                self.max_bid_attempts,
+            )
+            .await;
+            match result {
+                Ok(result) => {
+                    self.reconcile(client, wallet, order_id, &bid, after_bid_id, Some(result))
+                        .await
+                }
+                Err(err) if submit::classify(&err) == SubmitErrorKind::Permanent => {
+                    error!("bid failed and will not be retried: {:?}", err);
+                    self.notifications.send(Event::BidFailed {
+                        order_id,
+                        auction_id: bid.auction_id,
+                        wallet: wallet.name.clone(),
+                        reason: err.to_string(),
+                    });
+
+                    Fill::failed(order_id, bid.auction_id)
+                }
+                Err(err) => {
+                    error!("error submitting bid: {:?}", err);
+                    info!("this is likely a client timeout and the bid may be submitted successfully on chain. checking.");
+
+                    self.reconcile(client, wallet, order_id, &bid, after_bid_id, None)
+                        .await
+                }
+            }
         };
 
+        self.total_usomm_spent += fill.usomm_spent;
+        self.risk
+            .record(bid.fee_token, &fill, somm_usd_price, unix_now());
+        pnl::update(&self.pnl, |p| {
+            p.record(
+                &wallet.name,
+                bid.fee_token,
+                &fill,
+                usd_price,
+                somm_usd_price,
+            )
+        });
+        self.budget
+            .record(bid.auction_id, bid.fee_token, fill.usomm_spent, unix_now());
+        wallet
+            .budget
+            .record(bid.auction_id, bid.fee_token, fill.usomm_spent, unix_now());
+        let usage = self.budget.usage(unix_now());
+        self.metrics.record_budget(&usage);
+        info!("budget usage: {usage}");
+        info!(
+            "wallet {} budget usage: {}",
+            wallet.name,
+            wallet.budget.usage(unix_now())
+        );
+
+        // the watcher only goes away once every order is exhausted or the engine is shutting down
+        self.report_fill(fill_tx, fill);
+        if let (Some(intents), Some(key)) = (self.intents.as_mut(), intent_key) {
+            if let Err(err) = intents.resolve(&key) {
+                warn!("failed to resolve bid intent {key}, it will be checked again on restart: {err:?}");
+            }
+        }
+    }
+
+    // Looks up the bids of intents left by a previous run on chain. A bid that landed has its fill
+    // applied to its order and the budgets so it isn't placed again, and one that didn't is
+    // forgotten. Intents that can't be checked are kept for the next start. Returns the fills
+    // recovered, with the name of the wallet each was bid from and the token it bid for.
+    async fn recover_intents(&mut self) -> Result<Vec<(String, Denom, Fill)>> {
+        let pending = match &self.intents {
+            Some(intents) if !intents.pending().is_empty() => intents.pending().to_vec(),
+            _ => return Ok(Vec::new()),
+        };
+        info!(
+            "checking {} bids left unrecorded by the last run",
+            pending.len()
+        );
         let mut client =
             Client::with_endpoints(self.rpc_endpoint.clone(), self.grpc_endpoint.clone()).await?;
-        while let Some(bid) = rx.recv().await {
-            if let Err(err) = client.submit_bid(&sender, bid.clone()).await {
-                error!("error submitting bid: {:?}", err);
-                info!("this is likely a client timeout and the bid may be submitted successfully on chain.");
+
+        let mut recovered = Vec::new();
+        for intent in pending {
+            let landed = match submit::find_landed_bid(
+                &mut client,
+                &intent.bidder,
+                &intent.bid,
+                intent.after_bid_id,
+            )
+            .await
+            {
+                Ok(landed) => landed,
+                Err(err) => {
+                    warn!(
+                        "failed to check bid intent {}, keeping it: {err:?}",
+                        intent.key
+                    );
+
+                    continue;
+                }
+            };
+            match landed {
+                Some(result) => {
+                    let fill = Fill::from_bid_result(intent.order_id, &intent.bid, &result);
+                    info!(
+                        "bid intent {} landed before the engine stopped: {fill:?}",
+                        intent.key
+                    );
+                    self.apply_recovered_fill(&intent, &fill);
+                    recovered.push((intent.wallet.clone(), intent.bid.fee_token, fill));
+                }
+                None => info!(
+                    "bid intent {} never landed on chain, forgetting it",
+                    intent.key
+                ),
             }
 
-            // to keep things simple and cautious we optimistically update the total_usomm_spent here.
-            // in reality the spent amount could be less.
-            self.total_usomm_spent += bid.maximum_usomm_in;
+            if let Some(intents) = self.intents.as_mut() {
+                intents.resolve(&intent.key)?;
+            }
         }
 
-        handle.abort();
+        Ok(recovered)
+    }
 
-        info!("shutdown complete");
+    // Spends a recovered fill from its order and the engine's budget. Order IDs follow the order
+    // orders are loaded in, so the fill is only applied to an order of the same token.
+    fn apply_recovered_fill(&mut self, intent: &Intent, fill: &Fill) {
+        let now = unix_now();
+        self.total_usomm_spent += fill.usomm_spent;
+        self.budget
+            .record(fill.auction_id, intent.bid.fee_token, fill.usomm_spent, now);
+        self.metrics.record_budget(&self.budget.usage(now));
+        self.risk.record(intent.bid.fee_token, fill, None, now);
+        pnl::update(&self.pnl, |p| {
+            p.record(&intent.wallet, intent.bid.fee_token, fill, None, None)
+        });
 
-        Ok(())
+        let orders = self.orders.entry(intent.bid.fee_token).or_default();
+        match orders.iter_mut().find(|o| o.id == intent.order_id) {
+            Some(order) => {
+                order.spend(u64::try_from(fill.usomm_spent).unwrap_or(u64::MAX));
+                info!(
+                    "order {} has {} usomm remaining after its recovered fill",
+                    order.id, order.maximum_usomm_in
+                );
+            }
+            None => warn!(
+                "order {} of bid intent {} is gone, its fill isn't applied to an order",
+                intent.order_id, intent.key
+            ),
+        }
+        orders.retain(|o| o.maximum_usomm_in > 0);
+    }
+
+    // Checks a submitted bid's on-chain record to find what it actually spent and received,
+    // flagging any discrepancy. `reported` is the bid the transaction returned, or None if its
+    // outcome is unknown, in which case only bids after `after_bid_id` are taken as this one.
+    async fn reconcile(
+        &self,
+        client: &mut Client,
+        wallet: &Wallet,
+        order_id: usize,
+        bid: &Bid,
+        after_bid_id: u64,
+        reported: Option<BidResult>,
+    ) -> Fill {
+        let receipt = match wallet.bidder() {
+            Ok(bidder) => {
+                reconcile::reconcile(client, &bidder, bid, after_bid_id, reported.as_ref()).await
+            }
+            Err(err) => Err(err),
+        };
+        let receipt = match receipt {
+            Ok(receipt) => receipt,
+            Err(err) => {
+                warn!("failed to reconcile bid for order {order_id}: {err:?}");
+                // fall back to the transaction response, or a not found receipt if there was none
+                match &reported {
+                    Some(reported) => Receipt::Confirmed(reported.clone()),
+                    None => Receipt::NotFound,
+                }
+            }
+        };
+
+        let discrepancies = match (&receipt, &reported) {
+            (Receipt::Discrepant(_, reasons), _) => reasons.clone(),
+            (Receipt::NotFound, Some(_)) => {
+                vec!["the transaction succeeded but the bid isn't on chain".to_string()]
+            }
+            _ => vec![],
+        };
+        if !discrepancies.is_empty() {
+            warn!(
+                "bid for order {order_id} in auction {} settled unexpectedly: {}",
+                bid.auction_id,
+                discrepancies.join("; ")
+            );
+            self.metrics.bid_discrepancies.inc();
+            self.notifications.send(Event::BidDiscrepancy {
+                order_id,
+                auction_id: bid.auction_id,
+                wallet: wallet.name.clone(),
+                reasons: discrepancies,
+            });
+        }
+
+        match receipt.on_chain().or(reported.as_ref()) {
+            Some(result) => {
+                info!("bid for order {order_id} settled: {result:?}");
+                let fill = Fill::from_bid_result(order_id, bid, result);
+                self.notifications.send(Event::BidFilled {
+                    order_id,
+                    auction_id: bid.auction_id,
+                    wallet: wallet.name.clone(),
+                    usomm_spent: fill.usomm_spent,
+                    tokens_received: fill.tokens_received,
+                });
+
+                fill
+            }
+            None => {
+                self.notifications.send(Event::BidFailed {
+                    order_id,
+                    auction_id: bid.auction_id,
+                    wallet: wallet.name.clone(),
+                    reason: "outcome unknown, the bid may have landed on chain".to_string(),
+                });
+
+                // to keep things simple and cautious we assume the whole bid was spent. in
+                // reality the spent amount could be less.
+                Fill::unknown(order_id, bid)
+            }
+        }
+    }
+
+    // Checks a bid against the risk limits, first checking the prices it was evaluated at against
+    // the reference price source if one is configured. Returns why the bid should be skipped, if
+    // it should.
+    async fn check_risk(
+        &mut self,
+        bid: &Bid,
+        usd_price: Option<f64>,
+        somm_usd_price: Option<f64>,
+    ) -> Option<String> {
+        let reference_prices = self
+            .reference_prices
+            .as_ref()
+            .filter(|_| self.risk.killed().is_none());
+        if let Some(reference_prices) = reference_prices {
+            let prices = [(bid.fee_token, usd_price), (Denom::USOMM, somm_usd_price)]
+                .into_iter()
+                .filter_map(|(denom, price)| Some((denom, price?)))
+                .collect::<HashMap<Denom, f64>>();
+            let denoms = prices.keys().copied().collect::<Vec<Denom>>();
+            match reference_prices.usd_prices(&denoms).await {
+                Ok(reference) => {
+                    if let Some(reason) = self.risk.check_prices(&prices, &reference) {
+                        error!("stopping all bidding until restart: {reason}");
+                        self.notifications.send(Event::BiddingStopped { reason });
+                    }
+                }
+                Err(err) => return Some(format!("failed to query reference prices: {err:?}")),
+            }
+        }
+
+        self.risk.check(bid, usd_price, somm_usd_price, unix_now())
+    }
+
+    fn report_fill(&self, fill_tx: &UnboundedSender<Fill>, fill: Fill) {
+        self.metrics.record_fill(&fill);
+        let _ = fill_tx.send(fill);
+    }
+
+    // Shrinks a bid to fit the remaining engine and wallet budgets, scaling the minimum tokens out by
+    // the same ratio so the unit price doesn't change. Returns None if there is no budget left.
+    fn apply_budget(&mut self, mut bid: Bid, wallet: &mut Wallet) -> Option<Bid> {
+        let now = unix_now();
+        let allowance = match [
+            self.budget.allowance(bid.auction_id, bid.fee_token, now),
+            wallet.budget.allowance(bid.auction_id, bid.fee_token, now),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+        {
+            Some(a) => a,
+            None => return Some(bid),
+        };
+
+        if allowance == 0 {
+            warn!(
+                "usomm budget exhausted, skipping bid from wallet {} for auction {}",
+                wallet.name, bid.auction_id
+            );
+
+            return None;
+        }
+
+        if bid.maximum_usomm_in > allowance {
+            warn!(
+                "bid of {} usomm exceeds remaining budget, reducing it to {} usomm",
+                bid.maximum_usomm_in, allowance
+            );
+            bid.minimum_tokens_out = bid
+                .minimum_tokens_out
+                .checked_mul(allowance)
+                .map(|t| t / bid.maximum_usomm_in)
+                .unwrap_or(bid.minimum_tokens_out / bid.maximum_usomm_in * allowance);
+            bid.maximum_usomm_in = allowance;
+        }
+
+        Some(bid)
+    }
+
+    // Loads the configured wallets, or if there are none a single default wallet from the key path or
+    // mnemonic in the environment
+    fn load_wallets(&self) -> Result<Wallets> {
+        if self.wallets.is_empty() {
+            let signer = wallet::load_signer(self.signer_key_path.as_deref(), None)?;
+
+            return Ok(Wallets::new(vec![Wallet {
+                name: DEFAULT_WALLET_NAME.to_string(),
+                signer: WalletSigner::Key(signer),
+                budget: Budget::default(),
+                granter: None,
+                grant_expires_at: None,
+            }]));
+        }
+
+        let wallets = self
+            .wallets
+            .iter()
+            .cloned()
+            .map(Wallet::load)
+            .collect::<Result<Vec<Wallet>>>()?;
+        info!(
+            "loaded wallets: {}",
+            wallets
+                .iter()
+                .map(|w| w.name.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        );
+
+        Ok(Wallets::new(wallets))
+    }
+}
+
+// Groups orders by fee token, assigning IDs in the order they're listed
+fn group_orders(orders: Vec<Order>) -> HashMap<Denom, Vec<Order>> {
+    let mut grouped = HashMap::<Denom, Vec<Order>>::new();
+    orders.into_iter().enumerate().for_each(|(id, mut order)| {
+        order.id = id;
+        grouped.entry(order.fee_token).or_default().push(order);
+    });
+
+    grouped
+}
+
+// Resolves on ctrl-c or, on unix, SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            error!("failed to listen for ctrl-c: {err}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(err) => {
+                error!("failed to listen for SIGTERM: {err}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+// Fills a bid against the paper portfolio at the auction's current price. Bids on auctions that
+// can't be queried fail.
+async fn paper_fill(
+    paper: &mut PaperPortfolio,
+    client: &mut Client,
+    order_id: usize,
+    bid: &Bid,
+    usd_price: Option<f64>,
+    somm_usd_price: Option<f64>,
+) -> Fill {
+    let auction = match client.active_auction(bid.auction_id).await {
+        Ok(Some(auction)) => auction,
+        Ok(None) => {
+            warn!("auction {} is no longer active", bid.auction_id);
+
+            return Fill::failed(order_id, bid.auction_id);
+        }
+        Err(err) => {
+            warn!("failed to query auction {}: {err:?}", bid.auction_id);
+
+            return Fill::failed(order_id, bid.auction_id);
+        }
+    };
+    match (
+        evaluate::unit_price_in_usomm(&auction),
+        evaluate::remaining_tokens_for_sale(&auction),
+    ) {
+        (Ok(unit_price), Ok(remaining)) => paper.fill(
+            order_id,
+            bid,
+            unit_price,
+            remaining,
+            usd_price,
+            somm_usd_price,
+        ),
+        (Err(err), _) | (_, Err(err)) => {
+            warn!("failed to read auction {}: {err:?}", bid.auction_id);
+
+            Fill::failed(order_id, bid.auction_id)
+        }
     }
 }
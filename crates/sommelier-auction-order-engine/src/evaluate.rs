@@ -0,0 +1,490 @@
+use std::str::FromStr;
+
+use eyre::{eyre, Result};
+use rust_decimal::{
+    prelude::{FromPrimitive, ToPrimitive},
+    Decimal,
+};
+use sommelier_auction::{auction::Auction, bid::Bid};
+use tracing::{debug, info};
+
+use crate::{
+    decision::{Decision, Verdict},
+    order::Order,
+};
+
+const USOMM_PER_SOMM: u64 = 1_000_000;
+
+/// Parses an integer token amount from a chain string
+pub fn parse_amount(amount: &str) -> Result<u128> {
+    amount
+        .parse::<u128>()
+        .map_err(|err| eyre!("invalid token amount {amount:?}: {err}"))
+}
+
+/// Parses an `sdk.Dec` from a chain string. The exponent is lost when it's serialized into a proto,
+/// leaving an integer 1e18 times the value.
+pub fn parse_dec(value: &str) -> Result<Decimal> {
+    Decimal::from_str(value)
+        .map_err(|err| eyre!("invalid decimal {value:?}: {err}"))?
+        .checked_div(Decimal::from(1_000_000_000_000_000_000u64))
+        .ok_or_else(|| eyre!("decimal {value:?} out of range"))
+}
+
+/// Encodes a decimal the way the chain serializes an `sdk.Dec`. The inverse of [`parse_dec`].
+pub fn encode_dec(value: Decimal) -> Result<String> {
+    value
+        .checked_mul(Decimal::from(1_000_000_000_000_000_000u64))
+        .map(|d| d.floor().to_string())
+        .ok_or_else(|| eyre!("decimal {value} out of range"))
+}
+
+/// The auction's current price in usomm for one base unit of the token for sale
+pub fn unit_price_in_usomm(auction: &Auction) -> Result<Decimal> {
+    parse_dec(&auction.current_unit_price_in_usomm)
+        .map_err(|err| eyre!("invalid unit price for auction {}: {err}", auction.id))
+}
+
+/// The auction's remaining tokens for sale in base units
+pub fn remaining_tokens_for_sale(auction: &Auction) -> Result<u128> {
+    let remaining = auction
+        .remaining_tokens_for_sale
+        .as_ref()
+        .ok_or_else(|| eyre!("auction {} has no remaining tokens for sale", auction.id))?;
+
+    parse_amount(&remaining.amount)
+}
+
+/// How far the auction's unit price can still fall before its end block, in basis points of the
+/// current price, projected from `height` at the current decrease rate
+pub fn remaining_decline_bps(auction: &Auction, height: u64) -> Result<Decimal> {
+    let current = unit_price_in_usomm(auction)?;
+    if current.is_zero() {
+        return Ok(Decimal::ZERO);
+    }
+
+    let step = parse_dec(&auction.initial_unit_price_in_usomm)?
+        .checked_mul(parse_dec(&auction.current_price_decrease_rate)?)
+        .ok_or_else(|| eyre!("price decrease of auction {} out of range", auction.id))?;
+    let steps_left =
+        auction.end_block.saturating_sub(height) / auction.price_decrease_block_interval.max(1);
+    let decline = step
+        .checked_mul(Decimal::from(steps_left))
+        .unwrap_or(current)
+        .min(current);
+
+    Ok(decline / current * Decimal::from(10_000))
+}
+
+// Collin: Currently not checking USOMM price in USD and thus not guaranteeing a profitable
+// arbitrage. We're simply checking how much USD value we can get out with the max possible
+// USOMM offer.
+/// Decides whether `order` should bid on `auction` given the USD price of one whole token and, if
+/// known, of SOMM, returning the bid if so. Amounts are computed with checked decimal arithmetic so
+/// malformed chain data is an error rather than a panic or a silently wrong bid.
+pub fn evaluate_bid(
+    order: &Order,
+    usd_unit_value: f64,
+    somm_usd_price: Option<f64>,
+    auction: &Auction,
+) -> Result<Option<Bid>> {
+    evaluate(order, usd_unit_value, somm_usd_price, auction).0
+}
+
+/// Like [`evaluate_bid`], also returning a record of the values the verdict was based on
+pub fn evaluate(
+    order: &Order,
+    usd_unit_value: f64,
+    somm_usd_price: Option<f64>,
+    auction: &Auction,
+) -> (Result<Option<Bid>>, Decision) {
+    let mut decision = Decision::new(order, auction.id);
+    decision.usd_price = Some(usd_unit_value);
+    decision.somm_usd_price = somm_usd_price;
+
+    let result = evaluate_into(
+        order,
+        usd_unit_value,
+        somm_usd_price,
+        auction,
+        &mut decision,
+    );
+    match &result {
+        Ok(Some(_)) => decision.verdict = Verdict::Bid,
+        Ok(None) => decision.verdict = Verdict::Pass,
+        Err(err) => {
+            decision.verdict = Verdict::Error;
+            decision.reason = Some(err.to_string());
+        }
+    }
+
+    (result, decision)
+}
+
+fn evaluate_into(
+    order: &Order,
+    usd_unit_value: f64,
+    somm_usd_price: Option<f64>,
+    auction: &Auction,
+    decision: &mut Decision,
+) -> Result<Option<Bid>> {
+    debug!("evaluating bid for order: {:?}", order);
+    let denom = order.fee_token;
+    let usd_unit_value = Decimal::from_f64(usd_unit_value)
+        .ok_or_else(|| eyre!("invalid usd price {usd_unit_value} for {denom}"))?
+        .checked_div(Decimal::from(10u64.pow(denom.decimals() as u32)))
+        .ok_or_else(|| eyre!("usd price out of range for {denom}"))?;
+    let auction_unit_price_in_usomm = unit_price_in_usomm(auction)?;
+    decision.auction_unit_price_in_usomm = Some(auction_unit_price_in_usomm.to_string());
+    let remaining_tokens_for_sale = remaining_tokens_for_sale(auction)?;
+    decision.remaining_tokens_for_sale = Some(remaining_tokens_for_sale);
+    if auction_unit_price_in_usomm.is_zero() {
+        return Err(eyre!("auction {} has a unit price of zero", auction.id));
+    }
+
+    if let Some(reason) = check_price_limits(order, auction_unit_price_in_usomm, somm_usd_price)? {
+        info!("{reason} for order {}", order.id);
+        decision.reason = Some(reason);
+
+        return Ok(None);
+    }
+
+    // the auction will give us the best possible price which makes this simpler
+    let max_allowed_usomm_offer = order.bid_size();
+    let possible_token_out = Decimal::from(max_allowed_usomm_offer)
+        .checked_div(auction_unit_price_in_usomm)
+        .and_then(|t| t.floor().to_u128())
+        .ok_or_else(|| eyre!("token amount out of range for auction {}", auction.id))?;
+    let min_possible_token_out = std::cmp::min(possible_token_out, remaining_tokens_for_sale);
+    let usd_value_out = Decimal::from_u128(min_possible_token_out)
+        .and_then(|t| t.checked_mul(usd_unit_value))
+        .ok_or_else(|| eyre!("usd value out of range for auction {}", auction.id))?;
+    let minimum_tokens_out =
+        apply_slippage_margin(min_possible_token_out, order.slippage_margin_bps)?;
+    decision.usomm_offer = Some(max_allowed_usomm_offer);
+    decision.tokens_out = Some(min_possible_token_out);
+    decision.minimum_tokens_out = Some(minimum_tokens_out);
+    decision.usd_value_out = Some(usd_value_out.to_string());
+    let minimum_usd_value_out =
+        Decimal::from_f64(order.minimum_usd_value_out).ok_or_else(|| {
+            eyre!(
+                "invalid minimum usd value out {}",
+                order.minimum_usd_value_out
+            )
+        })?;
+
+    debug!(
+        "usd_unit_value = {}, auction_unit_price_in_usomm = {}, remaining_tokens_for_sale = {}, max_allowed_usomm_offer = {}, min_possible_token_out = {}, usd_value_out = {}",
+        usd_unit_value, auction_unit_price_in_usomm, remaining_tokens_for_sale, max_allowed_usomm_offer, min_possible_token_out, usd_value_out
+    );
+
+    if minimum_usd_value_out <= usd_value_out {
+        if let Some(margin) = order.slippage_margin_bps {
+            info!(
+                "applied slippage margin of {margin} bps to minimum tokens out. raw = {min_possible_token_out}, adjusted = {minimum_tokens_out}"
+            );
+        }
+        info!(
+            "order qualifies for bid. usomm offer = {}, minimum token out = {}, usd value out = {} for {}",
+            max_allowed_usomm_offer,
+            min_possible_token_out,
+            usd_value_out,
+            order.fee_token
+        );
+
+        return Ok(Some(Bid {
+            auction_id: auction.id,
+            fee_token: order.fee_token,
+            maximum_usomm_in: max_allowed_usomm_offer as u128,
+            minimum_tokens_out,
+        }));
+    }
+
+    info!(
+        " usd value out = {} does not meet minimum usd value out = {} for {}",
+        usd_value_out, order.minimum_usd_value_out, order.fee_token
+    );
+    decision.reason = Some(format!(
+        "usd value out of {usd_value_out} is below the minimum of {}",
+        order.minimum_usd_value_out
+    ));
+
+    Ok(None)
+}
+
+// Takes a margin in basis points off a minimum token amount, rounding down
+fn apply_slippage_margin(tokens: u128, margin_bps: Option<u32>) -> Result<u128> {
+    let margin_bps = match margin_bps {
+        Some(m) => m as u128,
+        None => return Ok(tokens),
+    };
+    if margin_bps > 10_000 {
+        return Err(eyre!(
+            "slippage margin of {margin_bps} bps is more than 100%"
+        ));
+    }
+
+    Ok(tokens
+        .checked_mul(10_000 - margin_bps)
+        .map(|t| t / 10_000)
+        .unwrap_or(tokens / 10_000 * (10_000 - margin_bps)))
+}
+
+// Checks the order's limit prices against the auction's current price, returning why the order
+// shouldn't bid if a limit isn't met
+fn check_price_limits(
+    order: &Order,
+    unit_price_in_usomm: Decimal,
+    somm_usd_price: Option<f64>,
+) -> Result<Option<String>> {
+    if order.max_somm_per_token.is_none() && order.max_usd_per_token.is_none() {
+        return Ok(None);
+    }
+
+    let denom = order.fee_token;
+    let somm_per_token = unit_price_in_usomm
+        .checked_mul(Decimal::from(10u64.pow(denom.decimals() as u32)))
+        .and_then(|p| p.checked_div(Decimal::from(USOMM_PER_SOMM)))
+        .ok_or_else(|| eyre!("somm price per token out of range for {denom}"))?;
+
+    if let Some(limit) = order.max_somm_per_token {
+        if somm_per_token > decimal(limit)? {
+            return Ok(Some(format!(
+                "auction price of {somm_per_token} SOMM per {} is above the limit of {limit}",
+                denom.symbol()
+            )));
+        }
+    }
+
+    if let Some(limit) = order.max_usd_per_token {
+        let somm_usd_price = match somm_usd_price {
+            Some(p) => decimal(p)?,
+            None => {
+                return Ok(Some(
+                    "no USD price for SOMM to check the limit against".to_string(),
+                ))
+            }
+        };
+        let usd_per_token = somm_per_token
+            .checked_mul(somm_usd_price)
+            .ok_or_else(|| eyre!("usd price per token out of range for {denom}"))?;
+        if usd_per_token > decimal(limit)? {
+            return Ok(Some(format!(
+                "auction price of {usd_per_token} USD per {} is above the limit of {limit}",
+                denom.symbol()
+            )));
+        }
+    }
+
+    Ok(None)
+}
+
+fn decimal(value: f64) -> Result<Decimal> {
+    Decimal::from_f64(value).ok_or_else(|| eyre!("invalid decimal value {value}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use sommelier_auction::{cosmos_sdk_proto::cosmos::base::v1beta1::Coin, denom::Denom};
+
+    use super::*;
+    use crate::order::Dca;
+
+    fn auction(unit_price: &str, remaining: &str) -> Auction {
+        Auction {
+            id: 1,
+            current_unit_price_in_usomm: unit_price.to_string(),
+            remaining_tokens_for_sale: Some(Coin {
+                denom: Denom::USDC.to_string(),
+                amount: remaining.to_string(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn order(maximum_usomm_in: u64, minimum_usd_value_out: f64) -> Order {
+        Order {
+            fee_token: Denom::USDC,
+            maximum_usomm_in,
+            minimum_usd_value_out,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_remaining_decline_bps() {
+        // price falls 0.01 usomm every 10 blocks from 0.5 usomm
+        let auction = Auction {
+            end_block: 1000,
+            price_decrease_block_interval: 10,
+            initial_unit_price_in_usomm: "1000000000000000000".to_string(),
+            current_price_decrease_rate: "10000000000000000".to_string(),
+            ..auction("500000000000000000", "1000")
+        };
+
+        assert_eq!(
+            remaining_decline_bps(&auction, 900).unwrap(),
+            Decimal::from(2000)
+        );
+        assert_eq!(
+            remaining_decline_bps(&auction, 990).unwrap(),
+            Decimal::from(200)
+        );
+        assert_eq!(
+            remaining_decline_bps(&auction, 1000).unwrap(),
+            Decimal::ZERO
+        );
+        // the price can't fall below zero
+        assert_eq!(
+            remaining_decline_bps(&auction, 0).unwrap(),
+            Decimal::from(10_000)
+        );
+    }
+
+    #[test]
+    fn test_qualifying_bid() {
+        // 10 usomm per base unit of USDC, so 100 SOMM buys 10 USDC
+        let auction = auction("10000000000000000000", "1000000000");
+        let bid = evaluate_bid(&order(100_000_000, 10.0), 1.0, None, &auction)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(bid.maximum_usomm_in, 100_000_000);
+        assert_eq!(bid.minimum_tokens_out, 10_000_000);
+    }
+
+    #[test]
+    fn test_limited_by_remaining_tokens() {
+        let auction = auction("10000000000000000000", "5000000");
+
+        assert!(evaluate_bid(&order(100_000_000, 10.0), 1.0, None, &auction)
+            .unwrap()
+            .is_none());
+
+        let bid = evaluate_bid(&order(100_000_000, 5.0), 1.0, None, &auction)
+            .unwrap()
+            .unwrap();
+        assert_eq!(bid.minimum_tokens_out, 5_000_000);
+    }
+
+    #[test]
+    fn test_dca_bid_size() {
+        let auction = auction("10000000000000000000", "1000000000");
+        let order = Order {
+            dca: Some(Dca {
+                usomm_per_bid: 50_000_000,
+                interval_secs: None,
+            }),
+            ..order(100_000_000, 5.0)
+        };
+        let bid = evaluate_bid(&order, 1.0, None, &auction).unwrap().unwrap();
+
+        assert_eq!(bid.maximum_usomm_in, 50_000_000);
+        assert_eq!(bid.minimum_tokens_out, 5_000_000);
+    }
+
+    #[test]
+    fn test_remainder_bids_after_partial_fill() {
+        // the first bid is only filled for 60 of its 100 SOMM
+        let auction = auction("10000000000000000000", "1000000000");
+        let mut partial = order(100_000_000, 10.0);
+        let bid = evaluate_bid(&partial, 1.0, None, &auction)
+            .unwrap()
+            .unwrap();
+        assert_eq!(bid.maximum_usomm_in, 100_000_000);
+        partial.spend(60_000_000);
+        assert_eq!(partial.maximum_usomm_in, 40_000_000);
+        assert!((partial.minimum_usd_value_out - 4.0).abs() < 1e-9);
+
+        // the remainder still bids at the same price
+        let bid = evaluate_bid(&partial, 1.0, None, &auction)
+            .unwrap()
+            .unwrap();
+        assert_eq!(bid.maximum_usomm_in, 40_000_000);
+        assert_eq!(bid.minimum_tokens_out, 4_000_000);
+
+        // a DCA order's minimum is per bid, so it isn't scaled
+        let mut dca = Order {
+            dca: Some(Dca {
+                usomm_per_bid: 50_000_000,
+                interval_secs: None,
+            }),
+            ..order(100_000_000, 5.0)
+        };
+        dca.spend(50_000_000);
+        assert_eq!(dca.minimum_usd_value_out, 5.0);
+    }
+
+    #[test]
+    fn test_limit_prices() {
+        // 10 usomm per base unit is 10 SOMM per USDC
+        let auction = auction("10000000000000000000", "1000000000");
+        let somm_limit = |limit| Order {
+            max_somm_per_token: Some(limit),
+            ..order(100_000_000, 0.0)
+        };
+        let usd_limit = |limit| Order {
+            max_usd_per_token: Some(limit),
+            ..order(100_000_000, 0.0)
+        };
+
+        assert!(evaluate_bid(&somm_limit(9.0), 1.0, None, &auction)
+            .unwrap()
+            .is_none());
+        assert!(evaluate_bid(&somm_limit(10.0), 1.0, None, &auction)
+            .unwrap()
+            .is_some());
+        assert!(evaluate_bid(&usd_limit(0.9), 1.0, Some(0.1), &auction)
+            .unwrap()
+            .is_none());
+        assert!(evaluate_bid(&usd_limit(1.0), 1.0, Some(0.1), &auction)
+            .unwrap()
+            .is_some());
+        assert!(evaluate_bid(&usd_limit(1.0), 1.0, None, &auction)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_slippage_margin() {
+        let auction = auction("10000000000000000000", "1000000000");
+        let order = Order {
+            slippage_margin_bps: Some(50),
+            ..order(100_000_000, 10.0)
+        };
+        let bid = evaluate_bid(&order, 1.0, None, &auction).unwrap().unwrap();
+
+        assert_eq!(bid.maximum_usomm_in, 100_000_000);
+        assert_eq!(bid.minimum_tokens_out, 9_950_000);
+
+        let order = Order {
+            slippage_margin_bps: Some(10_001),
+            ..order
+        };
+        assert!(evaluate_bid(&order, 1.0, None, &auction).is_err());
+    }
+
+    #[test]
+    fn test_decision_records_why() {
+        let auction = auction("10000000000000000000", "5000000");
+
+        let (result, decision) = evaluate(&order(100_000_000, 10.0), 1.0, None, &auction);
+        assert!(result.unwrap().is_none());
+        assert_eq!(decision.verdict, Verdict::Pass);
+        assert_eq!(decision.tokens_out, Some(5_000_000));
+        assert!(decision.reason.unwrap().contains("below the minimum"));
+
+        let (result, decision) = evaluate(&order(100_000_000, 10.0), 1.0, None, &auction("0", "1"));
+        assert!(result.is_err());
+        assert_eq!(decision.verdict, Verdict::Error);
+    }
+
+    #[test]
+    fn test_malformed_auction_is_an_error() {
+        let order = order(100_000_000, 10.0);
+
+        assert!(evaluate_bid(&order, 1.0, None, &auction("not a number", "1000")).is_err());
+        assert!(evaluate_bid(&order, 1.0, None, &auction("0", "1000")).is_err());
+        assert!(evaluate_bid(&order, 1.0, None, &auction("10000000000000000000", "-1")).is_err());
+    }
+}
@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+use sommelier_auction::denom::Denom;
+
+/// Restricts which auctioned denoms the engine bids on, whatever the orders say
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DenomFilter {
+    /// Optional list of the only denoms the engine may bid on
+    pub allow: Option<Vec<Denom>>,
+    /// Optional list of denoms the engine never bids on. Takes precedence over `allow`.
+    pub block: Option<Vec<Denom>>,
+}
+
+impl DenomFilter {
+    /// Why the engine may not bid on `denom`, if it may not
+    pub fn rejects(&self, denom: &Denom) -> Option<&'static str> {
+        if matches!(&self.block, Some(block) if block.contains(denom)) {
+            return Some("blocked");
+        }
+        if matches!(&self.allow, Some(allow) if !allow.contains(denom)) {
+            return Some("not in the allowlist");
+        }
+
+        None
+    }
+
+    pub fn permits(&self, denom: &Denom) -> bool {
+        self.rejects(denom).is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter() {
+        let filter = DenomFilter::default();
+        assert!(filter.permits(&Denom::WETH));
+
+        let filter = DenomFilter {
+            allow: Some(vec![Denom::WETH, Denom::USDC]),
+            block: Some(vec![Denom::USDC]),
+        };
+        assert!(filter.permits(&Denom::WETH));
+        assert_eq!(filter.rejects(&Denom::USDC), Some("blocked"));
+        assert_eq!(filter.rejects(&Denom::WBTC), Some("not in the allowlist"));
+    }
+}
@@ -0,0 +1,229 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use sommelier_auction::{client::Client, denom::Denom};
+use tracing::{info, warn};
+
+use crate::util::unix_now;
+
+pub const DEFAULT_MAX_LOOP_AGE_SECS: u64 = 600;
+pub const DEFAULT_MAX_PRICE_AGE_SECS: u64 = 900;
+pub const DEFAULT_MAX_CHAIN_QUERY_AGE_SECS: u64 = 600;
+
+/// How often wallet balances are refreshed
+pub const BALANCE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Settings for the health endpoint. Ages are in seconds.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthConfig {
+    /// Address to serve `/healthz` on, e.g. "0.0.0.0:9093"
+    pub listen_addr: String,
+    /// Unhealthy if the watcher loop hasn't run for this long. Defaults to 600. The watcher waits
+    /// up to 5 minutes between loops when there are no auctions.
+    pub max_loop_age_secs: Option<u64>,
+    /// Unhealthy if prices haven't been refreshed for this long. Defaults to 900.
+    pub max_price_age_secs: Option<u64>,
+    /// Unhealthy if no chain query has succeeded for this long. Defaults to 600.
+    pub max_chain_query_age_secs: Option<u64>,
+    /// Optional usomm balance each wallet must hold to be healthy
+    pub min_usomm_balance: Option<u128>,
+}
+
+/// Liveness timestamps reported by the engine. Times are unix seconds.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Health {
+    pub started_at: u64,
+    pub last_loop: Option<u64>,
+    /// The watcher has no orders, so it isn't querying prices or auctions
+    pub idle: bool,
+    pub prices_updated_at: Option<u64>,
+    pub last_chain_query: Option<u64>,
+    /// usomm balance of each wallet by name
+    pub balances: HashMap<String, u128>,
+    pub balances_checked_at: Option<u64>,
+}
+
+/// The outcome of checking [`Health`] against a [`HealthConfig`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthReport {
+    pub healthy: bool,
+    /// Why the engine is unhealthy, if it is
+    pub problems: Vec<String>,
+    pub health: Health,
+}
+
+impl Health {
+    pub fn new(now: u64) -> Self {
+        Self {
+            started_at: now,
+            ..Default::default()
+        }
+    }
+
+    /// Checks every liveness timestamp and balance. Timestamps that were never set are measured
+    /// from when the engine started, so a fresh engine gets a grace period. Price and chain query
+    /// freshness aren't checked while the watcher is idle.
+    pub fn report(&self, config: &HealthConfig, now: u64) -> HealthReport {
+        let mut problems = Vec::new();
+        let mut check = |name: &str, at: Option<u64>, max_age: u64| {
+            let age = now.saturating_sub(at.unwrap_or(self.started_at));
+            if age > max_age {
+                problems.push(format!("{name} is {age}s old, more than {max_age}s"));
+            }
+        };
+
+        check(
+            "watcher loop",
+            self.last_loop,
+            config
+                .max_loop_age_secs
+                .unwrap_or(DEFAULT_MAX_LOOP_AGE_SECS),
+        );
+        if !self.idle {
+            check(
+                "price feed",
+                self.prices_updated_at,
+                config
+                    .max_price_age_secs
+                    .unwrap_or(DEFAULT_MAX_PRICE_AGE_SECS),
+            );
+            check(
+                "last chain query",
+                self.last_chain_query,
+                config
+                    .max_chain_query_age_secs
+                    .unwrap_or(DEFAULT_MAX_CHAIN_QUERY_AGE_SECS),
+            );
+        }
+
+        HealthReport::new(problems, self, config)
+    }
+}
+
+impl HealthReport {
+    fn new(mut problems: Vec<String>, health: &Health, config: &HealthConfig) -> Self {
+        if let Some(min_balance) = config.min_usomm_balance {
+            let mut balances = health.balances.iter().collect::<Vec<(&String, &u128)>>();
+            balances.sort();
+            for (wallet, balance) in balances {
+                if *balance < min_balance {
+                    problems.push(format!(
+                        "wallet {wallet} has {balance} usomm, less than {min_balance}"
+                    ));
+                }
+            }
+        }
+
+        Self {
+            healthy: problems.is_empty(),
+            problems,
+            health: health.clone(),
+        }
+    }
+}
+
+/// Updates the shared health state, tolerating a poisoned lock
+pub fn update(health: &RwLock<Health>, f: impl FnOnce(&mut Health)) {
+    let mut health = health
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(&mut health);
+}
+
+/// Refreshes the usomm balance of each (name, address) wallet until the task is aborted
+pub async fn watch_balances(
+    mut client: Client,
+    wallets: Vec<(String, String)>,
+    health: Arc<RwLock<Health>>,
+) {
+    loop {
+        for (name, address) in &wallets {
+            match client.balance(address, Denom::USOMM).await {
+                Ok(balance) => update(&health, |h| {
+                    h.balances.insert(name.clone(), balance);
+                    h.balances_checked_at = Some(unix_now());
+                }),
+                Err(err) => warn!("failed to query balance of wallet {name}: {err:?}"),
+            }
+        }
+
+        tokio::time::sleep(BALANCE_CHECK_INTERVAL).await;
+    }
+}
+
+/// Serves `/healthz` until the task is aborted. Responds 200 when healthy and 503 otherwise.
+pub async fn serve(config: HealthConfig, health: Arc<RwLock<Health>>) -> Result<()> {
+    let listen_addr = config.listen_addr.clone();
+    let app = Router::new()
+        .route("/healthz", get(healthz))
+        .with_state((Arc::new(config), health));
+
+    let listener = tokio::net::TcpListener::bind(&listen_addr).await?;
+    info!("health endpoint listening on {listen_addr}");
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+async fn healthz(
+    State((config, health)): State<(Arc<HealthConfig>, Arc<RwLock<Health>>)>,
+) -> (StatusCode, Json<HealthReport>) {
+    let report = health
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .report(&config, unix_now());
+    let status = if report.healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let config = HealthConfig {
+            min_usomm_balance: Some(100),
+            ..Default::default()
+        };
+        let mut health = Health::new(1_000);
+
+        // fresh engines get a grace period
+        assert!(health.report(&config, 1_100).healthy);
+
+        let report = health.report(&config, 2_000);
+        assert!(!report.healthy);
+        assert_eq!(report.problems.len(), 3);
+
+        health.last_loop = Some(1_990);
+        health.prices_updated_at = Some(1_990);
+        health.last_chain_query = Some(1_990);
+        health.balances.insert("default".to_string(), 50);
+        let report = health.report(&config, 2_000);
+        assert_eq!(
+            report.problems,
+            vec!["wallet default has 50 usomm, less than 100".to_string()]
+        );
+
+        // an idle watcher doesn't query prices or auctions
+        health.prices_updated_at = None;
+        health.last_chain_query = None;
+        health.idle = true;
+        let report = health.report(&config, 2_000);
+        assert_eq!(
+            report.problems,
+            vec!["wallet default has 50 usomm, less than 100".to_string()]
+        );
+    }
+}
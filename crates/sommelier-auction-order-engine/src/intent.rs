@@ -0,0 +1,105 @@
+use std::path::Path;
+
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use sommelier_auction::bid::Bid;
+
+/// A bid about to be broadcast. Intents are written to disk before the broadcast and removed once
+/// the bid's fill is recorded, so a bid left behind by a crash can be looked up on chain at startup
+/// instead of being placed again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Intent {
+    /// Client-side key identifying this submission
+    pub key: String,
+    pub order_id: usize,
+    /// Name of the wallet the bid was submitted from
+    pub wallet: String,
+    /// The account the bid is made from
+    pub bidder: String,
+    pub bid: Bid,
+    /// ID of the last bid in the auction before this one was broadcast. Only later bids are
+    /// taken as this one when looking it up on chain. Zero in intents recorded before it was kept.
+    #[serde(default)]
+    pub after_bid_id: u64,
+    /// Unix time the intent was recorded
+    pub created_at: u64,
+}
+
+impl Intent {
+    pub fn new(
+        order_id: usize,
+        wallet: &str,
+        bidder: String,
+        bid: Bid,
+        after_bid_id: u64,
+        now: u64,
+    ) -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+
+        Self {
+            key: format!("{order_id}-{}-{wallet}-{now}.{nanos:09}", bid.auction_id),
+            order_id,
+            wallet: wallet.to_string(),
+            bidder,
+            bid,
+            after_bid_id,
+            created_at: now,
+        }
+    }
+}
+
+/// The intents of bids that were broadcast but whose fills haven't been recorded, kept in a file
+pub struct IntentLog {
+    path: String,
+    intents: Vec<Intent>,
+}
+
+impl IntentLog {
+    /// Opens the intent log at `path`, loading any intents left in it
+    pub fn open(path: &str) -> Result<Self> {
+        let intents = if Path::new(path).exists() {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|err| eyre!("failed to read bid intents from {path}: {err}"))?;
+            serde_json::from_str(&contents)
+                .map_err(|err| eyre!("failed to parse bid intents from {path}: {err}"))?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            path: path.to_string(),
+            intents,
+        })
+    }
+
+    /// Intents whose fills haven't been recorded
+    pub fn pending(&self) -> &[Intent] {
+        &self.intents
+    }
+
+    /// Records an intent. The bid must not be broadcast if this fails.
+    pub fn record(&mut self, intent: Intent) -> Result<()> {
+        self.intents.push(intent);
+        self.save()
+    }
+
+    /// Removes an intent once its bid's fill is recorded
+    pub fn resolve(&mut self, key: &str) -> Result<()> {
+        self.intents.retain(|i| i.key != key);
+        self.save()
+    }
+
+    // Writes to a temporary file first so a crash mid-write doesn't lose the previous intents
+    fn save(&self) -> Result<()> {
+        let tmp_path = format!("{}.tmp", self.path);
+        std::fs::write(&tmp_path, serde_json::to_string_pretty(&self.intents)?)
+            .map_err(|err| eyre!("failed to write bid intents to {tmp_path}: {err}"))?;
+        std::fs::rename(&tmp_path, &self.path)
+            .map_err(|err| eyre!("failed to move bid intents into {}: {err}", self.path))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+use std::io::Write;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use eyre::{eyre, Result};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+/// Environment variable a keystore's password is read from by default
+pub const DEFAULT_PASSWORD_ENV: &str = "SOMMELIER_AUCTION_KEYSTORE_PASSWORD";
+
+// scrypt cost parameters for new keystores. log_n = 15 takes around 100ms.
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+/// A mnemonic encrypted with ChaCha20-Poly1305 under a key derived from a password with scrypt
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u32,
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+    /// Base64 encoded
+    pub salt: String,
+    /// Base64 encoded
+    pub nonce: String,
+    /// Base64 encoded
+    pub ciphertext: String,
+}
+
+impl Keystore {
+    pub fn encrypt(mnemonic: &str, password: &str) -> Result<Self> {
+        let mut salt = [0u8; 32];
+        let mut nonce = [0u8; 12];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let cipher = cipher(password, &salt, SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P)?;
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), mnemonic.as_bytes())
+            .map_err(|_| eyre!("failed to encrypt mnemonic"))?;
+
+        Ok(Self {
+            version: 1,
+            log_n: SCRYPT_LOG_N,
+            r: SCRYPT_R,
+            p: SCRYPT_P,
+            salt: BASE64.encode(salt),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        })
+    }
+
+    /// Decrypts the mnemonic. Fails if the password is wrong or the file was tampered with.
+    pub fn decrypt(&self, password: &str) -> Result<String> {
+        if self.version != 1 {
+            return Err(eyre!("unsupported keystore version {}", self.version));
+        }
+
+        let cipher = cipher(
+            password,
+            &BASE64.decode(&self.salt)?,
+            self.log_n,
+            self.r,
+            self.p,
+        )?;
+        let nonce = BASE64.decode(&self.nonce)?;
+        if nonce.len() != 12 {
+            return Err(eyre!("invalid keystore nonce"));
+        }
+        let mnemonic = cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                BASE64.decode(&self.ciphertext)?.as_slice(),
+            )
+            .map_err(|_| eyre!("failed to decrypt keystore, is the password correct?"))?;
+
+        Ok(String::from_utf8(mnemonic)?)
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| eyre!("failed to read keystore {path}: {err}"))?;
+
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// Writes the keystore to `path`. On unix only its owner may read it, since anyone who can
+    /// read it can guess passwords against it offline.
+    pub fn save(&self, path: &str) -> Result<()> {
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options
+            .open(path)
+            .map_err(|err| eyre!("failed to write keystore {path}: {err}"))?;
+        // the mode only applies to new files, so an existing keystore is restricted too
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        }
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+fn cipher(password: &str, salt: &[u8], log_n: u8, r: u32, p: u32) -> Result<ChaCha20Poly1305> {
+    let params = scrypt::Params::new(log_n, r, p, 32)
+        .map_err(|err| eyre!("invalid scrypt parameters: {err}"))?;
+    let mut key = [0u8; 32];
+    scrypt::scrypt(password.as_bytes(), salt, &params, &mut key)
+        .map_err(|err| eyre!("failed to derive keystore key: {err}"))?;
+
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let keystore = Keystore::encrypt("some words", "hunter2").unwrap();
+
+        assert_eq!(keystore.decrypt("hunter2").unwrap(), "some words");
+        assert!(keystore.decrypt("hunter3").is_err());
+    }
+}
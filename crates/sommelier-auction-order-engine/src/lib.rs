@@ -1,5 +1,37 @@
+pub mod admin;
+pub mod admin_grpc;
+pub mod allocation;
+pub mod authz;
+pub mod backtest;
+pub mod budget;
+pub mod competition;
 pub mod config;
+pub mod dashboard;
+pub mod decision;
+pub mod endpoints;
 pub mod engine;
+pub mod evaluate;
+pub mod filter;
+pub mod health;
+pub mod intent;
+pub mod keystore;
+pub mod metrics;
+pub mod notify;
 pub mod order;
+pub mod paper;
+pub mod pnl;
+pub mod price;
+pub mod price_updater;
+pub mod projection;
+pub mod ratelimit;
+pub mod reconcile;
+pub mod reload;
+pub mod remote_signer;
+pub mod risk;
+pub mod state;
+pub mod strategy;
+pub mod submit;
+pub mod twap;
 pub mod util;
+pub mod wallet;
 pub mod watcher;
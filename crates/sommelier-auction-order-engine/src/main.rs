@@ -0,0 +1,91 @@
+use clap::Parser;
+use sommelier_auction_order_engine::{
+    config,
+    engine::OrderEngine,
+    keystore::{self, Keystore},
+    wallet::DEFAULT_MNEMONIC_ENV,
+};
+use tracing::{debug, error, info, Level};
+
+/// Runs the order engine on its own, bidding on auctions according to the configured orders
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[arg(short, long, required_unless_present = "create_keystore")]
+    config: Option<String>,
+    /// Log the bids that would be submitted instead of submitting them
+    #[arg(long)]
+    dry_run: bool,
+    #[arg(long, default_value_t = Level::INFO)]
+    log_level: Level,
+    /// Serve Prometheus metrics on this port. Overrides `metrics_listen_addr` in the config.
+    #[arg(long)]
+    metrics_port: Option<u16>,
+    /// Encrypt the mnemonic in SOMMELIER_AUCTION_MNEMONIC with the password in
+    /// SOMMELIER_AUCTION_KEYSTORE_PASSWORD, write it to this path as a keystore file, and exit
+    #[arg(long)]
+    create_keystore: Option<String>,
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    tracing_subscriber::fmt()
+        .with_max_level(args.log_level)
+        .init();
+
+    if let Some(path) = args.create_keystore {
+        if let Err(err) = create_keystore(&path) {
+            error!("failed to create keystore: {err}");
+            std::process::exit(1);
+        }
+        info!("wrote keystore to {path}");
+        return;
+    }
+
+    let config_path = match args.config {
+        Some(path) if !path.is_empty() => path,
+        _ => {
+            error!("config file path is required");
+            std::process::exit(1);
+        }
+    };
+
+    let mut config = match config::load(&config_path) {
+        Ok(config) => config,
+        Err(err) => {
+            error!("failed to load config: {err}");
+            std::process::exit(1);
+        }
+    };
+    if args.dry_run {
+        config.dry_run = Some(true);
+    }
+    if let Some(port) = args.metrics_port {
+        config.metrics_listen_addr = Some(format!("0.0.0.0:{port}"));
+    }
+    debug!("config: {config:?}");
+
+    let watch_orders = config.watch_orders.unwrap_or_default();
+    let mut engine = OrderEngine::new(config);
+    if watch_orders {
+        engine.orders_path = Some(config_path);
+    }
+    if let Err(e) = engine.start().await {
+        error!("error running engine: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn create_keystore(path: &str) -> eyre::Result<()> {
+    let mnemonic = std::env::var(DEFAULT_MNEMONIC_ENV)
+        .map_err(|_| eyre::eyre!("set {DEFAULT_MNEMONIC_ENV} to the mnemonic to encrypt"))?;
+    let password = std::env::var(keystore::DEFAULT_PASSWORD_ENV).map_err(|_| {
+        eyre::eyre!(
+            "set {} to the keystore's password",
+            keystore::DEFAULT_PASSWORD_ENV
+        )
+    })?;
+
+    Keystore::encrypt(mnemonic.trim(), &password)?.save(path)
+}
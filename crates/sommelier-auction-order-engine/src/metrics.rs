@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use eyre::Result;
+use sommelier_auction_metrics::{Counter, Gauge, Registry, ServiceMetrics};
+
+use crate::{budget::BudgetUsage, order::Fill};
+
+/// Prefix of every engine metric
+pub const METRICS_PREFIX: &str = "auction_engine";
+
+/// Engine counters and gauges, served in the Prometheus text format
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    pub registry: Registry,
+    pub service: ServiceMetrics,
+    pub bids_submitted: Counter,
+    pub bids_failed: Counter,
+    pub bids_skipped: Counter,
+    pub usomm_spent: Counter,
+    pub tokens_received: Counter,
+    pub bid_discrepancies: Counter,
+    pub auctions_unknown_denom: Counter,
+    pub orders_unpriced: Counter,
+    pub denoms_unpriced: Gauge,
+    pub orders: Gauge,
+    pub active_auctions: Gauge,
+    pub budget_daily_spent: Gauge,
+    pub budget_daily_limit: Gauge,
+    pub budget_weekly_spent: Gauge,
+    pub budget_weekly_limit: Gauge,
+    pub budget_auction_spent: Gauge,
+    pub budget_auction_limit: Gauge,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new(Registry::default())
+    }
+}
+
+impl Metrics {
+    /// Registers the engine's metrics in `registry`
+    pub fn new(registry: Registry) -> Self {
+        Self {
+            service: ServiceMetrics::register(&registry, METRICS_PREFIX),
+            bids_submitted: registry.counter(
+                "auction_engine_bids_submitted_total",
+                "Bids submitted successfully",
+            ),
+            bids_failed: registry.counter(
+                "auction_engine_bids_failed_total",
+                "Bids that failed or whose outcome is unknown",
+            ),
+            bids_skipped: registry.counter(
+                "auction_engine_bids_skipped_total",
+                "Bids skipped before submission",
+            ),
+            usomm_spent: registry
+                .counter("auction_engine_usomm_spent_total", "usomm spent on bids"),
+            tokens_received: registry.counter(
+                "auction_engine_tokens_received_total",
+                "Base units of auctioned tokens received",
+            ),
+            bid_discrepancies: registry.counter(
+                "auction_engine_bid_discrepancies_total",
+                "Bids whose on-chain fulfillment didn't match what was bid or reported",
+            ),
+            auctions_unknown_denom: registry.counter(
+                "auction_engine_auctions_unknown_denom_total",
+                "Auction evaluations skipped because the engine doesn't know the auction's denom",
+            ),
+            orders_unpriced: registry.counter(
+                "auction_engine_orders_unpriced_total",
+                "Order evaluations skipped because the order's token had no USD price",
+            ),
+            denoms_unpriced: registry.gauge(
+                "auction_engine_denoms_unpriced",
+                "Denoms with orders and an active auction but no USD price",
+            ),
+            orders: registry.gauge("auction_engine_orders", "Orders with usomm left to spend"),
+            active_auctions: registry
+                .gauge("auction_engine_active_auctions", "Active auctions on chain"),
+            budget_daily_spent: registry.gauge(
+                "auction_engine_budget_daily_usomm_spent",
+                "usomm spent in the last 24 hours",
+            ),
+            budget_daily_limit: registry.gauge(
+                "auction_engine_budget_daily_usomm_limit",
+                "Daily usomm budget, 0 if unlimited",
+            ),
+            budget_weekly_spent: registry.gauge(
+                "auction_engine_budget_weekly_usomm_spent",
+                "usomm spent in the last 7 days",
+            ),
+            budget_weekly_limit: registry.gauge(
+                "auction_engine_budget_weekly_usomm_limit",
+                "Weekly usomm budget, 0 if unlimited",
+            ),
+            budget_auction_spent: registry.gauge(
+                "auction_engine_budget_auction_usomm_spent",
+                "Most usomm spent in any one running auction",
+            ),
+            budget_auction_limit: registry.gauge(
+                "auction_engine_budget_auction_usomm_limit",
+                "Per-auction usomm budget, 0 if unlimited",
+            ),
+            registry,
+        }
+    }
+
+    /// Counts a bid's outcome. Fills that spent nothing and didn't fail were never submitted.
+    pub fn record_fill(&self, fill: &Fill) {
+        if fill.failed {
+            self.bids_failed.inc();
+        } else if fill.usomm_spent == 0 {
+            self.bids_skipped.inc();
+        } else {
+            self.bids_submitted.inc();
+        }
+
+        self.usomm_spent
+            .inc_by(u64::try_from(fill.usomm_spent).unwrap_or(u64::MAX));
+        self.tokens_received
+            .inc_by(u64::try_from(fill.tokens_received).unwrap_or(u64::MAX));
+    }
+
+    /// Reports the engine's spend against its budget limits
+    pub fn record_budget(&self, usage: &BudgetUsage) {
+        let usomm = |amount: u128| u64::try_from(amount).unwrap_or(u64::MAX);
+        self.budget_daily_spent.set(usomm(usage.daily_spent));
+        self.budget_daily_limit
+            .set(usage.daily_limit.unwrap_or_default());
+        self.budget_weekly_spent.set(usomm(usage.weekly_spent));
+        self.budget_weekly_limit
+            .set(usage.weekly_limit.unwrap_or_default());
+        self.budget_auction_spent.set(usomm(usage.auction_spent));
+        self.budget_auction_limit
+            .set(usage.auction_limit.unwrap_or_default());
+    }
+
+    /// Renders every metric in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        self.registry.render()
+    }
+}
+
+/// Serves `/metrics` until the task is aborted
+pub async fn serve(listen_addr: String, metrics: Arc<Metrics>) -> Result<()> {
+    sommelier_auction_metrics::serve(listen_addr, metrics.registry.clone()).await
+}
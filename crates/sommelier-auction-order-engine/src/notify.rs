@@ -0,0 +1,510 @@
+use std::fmt::Display;
+
+use async_trait::async_trait;
+use eyre::{eyre, Result};
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use serde::{Deserialize, Serialize};
+use sommelier_auction::denom::Denom;
+use tokio::{sync::mpsc::UnboundedSender, task::JoinHandle};
+use tracing::warn;
+
+/// Environment variable the Telegram bot token is read from by default
+pub const DEFAULT_TELEGRAM_TOKEN_ENV: &str = "TELEGRAM_BOT_TOKEN";
+/// Environment variables the SMTP credentials are read from by default
+pub const DEFAULT_SMTP_USERNAME_ENV: &str = "SMTP_USERNAME";
+pub const DEFAULT_SMTP_PASSWORD_ENV: &str = "SMTP_PASSWORD";
+/// Events emailed to a recipient who doesn't choose: fills and the PnL digest
+pub const DEFAULT_EMAIL_EVENTS: [&str; 2] = ["bid_filled", "pnl_summary"];
+/// Every kind of [`Event`], as named in its JSON
+pub const EVENT_KINDS: [&str; 8] = [
+    "bid_submitted",
+    "bid_filled",
+    "bid_failed",
+    "bid_discrepancy",
+    "order_exhausted",
+    "pnl_summary",
+    "bidding_stopped",
+    "price_stale",
+];
+
+// HTML body of each email, unless a template is configured
+const DEFAULT_EMAIL_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+  <body style="font-family: sans-serif">
+    <h2>{{subject}}</h2>
+    <p>{{message}}</p>
+  </body>
+</html>
+"#;
+
+/// Where to send notifications when the engine acts
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotificationConfig {
+    /// Optional Telegram chat to message
+    pub telegram: Option<TelegramConfig>,
+    /// Optional email recipients
+    pub email: Option<EmailConfig>,
+    /// Optional URLs each event is POSTed to as JSON
+    pub webhooks: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TelegramConfig {
+    pub chat_id: String,
+    /// Environment variable holding the bot token. Defaults to TELEGRAM_BOT_TOKEN.
+    pub bot_token_env: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmailConfig {
+    /// SMTP server to send through with STARTTLS
+    pub smtp_host: String,
+    /// Optional SMTP port. Defaults to 587.
+    pub smtp_port: Option<u16>,
+    /// Address emails are sent from
+    pub from: String,
+    /// Environment variable holding the SMTP username. Defaults to SMTP_USERNAME.
+    pub username_env: Option<String>,
+    /// Environment variable holding the SMTP password. Defaults to SMTP_PASSWORD.
+    pub password_env: Option<String>,
+    /// Optional path to an HTML template for email bodies. `{{subject}}` and `{{message}}` are
+    /// replaced with the email's subject and the event's description.
+    pub template_path: Option<String>,
+    pub recipients: Vec<EmailRecipient>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmailRecipient {
+    pub address: String,
+    /// Optional kinds of event the recipient opts in to, like `bid_filled`. Defaults to fills and
+    /// PnL summaries.
+    pub events: Option<Vec<String>>,
+}
+
+impl EmailRecipient {
+    pub fn wants(&self, event: &Event) -> bool {
+        match &self.events {
+            Some(events) => events.iter().any(|e| e == event.kind()),
+            None => DEFAULT_EMAIL_EVENTS.contains(&event.kind()),
+        }
+    }
+}
+
+/// Something the engine did with an operator's money
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    BidSubmitted {
+        order_id: usize,
+        auction_id: u32,
+        wallet: String,
+        maximum_usomm_in: u128,
+        minimum_tokens_out: u128,
+    },
+    BidFilled {
+        order_id: usize,
+        auction_id: u32,
+        wallet: String,
+        usomm_spent: u128,
+        tokens_received: u128,
+    },
+    BidFailed {
+        order_id: usize,
+        auction_id: u32,
+        wallet: String,
+        reason: String,
+    },
+    BidDiscrepancy {
+        order_id: usize,
+        auction_id: u32,
+        wallet: String,
+        reasons: Vec<String>,
+    },
+    OrderExhausted {
+        order_id: usize,
+    },
+    PnlSummary {
+        fills: u64,
+        usd_cost: f64,
+        usd_value_now: f64,
+        pnl_usd: f64,
+    },
+    BiddingStopped {
+        reason: String,
+    },
+    PriceStale {
+        denom: Denom,
+        age_secs: u64,
+    },
+}
+
+impl Event {
+    /// The event's name in its JSON, one of [`EVENT_KINDS`]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Event::BidSubmitted { .. } => "bid_submitted",
+            Event::BidFilled { .. } => "bid_filled",
+            Event::BidFailed { .. } => "bid_failed",
+            Event::BidDiscrepancy { .. } => "bid_discrepancy",
+            Event::OrderExhausted { .. } => "order_exhausted",
+            Event::PnlSummary { .. } => "pnl_summary",
+            Event::BiddingStopped { .. } => "bidding_stopped",
+            Event::PriceStale { .. } => "price_stale",
+        }
+    }
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Event::BidSubmitted {
+                order_id,
+                auction_id,
+                wallet,
+                maximum_usomm_in,
+                minimum_tokens_out,
+            } => write!(
+                f,
+                "order {order_id} bid up to {maximum_usomm_in} usomm for at least {minimum_tokens_out} tokens in auction {auction_id} from wallet {wallet}"
+            ),
+            Event::BidFilled {
+                order_id,
+                auction_id,
+                wallet,
+                usomm_spent,
+                tokens_received,
+            } => write!(
+                f,
+                "order {order_id} spent {usomm_spent} usomm for {tokens_received} tokens in auction {auction_id} from wallet {wallet}"
+            ),
+            Event::BidFailed {
+                order_id,
+                auction_id,
+                wallet,
+                reason,
+            } => write!(
+                f,
+                "order {order_id} bid in auction {auction_id} from wallet {wallet} failed: {reason}"
+            ),
+            Event::BidDiscrepancy {
+                order_id,
+                auction_id,
+                wallet,
+                reasons,
+            } => write!(
+                f,
+                "order {order_id} bid in auction {auction_id} from wallet {wallet} settled unexpectedly: {}",
+                reasons.join("; ")
+            ),
+            Event::OrderExhausted { order_id } => {
+                write!(f, "order {order_id} has spent its whole budget")
+            }
+            Event::PnlSummary {
+                fills,
+                usd_cost,
+                usd_value_now,
+                pnl_usd,
+            } => write!(
+                f,
+                "{fills} fills so far, tokens worth ${usd_value_now:.2} bought for ${usd_cost:.2}, pnl ${pnl_usd:.2}"
+            ),
+            Event::BiddingStopped { reason } => {
+                write!(f, "all bidding stopped until restart: {reason}")
+            }
+            Event::PriceStale { denom, age_secs } => write!(
+                f,
+                "no fresh USD price for {} in {age_secs} seconds",
+                denom.symbol()
+            ),
+        }
+    }
+}
+
+/// A destination for [`Event`]s
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn notify(&self, event: &Event) -> Result<()>;
+}
+
+/// Messages a Telegram chat through the Bot API
+pub struct TelegramNotifier {
+    http: reqwest::Client,
+    bot_token: String,
+    chat_id: String,
+}
+
+impl TelegramNotifier {
+    pub fn new(config: TelegramConfig) -> Result<Self> {
+        let token_env = config
+            .bot_token_env
+            .as_deref()
+            .unwrap_or(DEFAULT_TELEGRAM_TOKEN_ENV);
+        let bot_token = std::env::var(token_env)
+            .map_err(|_| eyre!("telegram notifications need a bot token in {token_env}"))?;
+
+        Ok(Self {
+            http: reqwest::Client::new(),
+            bot_token,
+            chat_id: config.chat_id,
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for TelegramNotifier {
+    async fn notify(&self, event: &Event) -> Result<()> {
+        // the url embeds the bot token, so it's stripped from errors before they can be logged
+        let url = format!("https://api.telegram.org/bot{}/sendMessage", self.bot_token);
+        self.http
+            .post(url)
+            .json(&serde_json::json!({
+                "chat_id": self.chat_id,
+                "text": event.to_string(),
+            }))
+            .send()
+            .await
+            .map_err(|err| err.without_url())?
+            .error_for_status()
+            .map_err(|err| err.without_url())?;
+
+        Ok(())
+    }
+}
+
+/// Emails each event to the recipients who opted in to it, as HTML with a plain text alternative
+pub struct EmailNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    template: String,
+    recipients: Vec<(Mailbox, EmailRecipient)>,
+}
+
+impl EmailNotifier {
+    pub fn new(config: EmailConfig) -> Result<Self> {
+        let username_env = config
+            .username_env
+            .as_deref()
+            .unwrap_or(DEFAULT_SMTP_USERNAME_ENV);
+        let password_env = config
+            .password_env
+            .as_deref()
+            .unwrap_or(DEFAULT_SMTP_PASSWORD_ENV);
+        let username = std::env::var(username_env)
+            .map_err(|_| eyre!("email notifications need an SMTP username in {username_env}"))?;
+        let password = std::env::var(password_env)
+            .map_err(|_| eyre!("email notifications need an SMTP password in {password_env}"))?;
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)?
+            .port(config.smtp_port.unwrap_or(587))
+            .credentials(Credentials::new(username, password))
+            .build();
+
+        let template = match &config.template_path {
+            Some(path) => std::fs::read_to_string(path)
+                .map_err(|err| eyre!("failed to read email template {path}: {err}"))?,
+            None => DEFAULT_EMAIL_TEMPLATE.to_string(),
+        };
+        let from = config
+            .from
+            .parse()
+            .map_err(|err| eyre!("invalid email address {}: {err}", config.from))?;
+        let recipients = config
+            .recipients
+            .into_iter()
+            .map(|recipient| {
+                let mailbox = recipient
+                    .address
+                    .parse()
+                    .map_err(|err| eyre!("invalid email address {}: {err}", recipient.address))?;
+
+                Ok((mailbox, recipient))
+            })
+            .collect::<Result<Vec<(Mailbox, EmailRecipient)>>>()?;
+
+        Ok(Self {
+            transport,
+            from,
+            template,
+            recipients,
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for EmailNotifier {
+    async fn notify(&self, event: &Event) -> Result<()> {
+        let subject = email_subject(event);
+        let message = event.to_string();
+        let html = render_email(&self.template, &subject, &message);
+        for (mailbox, _) in self.recipients.iter().filter(|(_, r)| r.wants(event)) {
+            let email = Message::builder()
+                .from(self.from.clone())
+                .to(mailbox.clone())
+                .subject(&subject)
+                .multipart(MultiPart::alternative_plain_html(
+                    message.clone(),
+                    html.clone(),
+                ))?;
+            self.transport.send(email).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Subject line of the email for an event, like "Auction bot: bid filled"
+pub fn email_subject(event: &Event) -> String {
+    format!("Auction bot: {}", event.kind().replace('_', " "))
+}
+
+/// Fills an HTML email template's `{{subject}}` and `{{message}}`, escaping both
+pub fn render_email(template: &str, subject: &str, message: &str) -> String {
+    template
+        .replace("{{subject}}", &escape_html(subject))
+        .replace("{{message}}", &escape_html(message))
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// POSTs events as JSON to a URL
+pub struct WebhookNotifier {
+    http: reqwest::Client,
+    url: String,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: String) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            url,
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    async fn notify(&self, event: &Event) -> Result<()> {
+        self.http
+            .post(&self.url)
+            .json(event)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// Constructs the configured [`Notifier`]s
+pub fn notifiers(config: NotificationConfig) -> Result<Vec<Box<dyn Notifier>>> {
+    let mut notifiers: Vec<Box<dyn Notifier>> = Vec::new();
+    if let Some(telegram) = config.telegram {
+        notifiers.push(Box::new(TelegramNotifier::new(telegram)?));
+    }
+    if let Some(email) = config.email {
+        notifiers.push(Box::new(EmailNotifier::new(email)?));
+    }
+    for url in config.webhooks.unwrap_or_default() {
+        notifiers.push(Box::new(WebhookNotifier::new(url)));
+    }
+
+    Ok(notifiers)
+}
+
+/// Queues events for delivery so sending never holds up bidding. The default handle drops every
+/// event.
+#[derive(Debug, Default, Clone)]
+pub struct Notifications {
+    events: Option<UnboundedSender<Event>>,
+}
+
+impl Notifications {
+    /// Spawns a task delivering events to every notifier until all handles are dropped
+    pub fn spawn(notifiers: Vec<Box<dyn Notifier>>) -> (Self, JoinHandle<()>) {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<Event>();
+        let task = tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                for notifier in &notifiers {
+                    if let Err(err) = notifier.notify(&event).await {
+                        warn!("failed to send notification: {err:?}");
+                    }
+                }
+            }
+        });
+
+        (Self { events: Some(tx) }, task)
+    }
+
+    pub fn send(&self, event: Event) {
+        if let Some(events) = &self.events {
+            let _ = events.send(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_json() {
+        let event = Event::OrderExhausted { order_id: 3 };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({ "event": "order_exhausted", "order_id": 3 })
+        );
+        assert_eq!(event.to_string(), "order 3 has spent its whole budget");
+        assert_eq!(event.kind(), "order_exhausted");
+    }
+
+    #[test]
+    fn test_email() {
+        let event = Event::BidFailed {
+            order_id: 1,
+            auction_id: 9,
+            wallet: "default".to_string(),
+            reason: "fee <too> low".to_string(),
+        };
+        let subject = email_subject(&event);
+        assert_eq!(subject, "Auction bot: bid failed");
+        assert_eq!(
+            render_email(
+                "<h1>{{subject}}</h1><p>{{message}}</p>",
+                &subject,
+                &event.to_string()
+            ),
+            "<h1>Auction bot: bid failed</h1><p>order 1 bid in auction 9 from wallet default failed: fee &lt;too&gt; low</p>"
+        );
+
+        let mut recipient = EmailRecipient {
+            address: "ops@example.com".to_string(),
+            events: None,
+        };
+        assert!(!recipient.wants(&event));
+        assert!(recipient.wants(&Event::PnlSummary {
+            fills: 1,
+            usd_cost: 1.0,
+            usd_value_now: 1.0,
+            pnl_usd: 0.0,
+        }));
+        recipient.events = Some(vec!["bid_failed".to_string()]);
+        assert!(recipient.wants(&event));
+    }
+}
@@ -1,9 +1,416 @@
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use sommelier_auction::denom::Denom;
+use sommelier_auction::{auction::Auction, bid::Bid, denom::Denom, fee::TxFee, BidResult};
+use tracing::warn;
+
+use crate::evaluate;
 
 #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Order {
+    /// Assigned by the engine when orders are loaded
+    #[serde(skip_deserializing)]
+    pub id: usize,
     pub fee_token: Denom,
+    /// The remaining usomm budget of the order. Decremented as bids are filled.
     pub maximum_usomm_in: u64,
+    /// The minimum USD value of tokens a bid must receive. May be left out for limit orders.
+    #[serde(default)]
     pub minimum_usd_value_out: f64,
+    /// Optional limit price in SOMM per whole token. The order only bids at or below it.
+    pub max_somm_per_token: Option<f64>,
+    /// Optional limit price in USD per whole token, valuing SOMM at its USD price. The order only
+    /// bids at or below it.
+    pub max_usd_per_token: Option<f64>,
+    /// Optional unix timestamp in seconds after which the order is dropped
+    pub expires_at: Option<u64>,
+    /// Optional block height after which the order is dropped
+    pub expires_at_height: Option<u64>,
+    /// Optional dollar-cost averaging. When set, `maximum_usomm_in` is the order's total budget and
+    /// `minimum_usd_value_out` applies to each bid.
+    pub dca: Option<Dca>,
+    /// Optional conditions under which the order deactivates itself
+    pub stop: Option<StopConditions>,
+    /// Optional name of the wallet to bid from. Bids rotate through the wallets if unset.
+    pub wallet: Option<String>,
+    /// Optional margin in basis points taken off the computed minimum tokens out, in case the price
+    /// the bid was computed from is stale
+    pub slippage_margin_bps: Option<u32>,
+    /// Optional priority when orders compete for an auction. Higher goes first, ties go to the
+    /// order loaded first. Defaults to 0.
+    pub priority: Option<i32>,
+    /// Optional share of an auction's supply under pro-rata allocation. Defaults to 1.
+    pub weight: Option<u32>,
+    /// Optional sniping. The order holds its bids until late in the auction.
+    pub snipe: Option<Snipe>,
+    /// Optional competition awareness. The order bids ahead of its price target when other bidders
+    /// are buying up an auction's supply fast.
+    pub compete: Option<Compete>,
+    /// Optional fixed transaction fee for the order's bids, e.g. `{ amount = 5000, denom = "usomm" }`.
+    /// If unset, the fee is chosen from the gas prices and the signing account's balances.
+    pub tx_fee: Option<TxFee>,
+    /// Optional wallets that share the order's bids, typically each bidding through authz for a
+    /// different granter. Every bid the order makes is split into one bid per member, sized by
+    /// weight. Replaces `wallet`.
+    pub group: Option<Vec<GroupMember>>,
+    /// Optional name of a configured strategy module that decides the order's bids in place of its
+    /// limit prices and `minimum_usd_value_out`
+    pub strategy: Option<String>,
+    /// Optional window in seconds to value the token over at its time-weighted average USD price,
+    /// instead of the latest price. Smooths out short spikes in the price feed.
+    pub twap_window_secs: Option<u64>,
+    /// Optional splitting of each bid into smaller ones over consecutive blocks, averaging the
+    /// entry price over the auction's price steps
+    pub split: Option<Split>,
+}
+
+/// A wallet bidding a share of a grouped order's bids
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupMember {
+    /// Name of the wallet
+    pub wallet: String,
+    /// Optional share of each bid relative to the other members. Defaults to 1.
+    pub weight: Option<u32>,
+}
+
+/// Spends a fixed amount of usomm per auction or per time window instead of one max-size bid
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dca {
+    /// usomm to offer in each bid
+    pub usomm_per_bid: u64,
+    /// Optional minimum number of seconds between bids. If unset, the order bids at most once per
+    /// auction.
+    pub interval_secs: Option<u64>,
+}
+
+/// Spreads what an order would spend on an auction over several smaller bids, each made at the
+/// auction's price at the time
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Split {
+    /// Number of bids the order's bid size is divided into
+    pub parts: u32,
+    /// Optional minimum number of blocks between the bids. Defaults to 1.
+    pub blocks_between: Option<u64>,
+}
+
+impl Split {
+    /// The usomm to offer in each part of a bid of `bid_size`, rounded up so the parts cover it
+    pub fn part_size(&self, bid_size: u64) -> u64 {
+        bid_size.div_ceil(u64::from(self.parts.max(1)))
+    }
+
+    /// Whether the next part may be bid at `height`, given the height the last part filled at.
+    /// Without a known height the order waits.
+    pub fn ready(&self, last_height: Option<u64>, height: Option<u64>) -> bool {
+        match (last_height, height) {
+            (None, _) => true,
+            (Some(last), Some(height)) => height >= last + self.blocks_between.unwrap_or(1),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+/// Holds an order's bids until late in an auction, trading fill certainty for better prices. The
+/// order bids once either condition is met.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snipe {
+    /// Bid only within this many blocks of the auction's end block
+    pub blocks_before_end: Option<u64>,
+    /// Bid once the price can fall by at most this many basis points more before the auction ends,
+    /// projected at the current decrease rate
+    pub max_remaining_decline_bps: Option<u32>,
+}
+
+impl Snipe {
+    /// Whether the order may bid on `auction` at block `height`. Without a known height neither
+    /// condition can be checked, so the order waits.
+    pub fn ready(&self, auction: &Auction, height: Option<u64>) -> bool {
+        if self.blocks_before_end.is_none() && self.max_remaining_decline_bps.is_none() {
+            return true;
+        }
+        let height = match height {
+            Some(h) => h,
+            None => return false,
+        };
+
+        if matches!(self.blocks_before_end, Some(b) if auction.end_block.saturating_sub(height) <= b)
+        {
+            return true;
+        }
+
+        match self.max_remaining_decline_bps {
+            Some(max) => match evaluate::remaining_decline_bps(auction, height) {
+                Ok(decline) => decline <= Decimal::from(max),
+                Err(err) => {
+                    warn!(
+                        "failed to project price decline of auction {}: {err:?}",
+                        auction.id
+                    );
+
+                    false
+                }
+            },
+            None => false,
+        }
+    }
+}
+
+/// Relaxes an order's price target when an auction is projected to sell out soon at the rate
+/// other bidders are buying it, so the order doesn't wait for a price it will never see
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Compete {
+    /// Start relaxing once the auction is projected to sell out within this many blocks
+    pub sellout_blocks: u64,
+    /// Most the price target is relaxed by, in basis points, reached as the sellout becomes
+    /// imminent. The relaxation scales linearly from zero at `sellout_blocks`.
+    pub max_premium_bps: u32,
+}
+
+impl Compete {
+    /// How many basis points to relax the price target by, given how many blocks until the
+    /// auction is projected to sell out
+    pub fn premium_bps(&self, blocks_until_sellout: Option<u64>) -> u32 {
+        let blocks = match blocks_until_sellout {
+            Some(b) if b < self.sellout_blocks => b,
+            _ => return 0,
+        };
+        let premium =
+            u64::from(self.max_premium_bps) * (self.sellout_blocks - blocks) / self.sellout_blocks;
+
+        premium as u32
+    }
+}
+
+/// Kill rules for an [`Order`]. Each is only enforced if set.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StopConditions {
+    /// Deactivate if the order's token hasn't had a fresh USD price for this many seconds
+    pub max_price_age_secs: Option<u64>,
+    /// Deactivate if the USD price of SOMM moves by more than this percentage from when the engine
+    /// first priced it
+    pub max_somm_price_change_pct: Option<f64>,
+    /// Deactivate after this many bid submissions in a row fail
+    pub max_consecutive_failures: Option<u32>,
+}
+
+impl StopConditions {
+    /// Returns why the order should be deactivated, if any condition is met. Unknown inputs never
+    /// trigger a condition.
+    pub fn triggered(
+        &self,
+        price_age_secs: Option<u64>,
+        somm_price_change_pct: Option<f64>,
+        consecutive_failures: u32,
+    ) -> Option<String> {
+        if let (Some(max), Some(age)) = (self.max_price_age_secs, price_age_secs) {
+            if age > max {
+                return Some(format!("price is {age} seconds old, limit is {max}"));
+            }
+        }
+
+        if let (Some(max), Some(change)) = (self.max_somm_price_change_pct, somm_price_change_pct) {
+            if change.abs() > max {
+                return Some(format!("SOMM price moved {change:.2}%, limit is {max}%"));
+            }
+        }
+
+        if let Some(max) = self.max_consecutive_failures {
+            if consecutive_failures >= max {
+                return Some(format!("{consecutive_failures} consecutive failed bids"));
+            }
+        }
+
+        None
+    }
+}
+
+impl Order {
+    /// Whether the order has expired as of `now` (unix seconds) and, if known, the block `height`
+    pub fn is_expired(&self, now: u64, height: Option<u64>) -> bool {
+        if matches!(self.expires_at, Some(t) if now >= t) {
+            return true;
+        }
+
+        matches!((self.expires_at_height, height), (Some(e), Some(h)) if h >= e)
+    }
+
+    /// A copy of the order with its price target relaxed by `bps` basis points: a lower minimum
+    /// USD value out and higher limit prices
+    pub fn with_premium(&self, bps: u32) -> Order {
+        let premium = f64::from(bps) / 10_000.0;
+
+        Order {
+            minimum_usd_value_out: self.minimum_usd_value_out * (1.0 - premium).max(0.0),
+            max_somm_per_token: self.max_somm_per_token.map(|p| p * (1.0 + premium)),
+            max_usd_per_token: self.max_usd_per_token.map(|p| p * (1.0 + premium)),
+            ..self.clone()
+        }
+    }
+
+    /// A copy of the order that bids at most `usomm`, with its minimum USD value out scaled down
+    /// in proportion so a part of a bid qualifies at the same price as the whole
+    pub fn part(&self, usomm: u64) -> Order {
+        let bid_size = self.bid_size();
+        let usomm = usomm.min(bid_size);
+        let share = if bid_size == 0 {
+            1.0
+        } else {
+            usomm as f64 / bid_size as f64
+        };
+
+        Order {
+            maximum_usomm_in: usomm,
+            minimum_usd_value_out: self.minimum_usd_value_out * share,
+            dca: None,
+            ..self.clone()
+        }
+    }
+
+    /// Takes `usomm` spent by a fill off the order's budget. The minimum USD value out shrinks in
+    /// proportion, as with [`Order::part`], so the remainder qualifies at the same price as the
+    /// whole. A DCA order's minimum applies to each bid and is left as is.
+    pub fn spend(&mut self, usomm: u64) {
+        let previous = self.maximum_usomm_in;
+        self.maximum_usomm_in = previous.saturating_sub(usomm);
+        if self.dca.is_none() && previous > 0 {
+            self.minimum_usd_value_out *= self.maximum_usomm_in as f64 / previous as f64;
+        }
+    }
+
+    /// The usomm to offer in the next bid
+    pub fn bid_size(&self) -> u64 {
+        match &self.dca {
+            Some(dca) => std::cmp::min(dca.usomm_per_bid, self.maximum_usomm_in),
+            None => self.maximum_usomm_in,
+        }
+    }
+
+    /// Whether a DCA order may bid on `auction_id` at `now` (unix seconds) given its last filled
+    /// bid. Always true for other orders.
+    pub fn dca_ready(&self, auction_id: u32, now: u64, last_bid: Option<(u32, u64)>) -> bool {
+        let (dca, (last_auction_id, last_bid_at)) = match (&self.dca, last_bid) {
+            (Some(dca), Some(last_bid)) => (dca, last_bid),
+            _ => return true,
+        };
+
+        match dca.interval_secs {
+            Some(interval) => now.saturating_sub(last_bid_at) >= interval,
+            None => auction_id != last_auction_id,
+        }
+    }
+}
+
+/// A bid placed on behalf of an [`Order`]
+#[derive(Debug, Clone)]
+pub struct OrderBid {
+    pub order_id: usize,
+    pub bid: Bid,
+    /// The wallet named by the order or its group, if any
+    pub wallet: Option<String>,
+    /// USD price of one whole token the bid was evaluated at
+    pub usd_price: Option<f64>,
+    /// USD price of one SOMM the bid was evaluated at, if known
+    pub somm_usd_price: Option<f64>,
+    /// The order's fixed transaction fee, if it sets one
+    pub tx_fee: Option<TxFee>,
+}
+
+/// The outcome of submitting an [`OrderBid`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fill {
+    pub order_id: usize,
+    pub auction_id: u32,
+    /// usomm paid for the bid
+    pub usomm_spent: u128,
+    /// Fee tokens received. Zero if the outcome of the bid is unknown.
+    pub tokens_received: u128,
+    /// Whether the submission failed or its outcome is unknown
+    pub failed: bool,
+}
+
+impl Fill {
+    /// Builds a fill from the on-chain result of a bid. If the amount paid can't be read we assume
+    /// the whole bid was spent.
+    pub fn from_bid_result(order_id: usize, bid: &Bid, result: &BidResult) -> Self {
+        let usomm_spent = result
+            .total_usomm_paid
+            .as_ref()
+            .and_then(|c| c.amount.parse::<u128>().ok())
+            .unwrap_or(bid.maximum_usomm_in);
+        let tokens_received = result
+            .total_fulfilled_sale_tokens
+            .as_ref()
+            .and_then(|c| c.amount.parse::<u128>().ok())
+            .unwrap_or_default();
+
+        Self {
+            order_id,
+            auction_id: bid.auction_id,
+            usomm_spent,
+            tokens_received,
+            failed: false,
+        }
+    }
+
+    /// Builds a fill for a bid that was never submitted
+    pub fn skipped(order_id: usize, auction_id: u32) -> Self {
+        Self {
+            order_id,
+            auction_id,
+            usomm_spent: 0,
+            tokens_received: 0,
+            failed: false,
+        }
+    }
+
+    /// Builds a fill for a bid that definitely failed without spending anything
+    pub fn failed(order_id: usize, auction_id: u32) -> Self {
+        Self {
+            failed: true,
+            ..Self::skipped(order_id, auction_id)
+        }
+    }
+
+    /// Builds a fill for a bid whose outcome is unknown, cautiously assuming the whole bid was
+    /// spent.
+    pub fn unknown(order_id: usize, bid: &Bid) -> Self {
+        Self {
+            order_id,
+            auction_id: bid.auction_id,
+            usomm_spent: bid.maximum_usomm_in,
+            tokens_received: 0,
+            failed: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        let split = Split {
+            parts: 3,
+            blocks_between: Some(2),
+        };
+        assert_eq!(split.part_size(1_000), 334);
+        assert!(split.ready(None, None));
+        assert!(!split.ready(Some(100), Some(101)));
+        assert!(split.ready(Some(100), Some(102)));
+        assert!(!split.ready(Some(100), None));
+
+        let order = Order {
+            maximum_usomm_in: 1_000,
+            minimum_usd_value_out: 10.0,
+            dca: Some(Dca {
+                usomm_per_bid: 500,
+                interval_secs: None,
+            }),
+            ..Default::default()
+        };
+        let part = order.part(split.part_size(order.bid_size()));
+        assert_eq!(part.maximum_usomm_in, 167);
+        assert_eq!(part.bid_size(), 167);
+        assert!((part.minimum_usd_value_out - 3.34).abs() < 1e-9);
+    }
 }
@@ -0,0 +1,175 @@
+use std::{collections::HashMap, fmt::Display};
+
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use serde::{Deserialize, Serialize};
+use sommelier_auction::{bid::Bid, denom::Denom};
+
+use crate::order::Fill;
+
+const USOMM_PER_SOMM: f64 = 1_000_000.0;
+
+/// Settings for paper trading, where bids fill against a simulated balance instead of being
+/// submitted
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaperConfig {
+    /// usomm the virtual portfolio starts with
+    pub starting_usomm: u128,
+}
+
+/// A simulated usomm balance and the tokens bought with it by virtual fills
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaperPortfolio {
+    pub usomm_balance: u128,
+    /// Base units of each token bought
+    pub holdings: HashMap<Denom, u128>,
+    /// USD value of the SOMM spent, at the SOMM price of each fill
+    pub usd_cost: f64,
+    /// The latest USD price of each token seen, for valuing holdings
+    pub usd_prices: HashMap<Denom, f64>,
+    pub fills: usize,
+}
+
+impl PaperPortfolio {
+    pub fn new(config: PaperConfig) -> Self {
+        Self {
+            usomm_balance: config.starting_usomm,
+            ..Default::default()
+        }
+    }
+
+    /// Fills a bid virtually at the auction's current unit price, the way the chain would: the bid
+    /// buys as many tokens as its usomm affords, up to what's left in the auction, and fails if
+    /// that's less than its minimum tokens out or the balance can't cover it
+    pub fn fill(
+        &mut self,
+        order_id: usize,
+        bid: &Bid,
+        unit_price_in_usomm: Decimal,
+        remaining_tokens_for_sale: u128,
+        usd_price: Option<f64>,
+        somm_usd_price: Option<f64>,
+    ) -> Fill {
+        if let Some(price) = usd_price {
+            self.usd_prices.insert(bid.fee_token, price);
+        }
+
+        let affordable = if unit_price_in_usomm.is_zero() {
+            remaining_tokens_for_sale
+        } else {
+            (Decimal::from(bid.maximum_usomm_in) / unit_price_in_usomm)
+                .floor()
+                .to_u128()
+                .unwrap_or(remaining_tokens_for_sale)
+        };
+        let tokens = affordable.min(remaining_tokens_for_sale);
+        let usomm_spent = (Decimal::from(tokens) * unit_price_in_usomm)
+            .ceil()
+            .to_u128()
+            .unwrap_or(u128::MAX)
+            .min(bid.maximum_usomm_in);
+        if tokens == 0 || tokens < bid.minimum_tokens_out || usomm_spent > self.usomm_balance {
+            return Fill::failed(order_id, bid.auction_id);
+        }
+
+        self.usomm_balance -= usomm_spent;
+        *self.holdings.entry(bid.fee_token).or_default() += tokens;
+        self.usd_cost += usomm_spent as f64 / USOMM_PER_SOMM * somm_usd_price.unwrap_or_default();
+        self.fills += 1;
+
+        Fill {
+            order_id,
+            auction_id: bid.auction_id,
+            usomm_spent,
+            tokens_received: tokens,
+            failed: false,
+        }
+    }
+
+    /// USD value of the holdings at the latest prices seen. Tokens without a price are left out.
+    pub fn holdings_usd_value(&self) -> f64 {
+        self.holdings
+            .iter()
+            .filter_map(|(denom, amount)| {
+                let price = self.usd_prices.get(denom)?;
+                Some(*amount as f64 / 10f64.powi(denom.decimals() as i32) * price)
+            })
+            .sum()
+    }
+
+    /// Value of the holdings less what the SOMM spent on them was worth
+    pub fn pnl_usd(&self) -> f64 {
+        self.holdings_usd_value() - self.usd_cost
+    }
+}
+
+impl Display for PaperPortfolio {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} fills, {} usomm left, holdings worth ${:.2} bought for ${:.2}, pnl ${:.2}",
+            self.fills,
+            self.usomm_balance,
+            self.holdings_usd_value(),
+            self.usd_cost,
+            self.pnl_usd()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bid(maximum_usomm_in: u128, minimum_tokens_out: u128) -> Bid {
+        Bid {
+            auction_id: 1,
+            fee_token: Denom::USDC,
+            maximum_usomm_in,
+            minimum_tokens_out,
+        }
+    }
+
+    #[test]
+    fn test_fill() {
+        let mut portfolio = PaperPortfolio::new(PaperConfig {
+            starting_usomm: 150_000_000,
+        });
+
+        // 100 SOMM at 10 usomm per base unit buys 10 USDC
+        let fill = portfolio.fill(
+            0,
+            &bid(100_000_000, 9_000_000),
+            Decimal::from(10),
+            1_000_000_000,
+            Some(1.0),
+            Some(0.2),
+        );
+        assert_eq!(fill.usomm_spent, 100_000_000);
+        assert_eq!(fill.tokens_received, 10_000_000);
+        assert_eq!(portfolio.usomm_balance, 50_000_000);
+        assert!((portfolio.pnl_usd() - 10.0 + 20.0).abs() < 1e-9);
+
+        // only 2 USDC left, below the minimum tokens out
+        let fill = portfolio.fill(
+            1,
+            &bid(40_000_000, 3_000_000),
+            Decimal::from(10),
+            2_000_000,
+            Some(1.0),
+            Some(0.2),
+        );
+        assert!(fill.failed);
+
+        // the balance can't cover the bid
+        let fill = portfolio.fill(
+            2,
+            &bid(100_000_000, 1),
+            Decimal::from(10),
+            1_000_000_000,
+            Some(1.0),
+            Some(0.2),
+        );
+        assert!(fill.failed);
+        assert_eq!(portfolio.fills, 1);
+    }
+}
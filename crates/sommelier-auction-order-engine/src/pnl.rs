@@ -0,0 +1,276 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use sommelier_auction::denom::Denom;
+
+use crate::{
+    notify::{Event, Notifications},
+    order::Fill,
+    util::unix_now,
+};
+
+const USOMM_PER_SOMM: f64 = 1_000_000.0;
+
+/// Settings for profit and loss reporting
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PnlConfig {
+    /// Optional number of seconds between PnL summary notifications. No summaries are sent if not
+    /// set.
+    pub summary_interval_secs: Option<u64>,
+}
+
+/// What the engine has spent and received bidding for one token
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    pub fee_token: Denom,
+    pub fills: u64,
+    pub usomm_spent: u128,
+    /// Base units of the token received
+    pub tokens_received: u128,
+    /// USD value of the SOMM spent, at the SOMM price of each fill. Fills without a SOMM price are
+    /// left out.
+    pub usd_cost: f64,
+    /// USD value of the tokens received, at the token price of each fill. Fills without a token
+    /// price are left out.
+    pub usd_value_at_fill: f64,
+}
+
+impl Position {
+    fn new(fee_token: Denom) -> Self {
+        Self {
+            fee_token,
+            ..Default::default()
+        }
+    }
+
+    fn record(&mut self, fill: &Fill, usd_price: Option<f64>, somm_usd_price: Option<f64>) {
+        self.fills += 1;
+        self.usomm_spent += fill.usomm_spent;
+        self.tokens_received += fill.tokens_received;
+        self.usd_cost +=
+            fill.usomm_spent as f64 / USOMM_PER_SOMM * somm_usd_price.unwrap_or_default();
+        self.usd_value_at_fill +=
+            self.whole_tokens(fill.tokens_received) * usd_price.unwrap_or_default();
+    }
+
+    fn whole_tokens(&self, amount: u128) -> f64 {
+        amount as f64 / 10f64.powi(self.fee_token.decimals() as i32)
+    }
+
+    // Values the position at the token's current price
+    fn report(&self, usd_prices: &HashMap<Denom, f64>) -> PositionReport {
+        let usd_value_now = usd_prices
+            .get(&self.fee_token)
+            .map(|price| self.whole_tokens(self.tokens_received) * price);
+
+        PositionReport {
+            position: self.clone(),
+            usd_value_now,
+            pnl_usd: usd_value_now.map(|value| value - self.usd_cost),
+        }
+    }
+}
+
+/// A [`Position`] valued at current prices
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PositionReport {
+    #[serde(flatten)]
+    pub position: Position,
+    /// USD value of the tokens received at the token's current price, if it's known
+    pub usd_value_now: Option<f64>,
+    /// What selling the tokens at the current price would realize, less the USD cost of the SOMM
+    /// spent on them
+    pub pnl_usd: Option<f64>,
+}
+
+/// Profit and loss of every order and wallet since the engine started
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PnlReport {
+    pub generated_at: u64,
+    pub orders: BTreeMap<usize, PositionReport>,
+    /// Each wallet's positions, one per token bought
+    pub wallets: BTreeMap<String, Vec<PositionReport>>,
+    pub fills: u64,
+    pub usd_cost: f64,
+    /// USD value now of every token received. Tokens without a current price are left out.
+    pub usd_value_now: f64,
+    pub pnl_usd: f64,
+}
+
+impl Display for PnlReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} fills across {} orders, tokens worth ${:.2} bought for ${:.2}, pnl ${:.2}",
+            self.fills,
+            self.orders.len(),
+            self.usd_value_now,
+            self.usd_cost,
+            self.pnl_usd
+        )
+    }
+}
+
+/// Tracks fills by order and by wallet, and the latest USD prices to value them at
+#[derive(Debug, Default, Clone)]
+pub struct Pnl {
+    orders: HashMap<usize, Position>,
+    wallets: HashMap<String, HashMap<Denom, Position>>,
+    usd_prices: HashMap<Denom, f64>,
+}
+
+impl Pnl {
+    /// Records a fill of an order's bid for `fee_token` from `wallet`, valued at the prices the bid
+    /// was evaluated at. Fills that spent and received nothing are ignored.
+    pub fn record(
+        &mut self,
+        wallet: &str,
+        fee_token: Denom,
+        fill: &Fill,
+        usd_price: Option<f64>,
+        somm_usd_price: Option<f64>,
+    ) {
+        if fill.failed || (fill.usomm_spent == 0 && fill.tokens_received == 0) {
+            return;
+        }
+
+        self.orders
+            .entry(fill.order_id)
+            .or_insert_with(|| Position::new(fee_token))
+            .record(fill, usd_price, somm_usd_price);
+        self.wallets
+            .entry(wallet.to_string())
+            .or_default()
+            .entry(fee_token)
+            .or_insert_with(|| Position::new(fee_token))
+            .record(fill, usd_price, somm_usd_price);
+    }
+
+    /// Updates the prices positions are valued at
+    pub fn update_prices(&mut self, prices: &HashMap<Denom, f64>) {
+        self.usd_prices
+            .extend(prices.iter().map(|(denom, price)| (*denom, *price)));
+    }
+
+    pub fn report(&self, now: u64) -> PnlReport {
+        let orders = self
+            .orders
+            .iter()
+            .map(|(id, position)| (*id, position.report(&self.usd_prices)))
+            .collect::<BTreeMap<usize, PositionReport>>();
+        let wallets = self
+            .wallets
+            .iter()
+            .map(|(name, positions)| {
+                let mut positions = positions
+                    .values()
+                    .map(|p| p.report(&self.usd_prices))
+                    .collect::<Vec<PositionReport>>();
+                positions.sort_by_key(|p| p.position.fee_token.symbol());
+
+                (name.clone(), positions)
+            })
+            .collect();
+        let fills = orders.values().map(|o| o.position.fills).sum();
+        let usd_cost = orders.values().map(|o| o.position.usd_cost).sum::<f64>();
+        let usd_value_now = orders.values().filter_map(|o| o.usd_value_now).sum::<f64>();
+
+        PnlReport {
+            generated_at: now,
+            orders,
+            wallets,
+            fills,
+            usd_cost,
+            usd_value_now,
+            pnl_usd: usd_value_now - usd_cost,
+        }
+    }
+}
+
+/// Updates the shared PnL, tolerating a poisoned lock
+pub fn update(pnl: &RwLock<Pnl>, f: impl FnOnce(&mut Pnl)) {
+    let mut pnl = pnl.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(&mut pnl);
+}
+
+/// Builds a report from the shared PnL, tolerating a poisoned lock
+pub fn report(pnl: &RwLock<Pnl>) -> PnlReport {
+    pnl.read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .report(unix_now())
+}
+
+/// Sends a PnL summary notification every `interval` until the task is aborted
+pub async fn send_summaries(
+    pnl: Arc<RwLock<Pnl>>,
+    notifications: Notifications,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    // the first tick completes immediately, before anything has filled
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        let report = report(&pnl);
+        notifications.send(Event::PnlSummary {
+            fills: report.fills,
+            usd_cost: report.usd_cost,
+            usd_value_now: report.usd_value_now,
+            pnl_usd: report.pnl_usd,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(order_id: usize, usomm_spent: u128, tokens_received: u128) -> Fill {
+        Fill {
+            order_id,
+            auction_id: 1,
+            usomm_spent,
+            tokens_received,
+            failed: false,
+        }
+    }
+
+    #[test]
+    fn test_report() {
+        let mut pnl = Pnl::default();
+        // 100 SOMM at $0.20 for 10 USDC, then 50 SOMM at $0.10 for 5 USDC
+        pnl.record(
+            "a",
+            Denom::USDC,
+            &fill(0, 100_000_000, 10_000_000),
+            Some(1.0),
+            Some(0.2),
+        );
+        pnl.record(
+            "b",
+            Denom::USDC,
+            &fill(1, 50_000_000, 5_000_000),
+            Some(1.0),
+            Some(0.1),
+        );
+        pnl.record("a", Denom::USDC, &Fill::skipped(0, 2), Some(1.0), Some(0.1));
+
+        // without a current price the tokens aren't valued
+        let report = pnl.report(10);
+        assert_eq!(report.fills, 2);
+        assert!((report.usd_cost - 25.0).abs() < 1e-9);
+        assert_eq!(report.orders[&0].usd_value_now, None);
+        assert!((report.orders[&0].position.usd_value_at_fill - 10.0).abs() < 1e-9);
+
+        pnl.update_prices(&HashMap::from([(Denom::USDC, 0.5)]));
+        let report = pnl.report(20);
+        assert!((report.usd_value_now - 7.5).abs() < 1e-9);
+        assert!((report.pnl_usd + 17.5).abs() < 1e-9);
+        assert!((report.wallets["b"][0].pnl_usd.unwrap() + 2.5).abs() < 1e-9);
+    }
+}
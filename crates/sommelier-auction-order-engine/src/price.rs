@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use eyre::{eyre, Result};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use sommelier_auction::{client::Client, denom::Denom};
+use tracing::warn;
+
+use crate::util;
+
+// upper bound on price queries in flight at once
+const MAX_CONCURRENT_QUERIES: usize = 8;
+
+/// Selects which [`PriceSource`] the engine uses to value auctioned tokens
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PriceSourceKind {
+    /// USD prices from the CoinGecko API
+    #[default]
+    CoinGecko,
+    /// USD prices stored on-chain by the Sommelier auction module
+    Sommelier,
+    /// Spot prices from Osmosis pools paired with a USD stablecoin
+    Osmosis,
+}
+
+/// A source of USD prices for auctioned denoms
+#[async_trait]
+pub trait PriceSource: Send + Sync {
+    /// Returns the USD price of one whole token for each of `denoms` the source is able to price.
+    /// Denoms without a price are left out of the result rather than treated as an error.
+    async fn usd_prices(&self, denoms: &[Denom]) -> Result<HashMap<Denom, f64>>;
+}
+
+/// Constructs the configured [`PriceSource`]
+pub fn price_source(
+    kind: PriceSourceKind,
+    grpc_endpoint: String,
+    osmosis: Option<OsmosisConfig>,
+) -> Result<Box<dyn PriceSource>> {
+    Ok(match kind {
+        PriceSourceKind::CoinGecko => Box::new(CoinGeckoPriceSource),
+        PriceSourceKind::Sommelier => Box::new(SommelierPriceSource::new(grpc_endpoint)),
+        PriceSourceKind::Osmosis => match osmosis {
+            Some(config) => Box::new(OsmosisPriceSource::new(config)),
+            None => {
+                return Err(eyre!(
+                    "price_source is osmosis but no [osmosis] section was provided in the config"
+                ))
+            }
+        },
+    })
+}
+
+/// Prices from the CoinGecko API. The free tier is rate limited, so callers should not query
+/// every block.
+#[derive(Debug, Default, Clone)]
+pub struct CoinGeckoPriceSource;
+
+#[async_trait]
+impl PriceSource for CoinGeckoPriceSource {
+    async fn usd_prices(&self, denoms: &[Denom]) -> Result<HashMap<Denom, f64>> {
+        // more than one denom can map to the same coingecko id (e.g. testnet tokens)
+        let mut coingecko_ids_reverse_lookup = HashMap::<String, Vec<Denom>>::new();
+        for denom in denoms {
+            coingecko_ids_reverse_lookup
+                .entry(util::denom_to_coingecko_id(*denom))
+                .or_default()
+                .push(*denom);
+        }
+
+        let coingecko_ids = coingecko_ids_reverse_lookup
+            .keys()
+            .cloned()
+            .collect::<Vec<String>>();
+        let prices = price_feed::get_usd_price_for_assets(None, coingecko_ids.clone())
+            .await
+            .map_err(|err| eyre!("failed to get prices for {coingecko_ids:?}: {err:?}"))?;
+
+        Ok(prices
+            .into_iter()
+            .filter_map(|(cid, p)| coingecko_ids_reverse_lookup.get(&cid).map(|d| (d, p)))
+            .flat_map(|(matched, p)| matched.iter().map(move |d| (*d, p)))
+            .collect())
+    }
+}
+
+/// Prices from the Sommelier auction module's on-chain token prices. These are the prices the
+/// chain itself uses to value auctions. They are set by governance, so they can lag the market.
+#[derive(Debug, Clone)]
+pub struct SommelierPriceSource {
+    grpc_endpoint: String,
+}
+
+impl SommelierPriceSource {
+    pub fn new(grpc_endpoint: String) -> Self {
+        Self { grpc_endpoint }
+    }
+}
+
+#[async_trait]
+impl PriceSource for SommelierPriceSource {
+    async fn usd_prices(&self, denoms: &[Denom]) -> Result<HashMap<Denom, f64>> {
+        let mut client = Client::with_endpoints("".to_string(), self.grpc_endpoint.clone()).await?;
+
+        let mut prices = HashMap::new();
+        for token_price in client.token_prices().await? {
+            let denom = match Denom::try_from(&token_price.denom) {
+                Ok(d) => d,
+                Err(_) => continue,
+            };
+            if !denoms.contains(&denom) {
+                continue;
+            }
+
+            match token_price.usd_price.parse::<f64>() {
+                Ok(p) => {
+                    prices.insert(denom, p);
+                }
+                Err(err) => warn!(
+                    "failed to parse on-chain usd price {} for {denom}: {err:?}",
+                    token_price.usd_price
+                ),
+            }
+        }
+
+        Ok(prices)
+    }
+}
+
+/// Configuration for [`OsmosisPriceSource`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OsmosisConfig {
+    /// Osmosis LCD (REST) endpoint
+    pub lcd_endpoint: String,
+    /// The Osmosis denom of the USD stablecoin every pool is quoted in, e.g. the IBC denom of USDC
+    pub quote_denom: String,
+    /// Decimals of the quote denom
+    pub quote_decimals: u8,
+    /// The pool to price each Sommelier denom with
+    pub pools: Vec<OsmosisPool>,
+}
+
+/// Maps a Sommelier denom to the Osmosis pool used to price it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OsmosisPool {
+    /// The denom as it appears on Sommelier
+    pub denom: Denom,
+    /// The ID of an Osmosis pool containing the token and the quote denom
+    pub pool_id: u64,
+    /// The IBC denom of the token on Osmosis
+    pub osmosis_denom: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SpotPriceResponse {
+    spot_price: String,
+}
+
+/// Spot prices from Osmosis pools. The spot price is read as quote base units per token base unit
+/// and scaled by each side's decimals, so it only gives USD prices when the quote denom is a USD
+/// stablecoin.
+#[derive(Debug, Clone)]
+pub struct OsmosisPriceSource {
+    config: OsmosisConfig,
+    http: reqwest::Client,
+}
+
+impl OsmosisPriceSource {
+    pub fn new(config: OsmosisConfig) -> Self {
+        Self {
+            config,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    async fn spot_price(&self, pool: &OsmosisPool) -> Result<f64> {
+        let url = format!(
+            "{}/osmosis/poolmanager/v1beta1/{}/prices",
+            self.config.lcd_endpoint.trim_end_matches('/'),
+            pool.pool_id
+        );
+        let response = self
+            .http
+            .get(url)
+            .query(&[
+                ("base_asset_denom", pool.osmosis_denom.as_str()),
+                ("quote_asset_denom", self.config.quote_denom.as_str()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<SpotPriceResponse>()
+            .await?;
+        let spot_price = response.spot_price.parse::<f64>()?;
+        let scale = 10f64.powi(pool.denom.decimals() as i32 - self.config.quote_decimals as i32);
+
+        Ok(spot_price * scale)
+    }
+}
+
+#[async_trait]
+impl PriceSource for OsmosisPriceSource {
+    async fn usd_prices(&self, denoms: &[Denom]) -> Result<HashMap<Denom, f64>> {
+        let results = futures::stream::iter(
+            self.config
+                .pools
+                .iter()
+                .filter(|p| denoms.contains(&p.denom)),
+        )
+        .map(|pool| async move { (pool, self.spot_price(pool).await) })
+        .buffer_unordered(MAX_CONCURRENT_QUERIES)
+        .collect::<Vec<_>>()
+        .await;
+
+        let mut prices = HashMap::new();
+        for (pool, result) in results {
+            match result {
+                Ok(p) => {
+                    prices.insert(pool.denom, p);
+                }
+                Err(err) => warn!(
+                    "failed to get spot price for {} from osmosis pool {}: {err:?}",
+                    pool.denom, pool.pool_id
+                ),
+            }
+        }
+
+        Ok(prices)
+    }
+}
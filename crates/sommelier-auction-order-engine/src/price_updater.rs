@@ -0,0 +1,227 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use sommelier_auction::denom::Denom;
+use tokio::sync::watch;
+use tracing::{debug, info, warn};
+
+use crate::{
+    notify::{Event, Notifications},
+    price::PriceSource,
+    util::unix_now,
+};
+
+/// Four refreshes of the watcher at its default interval, to stay under the CoinGecko free tier's
+/// rate limit
+pub const DEFAULT_PRICE_REFRESH_INTERVAL_SECS: u64 = 24;
+
+pub const DEFAULT_PRICE_STALE_AFTER_SECS: u64 = 600;
+
+/// The latest USD price of each denom and the unix time each was fetched
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PriceSnapshot {
+    pub prices: HashMap<Denom, f64>,
+    pub updated_at: HashMap<Denom, u64>,
+}
+
+/// The watcher's end of the [`PriceUpdater`]. It names the denoms to price and receives their
+/// prices.
+pub struct PriceFeed {
+    denoms: watch::Sender<Vec<Denom>>,
+    snapshots: watch::Receiver<PriceSnapshot>,
+}
+
+impl PriceFeed {
+    /// Sets the denoms the updater prices. A change takes effect right away rather than at the
+    /// next scheduled refresh.
+    pub fn request(&self, mut denoms: Vec<Denom>) {
+        denoms.sort_by_key(|d| d.symbol());
+        self.denoms.send_if_modified(|current| {
+            let changed = *current != denoms;
+            *current = denoms;
+
+            changed
+        });
+    }
+
+    /// The latest prices, if they've been refreshed since this was last called
+    pub fn latest(&mut self) -> Option<PriceSnapshot> {
+        match self.snapshots.has_changed() {
+            Ok(true) => Some(self.snapshots.borrow_and_update().clone()),
+            _ => None,
+        }
+    }
+
+    /// Waits up to `timeout` for the first prices, so the first evaluation doesn't run without them
+    pub async fn wait_for_first(&mut self, timeout: Duration) {
+        if self.snapshots.borrow().updated_at.is_empty() {
+            let _ = tokio::time::timeout(timeout, self.snapshots.changed()).await;
+        }
+    }
+}
+
+/// Refreshes USD prices from a [`PriceSource`] on a schedule and feeds them to the watcher,
+/// warning when a denom's price goes stale
+pub struct PriceUpdater {
+    source: Box<dyn PriceSource>,
+    denoms: watch::Receiver<Vec<Denom>>,
+    snapshots: watch::Sender<PriceSnapshot>,
+    refresh_interval: Duration,
+    stale_after_secs: u64,
+    notifications: Notifications,
+    // denoms already warned about, so each is only reported when it goes stale
+    stale: HashSet<Denom>,
+    started_at: u64,
+}
+
+impl PriceUpdater {
+    pub fn new(
+        source: Box<dyn PriceSource>,
+        refresh_interval: Duration,
+        stale_after_secs: u64,
+    ) -> (Self, PriceFeed) {
+        let (denoms_tx, denoms_rx) = watch::channel(Vec::new());
+        let (snapshots_tx, snapshots_rx) = watch::channel(PriceSnapshot::default());
+        let updater = Self {
+            source,
+            denoms: denoms_rx,
+            snapshots: snapshots_tx,
+            refresh_interval,
+            stale_after_secs,
+            notifications: Notifications::default(),
+            stale: HashSet::new(),
+            started_at: unix_now(),
+        };
+        let feed = PriceFeed {
+            denoms: denoms_tx,
+            snapshots: snapshots_rx,
+        };
+
+        (updater, feed)
+    }
+
+    /// Sends a notification when a denom's price goes stale
+    pub fn with_notifications(mut self, notifications: Notifications) -> Self {
+        self.notifications = notifications;
+
+        self
+    }
+
+    /// Refreshes prices every interval, and whenever the requested denoms change, until the task is
+    /// aborted or the watcher goes away
+    pub async fn run(mut self) {
+        let mut ticker = tokio::time::interval(self.refresh_interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                changed = self.denoms.changed() => {
+                    if changed.is_err() {
+                        return;
+                    }
+                    ticker.reset();
+                }
+            }
+
+            self.refresh().await;
+            self.check_staleness(unix_now());
+        }
+    }
+
+    async fn refresh(&mut self) {
+        let denoms = self.denoms.borrow_and_update().clone();
+        if denoms.is_empty() {
+            return;
+        }
+
+        debug!("refreshing prices");
+        // this will probably hit the per-minute query rate limit now and then, so the last prices
+        // are kept until the next refresh
+        let prices = match self.source.usd_prices(&denoms).await {
+            Ok(prices) => prices,
+            Err(err) => {
+                warn!("failed to refresh prices: {err:?}");
+
+                return;
+            }
+        };
+        let now = unix_now();
+        self.snapshots.send_modify(|snapshot| {
+            snapshot
+                .updated_at
+                .extend(prices.keys().map(|denom| (*denom, now)));
+            snapshot.prices = prices;
+        });
+    }
+
+    fn check_staleness(&mut self, now: u64) {
+        let denoms = self.denoms.borrow().clone();
+        let stale = stale_denoms(
+            &denoms,
+            &self.snapshots.borrow().updated_at,
+            self.started_at,
+            now,
+            self.stale_after_secs,
+        );
+
+        for (denom, age_secs) in &stale {
+            if self.stale.contains(denom) {
+                continue;
+            }
+            warn!(
+                denom = denom.symbol().as_str(),
+                age_secs, "USD price is stale, orders for it won't be evaluated at current prices"
+            );
+            self.notifications.send(Event::PriceStale {
+                denom: *denom,
+                age_secs: *age_secs,
+            });
+        }
+        let stale = stale
+            .into_iter()
+            .map(|(denom, _)| denom)
+            .collect::<HashSet<Denom>>();
+        for denom in self.stale.difference(&stale) {
+            info!(denom = denom.symbol().as_str(), "USD price is fresh again");
+        }
+        self.stale = stale;
+    }
+}
+
+/// Each of `denoms` whose price hasn't been refreshed for more than `stale_after_secs`, and how
+/// long it's been. Denoms never priced count from `started_at`.
+pub fn stale_denoms(
+    denoms: &[Denom],
+    updated_at: &HashMap<Denom, u64>,
+    started_at: u64,
+    now: u64,
+    stale_after_secs: u64,
+) -> Vec<(Denom, u64)> {
+    denoms
+        .iter()
+        .map(|denom| {
+            let since = updated_at.get(denom).copied().unwrap_or(started_at);
+            (*denom, now.saturating_sub(since))
+        })
+        .filter(|(_, age)| *age > stale_after_secs)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_denoms() {
+        let updated_at = HashMap::from([(Denom::USDC, 1_000), (Denom::WETH, 1_500)]);
+        let denoms = [Denom::USDC, Denom::WETH, Denom::WBTC];
+
+        assert!(stale_denoms(&denoms, &updated_at, 900, 1_500, 600).is_empty());
+        assert_eq!(
+            stale_denoms(&denoms, &updated_at, 900, 1_700, 600),
+            vec![(Denom::USDC, 700), (Denom::WBTC, 800)]
+        );
+    }
+}
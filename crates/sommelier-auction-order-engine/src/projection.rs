@@ -0,0 +1,130 @@
+use eyre::{eyre, Result};
+use rust_decimal::Decimal;
+use sommelier_auction::auction::Auction;
+
+use crate::{evaluate, order::Order};
+
+/// Default upper bound on how long the watcher sleeps on a projected wake-up
+pub const DEFAULT_MAX_PROJECTED_WAIT_SECS: u64 = 60;
+
+/// Projects an active auction's unit price forward from block `height`, returning the block and
+/// price of each future decrease until the auction ends or the price reaches zero. Each decrease
+/// takes the initial price times the current decrease rate, then the rate grows by
+/// `acceleration_rate`.
+pub fn project_prices(
+    auction: &Auction,
+    height: u64,
+    acceleration_rate: Decimal,
+) -> Result<Vec<(u64, Decimal)>> {
+    let initial_price = evaluate::parse_dec(&auction.initial_unit_price_in_usomm)?;
+    let mut rate = evaluate::parse_dec(&auction.current_price_decrease_rate)?;
+    let mut price = evaluate::unit_price_in_usomm(auction)?;
+    let interval = auction.price_decrease_block_interval.max(1);
+
+    // decreases happen every interval blocks from the start block
+    let mut block = auction.start_block
+        + (height.saturating_sub(auction.start_block) / interval + 1) * interval;
+    let mut projection = Vec::new();
+    while block <= auction.end_block {
+        price = initial_price
+            .checked_mul(rate)
+            .and_then(|d| price.checked_sub(d))
+            .ok_or_else(|| eyre!("price out of range for auction {}", auction.id))?;
+        if price <= Decimal::ZERO {
+            break;
+        }
+
+        projection.push((block, price));
+        rate += acceleration_rate;
+        block += interval;
+    }
+
+    Ok(projection)
+}
+
+/// The first block after `height` at which `order` would bid on `auction` if prices hold, or None
+/// if it never would before the auction ends
+pub fn first_qualifying_block(
+    order: &Order,
+    usd_unit_value: f64,
+    somm_usd_price: Option<f64>,
+    auction: &Auction,
+    height: u64,
+    acceleration_rate: Decimal,
+) -> Result<Option<u64>> {
+    for (block, price) in project_prices(auction, height, acceleration_rate)? {
+        let projected = Auction {
+            current_unit_price_in_usomm: evaluate::encode_dec(price)?,
+            ..auction.clone()
+        };
+        if evaluate::evaluate_bid(order, usd_unit_value, somm_usd_price, &projected)?.is_some() {
+            return Ok(Some(block));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use sommelier_auction::{cosmos_sdk_proto::cosmos::base::v1beta1::Coin, denom::Denom};
+
+    use super::*;
+
+    // 10 usomm per base unit of USDC at block 100, dropping by 1 usomm every 10 blocks
+    fn auction() -> Auction {
+        Auction {
+            id: 1,
+            start_block: 100,
+            end_block: 150,
+            current_price_decrease_rate: "100000000000000000".to_string(),
+            price_decrease_block_interval: 10,
+            initial_unit_price_in_usomm: "10000000000000000000".to_string(),
+            current_unit_price_in_usomm: "9000000000000000000".to_string(),
+            remaining_tokens_for_sale: Some(Coin {
+                denom: Denom::USDC.to_string(),
+                amount: "1000000000".to_string(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_project_prices() {
+        let projection = project_prices(&auction(), 112, Decimal::ZERO).unwrap();
+
+        assert_eq!(
+            projection,
+            vec![
+                (120, Decimal::from(8)),
+                (130, Decimal::from(7)),
+                (140, Decimal::from(6)),
+                (150, Decimal::from(5))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_first_qualifying_block() {
+        // 100 SOMM must buy at least 15 USDC, so the price has to reach 6.66 usomm per base unit
+        let order = Order {
+            fee_token: Denom::USDC,
+            maximum_usomm_in: 100_000_000,
+            minimum_usd_value_out: 15.0,
+            ..Default::default()
+        };
+
+        let block =
+            first_qualifying_block(&order, 1.0, None, &auction(), 112, Decimal::ZERO).unwrap();
+        assert_eq!(block, Some(140));
+
+        // never qualifies before the auction ends
+        let order = Order {
+            minimum_usd_value_out: 50.0,
+            ..order
+        };
+        let block =
+            first_qualifying_block(&order, 1.0, None, &auction(), 112, Decimal::ZERO).unwrap();
+        assert_eq!(block, None);
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+const MINUTE_SECS: u64 = 60;
+
+/// Caps on how often the engine submits bids across all orders. Unset limits are not enforced.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    /// Maximum bids submitted in any one block
+    pub max_bids_per_block: Option<u32>,
+    /// Maximum bids submitted in any rolling 60 second window
+    pub max_bids_per_minute: Option<u32>,
+}
+
+/// Tracks bid submissions against a [`RateLimitConfig`]. Times are unix seconds.
+#[derive(Debug, Default, Clone)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    // (time, block height) of every submission in the last minute
+    submissions: VecDeque<(u64, Option<u64>)>,
+    // the block of the latest submission and how many were made in it
+    block: Option<(u64, u32)>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Whether a block height is needed to check the per-block limit
+    pub fn needs_height(&self) -> bool {
+        self.config.max_bids_per_block.is_some()
+    }
+
+    /// Why a bid may not be submitted at `height` right now, if it may not. A per-block limit
+    /// isn't enforced when the height is unknown.
+    pub fn check(&mut self, now: u64, height: Option<u64>) -> Option<String> {
+        self.expire(now);
+
+        if let Some(limit) = self.config.max_bids_per_minute {
+            if self.submissions.len() >= limit as usize {
+                return Some(format!("rate limit of {limit} bids per minute reached"));
+            }
+        }
+        if let (Some(limit), Some(height)) = (self.config.max_bids_per_block, height) {
+            if matches!(self.block, Some((block, count)) if block == height && count >= limit) {
+                return Some(format!(
+                    "rate limit of {limit} bids per block reached at height {height}"
+                ));
+            }
+        }
+
+        None
+    }
+
+    /// Records a bid submitted at `height`
+    pub fn record(&mut self, now: u64, height: Option<u64>) {
+        self.submissions.push_back((now, height));
+        if let Some(height) = height {
+            self.block = match self.block {
+                Some((block, count)) if block == height => Some((block, count + 1)),
+                _ => Some((height, 1)),
+            };
+        }
+        self.expire(now);
+    }
+
+    fn expire(&mut self, now: u64) {
+        while matches!(self.submissions.front(), Some((t, _)) if now.saturating_sub(*t) >= MINUTE_SECS)
+        {
+            self.submissions.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        let mut limiter = RateLimiter::new(RateLimitConfig {
+            max_bids_per_block: Some(2),
+            max_bids_per_minute: Some(3),
+        });
+
+        assert!(limiter.check(0, Some(100)).is_none());
+        limiter.record(0, Some(100));
+        limiter.record(1, Some(100));
+        assert!(limiter.check(2, Some(100)).is_some());
+
+        // a new block resets the per-block count but not the per-minute one
+        assert!(limiter.check(6, Some(101)).is_none());
+        limiter.record(6, Some(101));
+        assert!(limiter.check(7, Some(102)).is_some());
+
+        // the first submissions age out of the window
+        assert!(limiter.check(61, Some(110)).is_none());
+    }
+}
@@ -0,0 +1,165 @@
+use std::time::Duration;
+
+use eyre::Result;
+use sommelier_auction::{
+    bid::Bid, client::Client, cosmos_sdk_proto::cosmos::base::v1beta1::Coin, BidResult,
+};
+
+use crate::submit;
+
+/// How many times to look for a bid's on-chain record before giving up
+const CONFIRM_ATTEMPTS: u32 = 5;
+
+// roughly a block, so each attempt sees new state
+const CONFIRM_INTERVAL: Duration = Duration::from_secs(6);
+
+/// How a submitted bid settled on chain
+#[derive(Debug, Clone, PartialEq)]
+pub enum Receipt {
+    /// The chain's record of the bid matches what was asked for and what the transaction reported
+    Confirmed(BidResult),
+    /// The chain's record of the bid doesn't match, for each of the reasons given
+    Discrepant(BidResult, Vec<String>),
+    /// No record of the bid was found on chain
+    NotFound,
+}
+
+impl Receipt {
+    /// The chain's record of the bid, if it was found
+    pub fn on_chain(&self) -> Option<&BidResult> {
+        match self {
+            Receipt::Confirmed(result) | Receipt::Discrepant(result, _) => Some(result),
+            Receipt::NotFound => None,
+        }
+    }
+}
+
+/// Polls the auction for the chain's record of a submitted bid and checks how it was fulfilled.
+/// `reported` is the bid the transaction response returned, if its outcome is known. Otherwise
+/// the auction's bids placed after `after_bid_id` are searched for one matching `bid` from
+/// `bidder`.
+pub async fn reconcile(
+    client: &mut Client,
+    bidder: &str,
+    bid: &Bid,
+    after_bid_id: u64,
+    reported: Option<&BidResult>,
+) -> Result<Receipt> {
+    for attempt in 1..=CONFIRM_ATTEMPTS {
+        let on_chain = match reported {
+            Some(reported) => Some(client.auction_bid(bid.auction_id, reported.id).await?),
+            None => submit::find_landed_bid(client, bidder, bid, after_bid_id).await?,
+        };
+        if let Some(on_chain) = on_chain {
+            let discrepancies = discrepancies(bid, bidder, reported, &on_chain);
+            if discrepancies.is_empty() {
+                return Ok(Receipt::Confirmed(on_chain));
+            }
+
+            return Ok(Receipt::Discrepant(on_chain, discrepancies));
+        }
+
+        if attempt < CONFIRM_ATTEMPTS {
+            tokio::time::sleep(CONFIRM_INTERVAL).await;
+        }
+    }
+
+    Ok(Receipt::NotFound)
+}
+
+/// Ways the chain's record of a bid differs from what was bid and from what the transaction
+/// response reported
+pub fn discrepancies(
+    bid: &Bid,
+    bidder: &str,
+    reported: Option<&BidResult>,
+    on_chain: &BidResult,
+) -> Vec<String> {
+    let mut discrepancies = Vec::new();
+    let paid = amount(&on_chain.total_usomm_paid);
+    let received = amount(&on_chain.total_fulfilled_sale_tokens);
+
+    if on_chain.bidder != bidder {
+        discrepancies.push(format!(
+            "bid {} was made by {}, not {bidder}",
+            on_chain.id, on_chain.bidder
+        ));
+    }
+    if received == 0 {
+        discrepancies.push("the transaction succeeded but no tokens were received".to_string());
+    } else if received < bid.minimum_tokens_out {
+        discrepancies.push(format!(
+            "received {received} tokens, less than the minimum of {}",
+            bid.minimum_tokens_out
+        ));
+    }
+    if paid > bid.maximum_usomm_in {
+        discrepancies.push(format!(
+            "paid {paid} usomm, more than the maximum of {}",
+            bid.maximum_usomm_in
+        ));
+    }
+    if let Some(reported) = reported {
+        let reported_paid = amount(&reported.total_usomm_paid);
+        let reported_received = amount(&reported.total_fulfilled_sale_tokens);
+        if (reported_paid, reported_received) != (paid, received) {
+            discrepancies.push(format!(
+                "the transaction reported paying {reported_paid} usomm for {reported_received} tokens, but the chain records {paid} usomm for {received}"
+            ));
+        }
+    }
+
+    discrepancies
+}
+
+fn amount(coin: &Option<Coin>) -> u128 {
+    coin.as_ref()
+        .and_then(|c| c.amount.parse().ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use sommelier_auction::denom::Denom;
+
+    use super::*;
+
+    fn coin(amount: u128) -> Option<Coin> {
+        Some(Coin {
+            denom: String::new(),
+            amount: amount.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_discrepancies() {
+        let bid = Bid {
+            auction_id: 1,
+            fee_token: Denom::USDC,
+            maximum_usomm_in: 100,
+            minimum_tokens_out: 10,
+        };
+        let on_chain = BidResult {
+            id: 7,
+            auction_id: 1,
+            bidder: "somm1bidder".to_string(),
+            total_usomm_paid: coin(100),
+            total_fulfilled_sale_tokens: coin(12),
+            ..Default::default()
+        };
+        assert!(discrepancies(&bid, "somm1bidder", Some(&on_chain), &on_chain).is_empty());
+
+        let unfulfilled = BidResult {
+            total_usomm_paid: coin(0),
+            total_fulfilled_sale_tokens: coin(0),
+            ..on_chain.clone()
+        };
+        assert_eq!(
+            discrepancies(&bid, "somm1bidder", Some(&on_chain), &unfulfilled),
+            vec![
+                "the transaction succeeded but no tokens were received".to_string(),
+                "the transaction reported paying 100 usomm for 12 tokens, but the chain records 0 usomm for 0".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,253 @@
+use std::{path::Path, time::Duration};
+
+use eyre::{eyre, Result};
+use notify::{RecursiveMode, Watcher as _};
+use tokio::sync::{mpsc::UnboundedSender, oneshot};
+use tracing::{debug, info, warn};
+
+use crate::{admin::AdminCommand, config, order::Order};
+
+// editors tend to write a file in several steps, so changes are read once they settle
+const SETTLE_DELAY: Duration = Duration::from_millis(500);
+
+/// An order whose configuration changed
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderEdit {
+    /// ID of the running order
+    pub id: usize,
+    /// The order as it was configured before
+    pub previous: Order,
+    /// The order as it's configured now
+    pub order: Order,
+}
+
+/// How the configured orders changed between two loads of the config file
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct OrderChanges {
+    pub added: Vec<Order>,
+    /// IDs of the running orders that are no longer configured
+    pub removed: Vec<usize>,
+    pub edited: Vec<OrderEdit>,
+}
+
+impl OrderChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.edited.is_empty()
+    }
+}
+
+/// Matches the orders in a reloaded config against the ones configured before, which are paired
+/// with the IDs they run under. Orders that are unchanged match themselves. Of the rest, a previous
+/// order and a current one with the same fee token are taken to be an edit, in the order they
+/// appear in the file. Anything left over was removed or added.
+pub fn diff(previous: &[(usize, Order)], current: &[Order]) -> OrderChanges {
+    let mut unmatched = current.iter().collect::<Vec<&Order>>();
+    let mut changed = Vec::new();
+    for (id, order) in previous {
+        match unmatched.iter().position(|o| *o == order) {
+            Some(index) => {
+                unmatched.remove(index);
+            }
+            None => changed.push((*id, order)),
+        }
+    }
+
+    let mut changes = OrderChanges::default();
+    for (id, previous) in changed {
+        match unmatched
+            .iter()
+            .position(|o| o.fee_token == previous.fee_token)
+        {
+            Some(index) => changes.edited.push(OrderEdit {
+                id,
+                previous: previous.clone(),
+                order: unmatched.remove(index).clone(),
+            }),
+            None => changes.removed.push(id),
+        }
+    }
+    changes.added = unmatched.into_iter().cloned().collect();
+
+    changes
+}
+
+/// The fields that differ between two versions of an order, as `field: old -> new`
+pub fn changed_fields(previous: &Order, order: &Order) -> Vec<String> {
+    let (previous, order) = match (serde_json::to_value(previous), serde_json::to_value(order)) {
+        (Ok(serde_json::Value::Object(previous)), Ok(serde_json::Value::Object(order))) => {
+            (previous, order)
+        }
+        _ => return Vec::new(),
+    };
+
+    order
+        .iter()
+        .filter(|(key, _)| key.as_str() != "id")
+        .filter_map(|(key, value)| {
+            let old = previous.get(key).unwrap_or(&serde_json::Value::Null);
+            (old != value).then(|| format!("{key}: {old} -> {value}"))
+        })
+        .collect()
+}
+
+/// Watches the config file at `path` and applies changes to its orders to the running watcher, all
+/// at once, between evaluations. `orders` are the orders the engine was started with, paired with
+/// their IDs. A config that fails to load is logged and skipped, leaving the running orders as they
+/// are. Only orders are reloaded; other settings need a restart.
+pub async fn watch_orders(
+    path: String,
+    mut orders: Vec<(usize, Order)>,
+    commands: UnboundedSender<AdminCommand>,
+) -> Result<()> {
+    let file_name = Path::new(&path)
+        .file_name()
+        .ok_or_else(|| eyre!("{path} is not a file"))?
+        .to_owned();
+    let dir = match Path::new(&path).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => Path::new(".").to_path_buf(),
+    };
+
+    // the directory is watched rather than the file, since editors often save by replacing it
+    let (changed_tx, mut changed_rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut file_watcher =
+        notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            if let Ok(event) = event {
+                if event
+                    .paths
+                    .iter()
+                    .any(|p| p.file_name() == Some(file_name.as_os_str()))
+                {
+                    let _ = changed_tx.send(());
+                }
+            }
+        })?;
+    file_watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+    info!("watching {path} for order changes");
+
+    for order in orders.iter_mut() {
+        order.1.id = 0;
+    }
+    while changed_rx.recv().await.is_some() {
+        tokio::time::sleep(SETTLE_DELAY).await;
+        while changed_rx.try_recv().is_ok() {}
+
+        let config = match config::load(&path) {
+            Ok(config) => config,
+            Err(err) => {
+                warn!("not reloading orders: {err}");
+                continue;
+            }
+        };
+        let changes = diff(&orders, &config.orders);
+        if changes.is_empty() {
+            debug!("{path} changed but its orders didn't");
+            continue;
+        }
+        log_changes(&changes);
+
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let command = AdminCommand::ReloadOrders {
+            changes: changes.clone(),
+            reply: reply_tx,
+        };
+        if commands.send(command).is_err() {
+            // the watcher has shut down
+            return Ok(());
+        }
+        let added_ids = reply_rx
+            .await
+            .map_err(|_| eyre!("watcher dropped the order reload"))?;
+
+        orders.retain(|(id, _)| !changes.removed.contains(id));
+        for edit in changes.edited {
+            if let Some(order) = orders.iter_mut().find(|(id, _)| *id == edit.id) {
+                order.1 = edit.order;
+            }
+        }
+        orders.extend(added_ids.into_iter().zip(changes.added));
+    }
+
+    Ok(())
+}
+
+fn log_changes(changes: &OrderChanges) {
+    for order in &changes.added {
+        info!("config added order: {order:?}");
+    }
+    for id in &changes.removed {
+        info!("config removed order {id}");
+    }
+    for edit in &changes.edited {
+        info!(
+            "config changed order {}: {}",
+            edit.id,
+            changed_fields(&edit.previous, &edit.order).join(", ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sommelier_auction::denom::Denom;
+
+    use super::*;
+
+    fn order(fee_token: Denom, maximum_usomm_in: u64) -> Order {
+        Order {
+            fee_token,
+            maximum_usomm_in,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let previous = vec![
+            (0, order(Denom::USDC, 100)),
+            (1, order(Denom::WETH, 200)),
+            (4, order(Denom::WBTC, 300)),
+        ];
+        let current = vec![
+            order(Denom::WETH, 250),
+            order(Denom::USDC, 100),
+            order(Denom::USDT, 50),
+        ];
+
+        let changes = diff(&previous, &current);
+        assert_eq!(changes.added, vec![order(Denom::USDT, 50)]);
+        assert_eq!(changes.removed, vec![4]);
+        assert_eq!(
+            changes.edited,
+            vec![OrderEdit {
+                id: 1,
+                previous: order(Denom::WETH, 200),
+                order: order(Denom::WETH, 250),
+            }]
+        );
+
+        let previous = current
+            .iter()
+            .cloned()
+            .enumerate()
+            .collect::<Vec<(usize, Order)>>();
+        assert!(diff(&previous, &current).is_empty());
+    }
+
+    #[test]
+    fn test_changed_fields() {
+        let previous = order(Denom::WETH, 200);
+        let mut order = previous.clone();
+        order.id = 3;
+        order.maximum_usomm_in = 250;
+        order.max_somm_per_token = Some(1.5);
+
+        assert_eq!(
+            changed_fields(&previous, &order),
+            vec![
+                "max_somm_per_token: null -> 1.5".to_string(),
+                "maximum_usomm_in: 200 -> 250".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use sommelier_auction::signer::{SignMode, Signer};
+
+/// How long to wait for the signing service by default. Signing holds the wallet's sequence, so a
+/// stalled service would otherwise hold up every later bid from the wallet.
+pub const DEFAULT_SIGNER_TIMEOUT_SECS: u64 = 10;
+
+/// A signing service holding the wallet's key. The engine POSTs each sign doc to `url` as
+/// `{"address", "sign_mode": "direct", "sign_doc": <base64>}` and expects `{"signature": <base64>}`
+/// back, a 64 byte secp256k1 signature over the SHA-256 of the sign doc.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemoteSignerConfig {
+    pub url: String,
+    /// The key's bech32 address
+    pub address: String,
+    /// The key's base64 encoded compressed public key
+    pub public_key: String,
+    /// Optional environment variable holding a bearer token sent with each request
+    pub token_env: Option<String>,
+    /// Optional seconds to wait for the service to sign before failing the bid. Defaults to 10.
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct SignRequest<'a> {
+    address: &'a str,
+    sign_mode: &'static str,
+    sign_doc: String,
+}
+
+#[derive(Deserialize)]
+struct SignResponse {
+    signature: String,
+}
+
+pub struct RemoteSigner {
+    http: reqwest::Client,
+    url: String,
+    address: String,
+    public_key: Vec<u8>,
+    token: Option<String>,
+}
+
+impl RemoteSigner {
+    pub fn new(config: RemoteSignerConfig) -> Result<Self> {
+        let public_key = BASE64
+            .decode(&config.public_key)
+            .map_err(|err| eyre!("invalid remote signer public key: {err}"))?;
+        let token = match config.token_env {
+            Some(env) => Some(
+                std::env::var(&env).map_err(|_| eyre!("remote signer token not found in {env}"))?,
+            ),
+            None => None,
+        };
+
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(
+                config
+                    .timeout_secs
+                    .unwrap_or(DEFAULT_SIGNER_TIMEOUT_SECS)
+                    .max(1),
+            ))
+            .build()?;
+
+        Ok(Self {
+            http,
+            url: config.url,
+            address: config.address,
+            public_key,
+            token,
+        })
+    }
+}
+
+#[async_trait]
+impl Signer for RemoteSigner {
+    fn address(&self) -> &str {
+        &self.address
+    }
+
+    fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    fn sign_mode(&self) -> SignMode {
+        SignMode::Direct
+    }
+
+    async fn sign(&self, sign_doc: Vec<u8>) -> Result<Vec<u8>> {
+        let mut request = self.http.post(&self.url).json(&SignRequest {
+            address: &self.address,
+            sign_mode: "direct",
+            sign_doc: BASE64.encode(sign_doc),
+        });
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response: SignResponse = request.send().await?.error_for_status()?.json().await?;
+        let signature = BASE64.decode(response.signature)?;
+        if signature.len() != 64 {
+            return Err(eyre!(
+                "remote signer returned a {} byte signature, expected 64",
+                signature.len()
+            ));
+        }
+
+        Ok(signature)
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::{HashMap, VecDeque};
+
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use sommelier_auction::{bid::Bid, denom::Denom};
+
+use crate::{order::Fill, price::PriceSourceKind};
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+const USOMM_PER_SOMM: f64 = 1_000_000.0;
+
+/// Risk controls enforced over every bid, whichever order or wallet it's from. Unset limits are not
+/// enforced. Unlike budgets, a bid that would break a limit is skipped rather than shrunk.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RiskConfig {
+    /// Maximum USD value of any one denom bought by the engine, counting what a bid could buy at
+    /// the SOMM price it was evaluated at
+    pub max_exposure_usd_per_denom: Option<f64>,
+    /// Maximum USD value of SOMM spent in any rolling 24 hour window, at the SOMM price of each bid
+    pub max_daily_spend_usd: Option<f64>,
+    /// Maximum usomm offered in any single bid
+    pub max_bid_usomm: Option<u64>,
+    /// Stops all bidding until the engine is restarted if the price source and
+    /// `reference_price_source` disagree on a price by more than this percentage
+    pub max_price_divergence_pct: Option<f64>,
+    /// Price source prices are checked against. Required with `max_price_divergence_pct`.
+    pub reference_price_source: Option<PriceSourceKind>,
+}
+
+/// Enforces a [`RiskConfig`]. Times are unix seconds.
+#[derive(Debug, Default, Clone)]
+pub struct RiskManager {
+    config: RiskConfig,
+    // (time, USD) of every spend in the last day
+    spends: VecDeque<(u64, f64)>,
+    // base units of each denom bought
+    holdings: HashMap<Denom, u128>,
+    // why bidding was stopped, once a kill switch trips
+    killed: Option<String>,
+}
+
+impl RiskManager {
+    pub fn new(config: RiskConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Why bidding was stopped, if it was
+    pub fn killed(&self) -> Option<&str> {
+        self.killed.as_deref()
+    }
+
+    /// The price source prices must be checked against before each bid, if any. Fails if a price
+    /// divergence limit is set without one.
+    pub fn reference_price_source(&self) -> Result<Option<PriceSourceKind>> {
+        match (
+            self.config.max_price_divergence_pct,
+            self.config.reference_price_source,
+        ) {
+            (Some(_), None) => Err(eyre!(
+                "risk.max_price_divergence_pct is set but risk.reference_price_source is not"
+            )),
+            (Some(_), kind) => Ok(kind),
+            (None, _) => Ok(None),
+        }
+    }
+
+    /// Why `bid` may not be submitted, if it may not. `usd_price` and `somm_usd_price` are the
+    /// prices it was evaluated at. Limits valued in USD reject bids whose prices aren't known.
+    pub fn check(
+        &mut self,
+        bid: &Bid,
+        usd_price: Option<f64>,
+        somm_usd_price: Option<f64>,
+        now: u64,
+    ) -> Option<String> {
+        if let Some(reason) = &self.killed {
+            return Some(format!("bidding was stopped: {reason}"));
+        }
+        self.expire(now);
+
+        if let Some(max) = self.config.max_bid_usomm {
+            if bid.maximum_usomm_in > u128::from(max) {
+                return Some(format!(
+                    "bid of {} usomm is over the {max} usomm single bid limit",
+                    bid.maximum_usomm_in
+                ));
+            }
+        }
+
+        if self.config.max_daily_spend_usd.is_none()
+            && self.config.max_exposure_usd_per_denom.is_none()
+        {
+            return None;
+        }
+        let somm_usd_price = match somm_usd_price {
+            Some(p) => p,
+            None => return Some("no SOMM price to check USD risk limits against".to_string()),
+        };
+        let bid_usd = bid.maximum_usomm_in as f64 / USOMM_PER_SOMM * somm_usd_price;
+
+        if let Some(max) = self.config.max_daily_spend_usd {
+            let spent = self.spends.iter().map(|(_, usd)| usd).sum::<f64>();
+            if spent + bid_usd > max {
+                return Some(format!(
+                    "bid worth ${bid_usd:.2} would take daily spend from ${spent:.2} past the ${max:.2} limit"
+                ));
+            }
+        }
+
+        if let Some(max) = self.config.max_exposure_usd_per_denom {
+            let usd_price = match usd_price {
+                Some(p) => p,
+                None => return Some("no token price to check exposure against".to_string()),
+            };
+            let held = self
+                .holdings
+                .get(&bid.fee_token)
+                .copied()
+                .unwrap_or_default();
+            let exposure = held as f64 / 10f64.powi(bid.fee_token.decimals() as i32) * usd_price;
+            if exposure + bid_usd > max {
+                return Some(format!(
+                    "bid worth ${bid_usd:.2} would take exposure to {} from ${exposure:.2} past the ${max:.2} limit",
+                    bid.fee_token.symbol()
+                ));
+            }
+        }
+
+        None
+    }
+
+    /// Records what a bid for `fee_token` spent and received, valuing the spend at the SOMM price
+    /// the bid was evaluated at
+    pub fn record(&mut self, fee_token: Denom, fill: &Fill, somm_usd_price: Option<f64>, now: u64) {
+        if fill.usomm_spent > 0 {
+            let usd = fill.usomm_spent as f64 / USOMM_PER_SOMM * somm_usd_price.unwrap_or_default();
+            self.spends.push_back((now, usd));
+        }
+        *self.holdings.entry(fee_token).or_default() += fill.tokens_received;
+        self.expire(now);
+    }
+
+    /// Compares the prices a bid was evaluated at against the reference source's, stopping all
+    /// bidding if any differ by more than the configured percentage. Returns why bidding was
+    /// stopped, if it was.
+    pub fn check_prices(
+        &mut self,
+        prices: &HashMap<Denom, f64>,
+        reference: &HashMap<Denom, f64>,
+    ) -> Option<String> {
+        let max = self.config.max_price_divergence_pct?;
+        for (denom, price) in prices {
+            let reference = match reference.get(denom) {
+                Some(r) if *r > 0.0 => *r,
+                _ => continue,
+            };
+            let divergence = (price - reference).abs() / reference * 100.0;
+            if divergence > max {
+                let reason = format!(
+                    "{} price feeds disagree by {divergence:.2}% (${price} against ${reference}), limit is {max}%",
+                    denom.symbol()
+                );
+                self.killed = Some(reason.clone());
+
+                return Some(reason);
+            }
+        }
+
+        None
+    }
+
+    fn expire(&mut self, now: u64) {
+        while matches!(self.spends.front(), Some((t, _)) if now.saturating_sub(*t) >= DAY_SECS) {
+            self.spends.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bid(maximum_usomm_in: u128) -> Bid {
+        Bid {
+            auction_id: 1,
+            fee_token: Denom::USDC,
+            maximum_usomm_in,
+            minimum_tokens_out: 1,
+        }
+    }
+
+    fn fill(usomm_spent: u128, tokens_received: u128) -> Fill {
+        Fill {
+            order_id: 0,
+            auction_id: 1,
+            usomm_spent,
+            tokens_received,
+            failed: false,
+        }
+    }
+
+    #[test]
+    fn test_risk_limits() {
+        let mut risk = RiskManager::new(RiskConfig {
+            max_bid_usomm: Some(200_000_000),
+            max_daily_spend_usd: Some(50.0),
+            ..Default::default()
+        });
+        assert!(risk
+            .check(&bid(300_000_000), Some(1.0), Some(0.1), 0)
+            .is_some());
+        assert!(risk.check(&bid(100_000_000), Some(1.0), None, 0).is_some());
+
+        // $40 spent, so another $20 is over the daily limit until the spend is a day old
+        risk.record(Denom::USDC, &fill(200_000_000, 40_000_000), Some(0.2), 0);
+        assert!(risk.check(&bid(100_000_000), None, Some(0.2), 10).is_some());
+        assert!(risk
+            .check(&bid(100_000_000), None, Some(0.2), DAY_SECS)
+            .is_none());
+
+        let mut risk = RiskManager::new(RiskConfig {
+            max_exposure_usd_per_denom: Some(30.0),
+            ..Default::default()
+        });
+        assert!(risk.check(&bid(100_000_000), None, Some(0.2), 0).is_some());
+
+        // holding 20 USDC, another $20 bid is over the limit until USDC's price halves
+        risk.record(Denom::USDC, &fill(100_000_000, 20_000_000), Some(0.2), 0);
+        assert!(risk
+            .check(&bid(100_000_000), Some(1.0), Some(0.2), 10)
+            .is_some());
+        assert!(risk
+            .check(&bid(100_000_000), Some(0.5), Some(0.2), 10)
+            .is_none());
+    }
+
+    #[test]
+    fn test_price_divergence_kill() {
+        let mut risk = RiskManager::new(RiskConfig {
+            max_price_divergence_pct: Some(5.0),
+            reference_price_source: Some(PriceSourceKind::Sommelier),
+            ..Default::default()
+        });
+        assert_eq!(
+            risk.reference_price_source().unwrap(),
+            Some(PriceSourceKind::Sommelier)
+        );
+
+        let prices = HashMap::from([(Denom::USDC, 1.0)]);
+        assert!(risk
+            .check_prices(&prices, &HashMap::from([(Denom::USDC, 0.97)]))
+            .is_none());
+        assert!(risk.check(&bid(1), None, None, 0).is_none());
+
+        assert!(risk
+            .check_prices(&prices, &HashMap::from([(Denom::USDC, 0.9)]))
+            .is_some());
+        assert!(risk.killed().is_some());
+        assert!(risk.check(&bid(1), None, None, 0).is_some());
+    }
+}
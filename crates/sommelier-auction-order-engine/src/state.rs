@@ -0,0 +1,40 @@
+use std::path::Path;
+
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::order::Order;
+
+/// Orders saved on shutdown so a restarted engine resumes with what they have left to spend
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderState {
+    /// Unix time the state was saved
+    pub saved_at: u64,
+    pub orders: Vec<Order>,
+}
+
+/// Loads saved order state, if the file exists
+pub fn load(path: &str) -> Result<Option<OrderState>> {
+    if !Path::new(path).exists() {
+        return Ok(None);
+    }
+
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| eyre!("failed to read order state from {path}: {err}"))?;
+    let state = serde_json::from_str(&contents)
+        .map_err(|err| eyre!("failed to parse order state from {path}: {err}"))?;
+
+    Ok(Some(state))
+}
+
+/// Saves order state, writing to a temporary file first so a crash mid-write doesn't corrupt the
+/// previous state
+pub fn save(path: &str, state: &OrderState) -> Result<()> {
+    let tmp_path = format!("{path}.tmp");
+    std::fs::write(&tmp_path, serde_json::to_string_pretty(state)?)
+        .map_err(|err| eyre!("failed to write order state to {tmp_path}: {err}"))?;
+    std::fs::rename(&tmp_path, path)
+        .map_err(|err| eyre!("failed to move order state into {path}: {err}"))?;
+
+    Ok(())
+}
@@ -0,0 +1,344 @@
+//! User-provided order strategies compiled to WebAssembly.
+//!
+//! A strategy module decides whether an order bids on an auction in place of the engine's limit
+//! price and minimum value checks. It exports `memory`, `alloc(len: i32) -> i32` and
+//! `evaluate(ptr: i32, len: i32) -> i64`. The engine allocates room for a JSON
+//! [`StrategyInput`], writes it, and calls `evaluate`, which returns a JSON [`StrategyOutput`]
+//! packed as `ptr << 32 | len`. Modules may import `env.log(ptr: i32, len: i32)` to log a UTF-8
+//! message. Each evaluation runs in a fresh instance with limited fuel, so modules can't keep
+//! state between calls or run forever.
+
+use std::collections::HashMap;
+
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use sommelier_auction::{auction::Auction, bid::Bid};
+
+use crate::{
+    decision::{Decision, Verdict},
+    evaluate,
+    order::Order,
+};
+
+/// Enough for a strategy to do a fair amount of arithmetic on each evaluation
+pub const DEFAULT_STRATEGY_FUEL: u64 = 10_000_000;
+
+/// A strategy module orders can name
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StrategyConfig {
+    /// Name orders refer to the strategy by
+    pub name: String,
+    /// Path to the compiled `.wasm` module
+    pub path: String,
+    /// Optional limit on the instructions one evaluation may run. Defaults to 10,000,000.
+    pub fuel: Option<u64>,
+}
+
+/// What a strategy is asked to evaluate. Token and usomm amounts are integer strings, since they
+/// may not fit in a JSON number.
+#[derive(Debug, Clone, Serialize)]
+pub struct StrategyInput<'a> {
+    pub order: &'a Order,
+    pub auction_id: u32,
+    /// The auction's price in usomm per base unit of the token, as a decimal string
+    pub unit_price_in_usomm: String,
+    pub remaining_tokens_for_sale: String,
+    /// The most the order may offer in this bid, in usomm
+    pub max_usomm_in: String,
+    /// USD price of one whole token
+    pub usd_price: f64,
+    /// USD price of one SOMM, if known
+    pub somm_usd_price: Option<f64>,
+    pub height: Option<u64>,
+    /// Unix time in seconds
+    pub now: u64,
+}
+
+/// A strategy's verdict. No bid means the order passes on the auction.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+pub struct StrategyOutput {
+    pub bid: Option<StrategyBid>,
+    /// Optional explanation, recorded in the order's decision
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+pub struct StrategyBid {
+    pub maximum_usomm_in: String,
+    pub minimum_tokens_out: String,
+}
+
+/// The strategy modules loaded at startup, by name
+#[derive(Clone, Default)]
+pub struct Strategies {
+    modules: HashMap<String, Module>,
+}
+
+impl Strategies {
+    /// Compiles each configured strategy module. Fails if any of them can't be loaded.
+    pub fn load(configs: &[StrategyConfig]) -> Result<Self> {
+        let mut modules = HashMap::new();
+        for config in configs {
+            modules.insert(config.name.clone(), Module::load(config)?);
+        }
+
+        Ok(Self { modules })
+    }
+
+    /// Decides whether `order` bids on `auction` with the strategy it names, like
+    /// [`evaluate::evaluate`]. The strategy's bid is checked against the order's bid size and the
+    /// auction's remaining supply before it's accepted.
+    pub fn evaluate(
+        &self,
+        order: &Order,
+        auction: &Auction,
+        usd_price: f64,
+        somm_usd_price: Option<f64>,
+        height: Option<u64>,
+        now: u64,
+    ) -> (Result<Option<Bid>>, Decision) {
+        let mut decision = Decision::new(order, auction.id);
+        decision.usd_price = Some(usd_price);
+        decision.somm_usd_price = somm_usd_price;
+
+        let strategy = order.strategy.as_deref().unwrap_or_default();
+        let result = match self.modules.get(strategy) {
+            Some(module) => {
+                input(order, auction, usd_price, somm_usd_price, height, now).and_then(|input| {
+                    decision.auction_unit_price_in_usomm = Some(input.unit_price_in_usomm.clone());
+                    decision.remaining_tokens_for_sale =
+                        input.remaining_tokens_for_sale.parse().ok();
+                    let output = module.evaluate(&input)?;
+                    decision.reason = output.reason.clone();
+
+                    checked_bid(order, auction, &output)
+                })
+            }
+            None => Err(eyre!("no strategy named {strategy} is loaded")),
+        };
+        match &result {
+            Ok(Some(bid)) => {
+                decision.verdict = Verdict::Bid;
+                decision.usomm_offer = u64::try_from(bid.maximum_usomm_in).ok();
+                decision.minimum_tokens_out = Some(bid.minimum_tokens_out);
+            }
+            Ok(None) => decision.verdict = Verdict::Pass,
+            Err(err) => {
+                decision.verdict = Verdict::Error;
+                decision.reason = Some(err.to_string());
+            }
+        }
+
+        (result, decision)
+    }
+}
+
+/// The inputs for evaluating `order` on `auction` with a strategy
+pub fn input<'a>(
+    order: &'a Order,
+    auction: &Auction,
+    usd_price: f64,
+    somm_usd_price: Option<f64>,
+    height: Option<u64>,
+    now: u64,
+) -> Result<StrategyInput<'a>> {
+    Ok(StrategyInput {
+        order,
+        auction_id: auction.id,
+        unit_price_in_usomm: evaluate::unit_price_in_usomm(auction)?.to_string(),
+        remaining_tokens_for_sale: evaluate::remaining_tokens_for_sale(auction)?.to_string(),
+        max_usomm_in: order.bid_size().to_string(),
+        usd_price,
+        somm_usd_price,
+        height,
+        now,
+    })
+}
+
+/// Turns a strategy's output into a bid, rejecting one that offers nothing, more than the order's
+/// bid size, or asks for more tokens than the auction has left
+pub fn checked_bid(
+    order: &Order,
+    auction: &Auction,
+    output: &StrategyOutput,
+) -> Result<Option<Bid>> {
+    let bid = match &output.bid {
+        Some(bid) => bid,
+        None => return Ok(None),
+    };
+    let maximum_usomm_in = evaluate::parse_amount(&bid.maximum_usomm_in)?;
+    let minimum_tokens_out = evaluate::parse_amount(&bid.minimum_tokens_out)?;
+    if maximum_usomm_in == 0 {
+        return Err(eyre!("strategy bid offers no usomm"));
+    }
+    if maximum_usomm_in > order.bid_size() as u128 {
+        return Err(eyre!(
+            "strategy bid of {maximum_usomm_in} usomm is more than the order's bid size of {}",
+            order.bid_size()
+        ));
+    }
+    let remaining_tokens_for_sale = evaluate::remaining_tokens_for_sale(auction)?;
+    if minimum_tokens_out > remaining_tokens_for_sale {
+        return Err(eyre!(
+            "strategy bid asks for {minimum_tokens_out} tokens but only {remaining_tokens_for_sale} remain"
+        ));
+    }
+
+    Ok(Some(Bid {
+        auction_id: auction.id,
+        fee_token: order.fee_token,
+        maximum_usomm_in,
+        minimum_tokens_out,
+    }))
+}
+
+#[cfg(feature = "wasm")]
+#[derive(Clone)]
+struct Module {
+    name: String,
+    fuel: u64,
+    module: wasmtime::Module,
+    linker: std::sync::Arc<wasmtime::Linker<()>>,
+}
+
+#[cfg(feature = "wasm")]
+impl Module {
+    fn load(config: &StrategyConfig) -> Result<Self> {
+        let mut wasm_config = wasmtime::Config::new();
+        wasm_config.consume_fuel(true);
+        let engine = wasmtime::Engine::new(&wasm_config)
+            .map_err(|err| eyre!("failed to create wasm engine: {err:#}"))?;
+        let module = wasmtime::Module::from_file(&engine, &config.path).map_err(|err| {
+            eyre!(
+                "failed to load strategy {} from {}: {err:#}",
+                config.name,
+                config.path
+            )
+        })?;
+
+        let mut linker = wasmtime::Linker::new(&engine);
+        let name = config.name.clone();
+        linker
+            .func_wrap(
+                "env",
+                "log",
+                move |mut caller: wasmtime::Caller<'_, ()>, ptr: i32, len: i32| {
+                    let memory = caller.get_export("memory").and_then(|e| e.into_memory());
+                    let mut message = vec![0; len.max(0) as usize];
+                    if let Some(memory) = memory {
+                        if memory.read(&caller, ptr as usize, &mut message).is_ok() {
+                            tracing::info!(
+                                strategy = name.as_str(),
+                                "{}",
+                                String::from_utf8_lossy(&message)
+                            );
+                        }
+                    }
+                },
+            )
+            .map_err(|err| eyre!("failed to define strategy host functions: {err:#}"))?;
+
+        Ok(Self {
+            name: config.name.clone(),
+            fuel: config.fuel.unwrap_or(DEFAULT_STRATEGY_FUEL),
+            module,
+            linker: std::sync::Arc::new(linker),
+        })
+    }
+
+    fn evaluate(&self, input: &StrategyInput) -> Result<StrategyOutput> {
+        let input = serde_json::to_vec(input)?;
+        let output = self
+            .call(&input)
+            .map_err(|err| eyre!("strategy {} failed: {err:#}", self.name))?;
+
+        serde_json::from_slice(&output)
+            .map_err(|err| eyre!("strategy {} returned invalid output: {err}", self.name))
+    }
+
+    fn call(&self, input: &[u8]) -> wasmtime::Result<Vec<u8>> {
+        let mut store = wasmtime::Store::new(self.module.engine(), ());
+        store.set_fuel(self.fuel)?;
+        let instance = self.linker.instantiate(&mut store, &self.module)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| wasmtime::Error::msg("module doesn't export its memory"))?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+        let evaluate = instance.get_typed_func::<(i32, i32), i64>(&mut store, "evaluate")?;
+
+        let len = i32::try_from(input.len())?;
+        let ptr = alloc.call(&mut store, len)?;
+        memory.write(&mut store, ptr as u32 as usize, input)?;
+        let packed = evaluate.call(&mut store, (ptr, len))? as u64;
+        let mut output = vec![0; (packed & 0xffff_ffff) as usize];
+        memory.read(&store, (packed >> 32) as usize, &mut output)?;
+
+        Ok(output)
+    }
+}
+
+#[cfg(not(feature = "wasm"))]
+#[derive(Clone)]
+struct Module;
+
+#[cfg(not(feature = "wasm"))]
+impl Module {
+    fn load(_config: &StrategyConfig) -> Result<Self> {
+        Err(eyre!(
+            "strategies need the engine to be built with the wasm feature"
+        ))
+    }
+
+    fn evaluate(&self, _input: &StrategyInput) -> Result<StrategyOutput> {
+        Err(eyre!(
+            "strategies need the engine to be built with the wasm feature"
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sommelier_auction::{cosmos_sdk_proto::cosmos::base::v1beta1::Coin, denom::Denom};
+
+    use super::*;
+
+    fn output(maximum_usomm_in: &str, minimum_tokens_out: &str) -> StrategyOutput {
+        StrategyOutput {
+            bid: Some(StrategyBid {
+                maximum_usomm_in: maximum_usomm_in.to_string(),
+                minimum_tokens_out: minimum_tokens_out.to_string(),
+            }),
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn test_checked_bid() {
+        let order = Order {
+            fee_token: Denom::USDC,
+            maximum_usomm_in: 1_000,
+            ..Default::default()
+        };
+        let auction = Auction {
+            id: 7,
+            remaining_tokens_for_sale: Some(Coin {
+                denom: Denom::USDC.to_string(),
+                amount: "500".to_string(),
+            }),
+            ..Default::default()
+        };
+
+        let bid = checked_bid(&order, &auction, &output("800", "400"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(bid.auction_id, 7);
+        assert_eq!(bid.maximum_usomm_in, 800);
+        assert_eq!(bid.minimum_tokens_out, 400);
+        assert!(checked_bid(&order, &auction, &StrategyOutput::default())
+            .unwrap()
+            .is_none());
+        assert!(checked_bid(&order, &auction, &output("0", "400")).is_err());
+        assert!(checked_bid(&order, &auction, &output("1001", "400")).is_err());
+        assert!(checked_bid(&order, &auction, &output("800", "501")).is_err());
+    }
+}
@@ -0,0 +1,359 @@
+use std::{collections::HashMap, time::Duration};
+
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use sommelier_auction::{
+    bid::Bid,
+    client::{Client, BID_GAS_LIMIT},
+    denom::Denom,
+    fee::{self, GasPrice, TxFee},
+    parameters::AuctionParameters,
+    BidResult,
+};
+use tracing::{info, warn};
+
+use crate::{evaluate, wallet::Wallet};
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+// roughly two blocks, long enough for an unconfirmed broadcast to land if it's going to
+const TIMEOUT_SETTLE: Duration = Duration::from_secs(12);
+
+/// How bid transaction fees are chosen
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TxFeeConfig {
+    /// Optional gas prices to pay fees at, in order of preference, e.g. `["0.025usomm"]`. Defaults
+    /// to the node's minimum gas prices.
+    pub gas_prices: Option<Vec<String>>,
+}
+
+/// How a failed bid submission should be handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmitErrorKind {
+    /// Safe to retry, e.g. an account sequence mismatch or a full mempool. Each attempt re-signs
+    /// the transaction, which re-queries the account sequence.
+    Transient,
+    /// The broadcast timed out. The bid may still land on chain, so it must be checked for before
+    /// retrying.
+    Timeout,
+    /// The connection to the node failed. The transaction may have reached the node first, so as
+    /// with a timeout the bid must be checked for before retrying.
+    Disconnected,
+    /// Retrying won't help, e.g. insufficient funds or the auction has ended. Nothing was spent.
+    Permanent,
+    /// An error we don't recognize. It isn't retried, and the bid may or may not have landed.
+    Unknown,
+}
+
+/// Classifies a bid submission error by its message
+pub fn classify(err: &eyre::Report) -> SubmitErrorKind {
+    let message = format!("{err:?}").to_lowercase();
+    let matches = |patterns: &[&str]| patterns.iter().any(|p| message.contains(p));
+
+    if matches(&[
+        "insufficient funds",
+        "insufficient fee",
+        "no active auction found",
+        "auction not found",
+        "unauthorized",
+    ]) {
+        SubmitErrorKind::Permanent
+    } else if matches(&["timed out", "timeout", "deadline"]) {
+        SubmitErrorKind::Timeout
+    } else if matches(&["connection", "transport error", "unavailable"]) {
+        SubmitErrorKind::Disconnected
+    } else if matches(&[
+        "account sequence mismatch",
+        "incorrect account sequence",
+        "mempool is full",
+        "tx already exists in cache",
+    ]) {
+        SubmitErrorKind::Transient
+    } else {
+        SubmitErrorKind::Unknown
+    }
+}
+
+impl SubmitErrorKind {
+    /// Whether the bid may be submitted again
+    pub fn is_retryable(self) -> bool {
+        matches!(self, Self::Transient | Self::Timeout | Self::Disconnected)
+    }
+
+    /// Whether the bid may have landed on chain despite the error, so it must be checked for
+    /// before retrying
+    pub fn may_have_landed(self) -> bool {
+        matches!(self, Self::Timeout | Self::Disconnected)
+    }
+}
+
+/// Checks a bid against current chain state before it is signed and broadcast, so bids that would
+/// fail on chain are caught without paying for them. Returns the reason the bid should be skipped,
+/// if any. An error means the checks themselves couldn't be run.
+pub async fn preflight(
+    client: &mut Client,
+    bid: &Bid,
+    parameters: Option<&AuctionParameters>,
+) -> Result<Option<String>> {
+    if let Some(parameters) = parameters {
+        if bid.maximum_usomm_in < parameters.minimum_bid_in_usomm as u128 {
+            return Ok(Some(format!(
+                "bid of {} usomm is below the minimum bid of {} usomm",
+                bid.maximum_usomm_in, parameters.minimum_bid_in_usomm
+            )));
+        }
+    }
+
+    let auction = match client.active_auction(bid.auction_id).await? {
+        Some(auction) => auction,
+        None => {
+            return Ok(Some(format!(
+                "auction {} is no longer active",
+                bid.auction_id
+            )))
+        }
+    };
+
+    let remaining_tokens_for_sale = evaluate::remaining_tokens_for_sale(&auction)?;
+    if bid.minimum_tokens_out > remaining_tokens_for_sale {
+        return Ok(Some(format!(
+            "bid asks for at least {} tokens but only {} remain in auction {}",
+            bid.minimum_tokens_out, remaining_tokens_for_sale, bid.auction_id
+        )));
+    }
+
+    Ok(None)
+}
+
+/// The gas prices bid fees are paid at: the configured ones if set, otherwise the node's minimum
+/// gas prices
+pub async fn gas_prices(client: &mut Client, config: &TxFeeConfig) -> Result<Vec<GasPrice>> {
+    match &config.gas_prices {
+        Some(prices) => prices.iter().map(|p| p.parse()).collect(),
+        None => client.minimum_gas_prices().await,
+    }
+}
+
+/// Chooses the fee for a wallet's bid at the first of `gas_prices` the signing account can pay,
+/// leaving enough usomm for the bid if it's also the bidder. None if it can't pay any of them.
+pub async fn select_fee(
+    client: &mut Client,
+    wallet: &Wallet,
+    bid: &Bid,
+    gas_prices: &[GasPrice],
+) -> Result<Option<TxFee>> {
+    let signer = wallet.signer.address()?;
+    let mut balances = HashMap::new();
+    for price in gas_prices {
+        let balance = client.denom_balance(&signer, &price.denom).await?;
+        balances.insert(price.denom.as_str(), balance);
+    }
+    let usomm = Denom::USOMM.to_string();
+    let reserved = |denom: &str| match wallet.granter {
+        None if denom == usomm => bid.maximum_usomm_in,
+        _ => 0,
+    };
+
+    Ok(fee::select_fee(
+        gas_prices,
+        BID_GAS_LIMIT,
+        |denom| balances.get(denom).copied().unwrap_or_default(),
+        reserved,
+    ))
+}
+
+/// Checks that a wallet can pay for a bid: the account bidding holds the bid's usomm, and the
+/// account signing holds the transaction fee. For a wallet bidding through authz these are its
+/// granter and itself. Returns the reason the bid should be skipped, if any.
+pub async fn check_funds(
+    client: &mut Client,
+    wallet: &Wallet,
+    bid: &Bid,
+    fee: &TxFee,
+) -> Result<Option<String>> {
+    let usomm = Denom::USOMM.to_string();
+    let mut required = vec![(wallet.bidder()?, usomm, bid.maximum_usomm_in)];
+    let signer = wallet.signer.address()?;
+    if wallet.granter.is_none() && fee.denom == required[0].1 {
+        required[0].2 += fee.amount;
+    } else {
+        required.push((signer, fee.denom.clone(), fee.amount));
+    }
+
+    for (address, denom, amount) in required {
+        if amount == 0 {
+            continue;
+        }
+
+        let balance = client.denom_balance(&address, &denom).await?;
+        if balance < amount {
+            return Ok(Some(format!(
+                "{address} holds {balance} {denom} but the bid needs {amount} {denom} including fees"
+            )));
+        }
+    }
+
+    Ok(None)
+}
+
+/// The highest ID of the bids already placed in an auction. Bid IDs only increase, so a bid found
+/// after submitting with a higher ID can't be an identical one placed earlier, e.g. by a DCA or
+/// split order bidding the same amounts again.
+pub async fn last_bid_id(client: &mut Client, auction_id: u32) -> Result<u64> {
+    Ok(client
+        .auction_bids(auction_id)
+        .await?
+        .iter()
+        .map(|b| b.id)
+        .max()
+        .unwrap_or_default())
+}
+
+/// Submits a bid, retrying transient failures with exponential backoff up to `max_attempts` times.
+/// After a timeout or a dropped connection the auction's bids placed after `after_bid_id` are
+/// checked for one matching ours before trying again, so a bid that landed despite the error is
+/// not placed twice.
+pub async fn submit_bid_with_retry(
+    client: &mut Client,
+    wallet: &Wallet,
+    bid: &Bid,
+    after_bid_id: u64,
+    max_attempts: u32,
+) -> Result<BidResult> {
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        let err = match wallet.submit_bid(client, bid.clone()).await {
+            Ok(result) => return Ok(result),
+            Err(err) => err,
+        };
+
+        let kind = classify(&err);
+        if !kind.is_retryable() || attempt >= max_attempts {
+            return Err(err);
+        }
+
+        if kind.may_have_landed() {
+            info!("bid submission failed ({kind:?}), checking whether it landed on chain");
+            tokio::time::sleep(TIMEOUT_SETTLE).await;
+
+            match find_landed_bid(client, &wallet.bidder()?, bid, after_bid_id).await {
+                Ok(Some(result)) => {
+                    info!("bid landed despite the error: {result:?}");
+
+                    return Ok(result);
+                }
+                Ok(None) => {}
+                Err(find_err) => {
+                    warn!("failed to check for landed bid, not retrying: {find_err:?}");
+
+                    return Err(err);
+                }
+            }
+        }
+
+        warn!(
+            "bid submission attempt {attempt}/{max_attempts} failed ({kind:?}), retrying in {backoff:?}: {err:?}"
+        );
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+        attempt += 1;
+    }
+}
+
+/// Looks for a bid in the auction from `bidder` with the same offer and minimum out as `bid`,
+/// placed after the bid with ID `after_bid_id`
+pub async fn find_landed_bid(
+    client: &mut Client,
+    bidder: &str,
+    bid: &Bid,
+    after_bid_id: u64,
+) -> Result<Option<BidResult>> {
+    let bids = client.auction_bids(bid.auction_id).await?;
+
+    Ok(landed_bid(bids, bidder, bid, after_bid_id))
+}
+
+fn landed_bid(
+    bids: Vec<BidResult>,
+    bidder: &str,
+    bid: &Bid,
+    after_bid_id: u64,
+) -> Option<BidResult> {
+    let maximum_usomm_in = bid.maximum_usomm_in.to_string();
+    let minimum_tokens_out = bid.minimum_tokens_out.to_string();
+
+    bids.into_iter().find(|b| {
+        b.id > after_bid_id
+            && b.bidder == bidder
+            && matches!(&b.max_bid_in_usomm, Some(c) if c.amount == maximum_usomm_in)
+            && matches!(&b.sale_token_minimum_amount, Some(c) if c.amount == minimum_tokens_out)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use eyre::eyre;
+    use sommelier_auction::cosmos_sdk_proto::cosmos::base::v1beta1::Coin;
+
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let kind = |message: &str| classify(&eyre!(message.to_string()));
+
+        assert_eq!(
+            kind("account sequence mismatch, expected 5, got 4"),
+            SubmitErrorKind::Transient
+        );
+        assert_eq!(kind("insufficient funds"), SubmitErrorKind::Permanent);
+        assert_eq!(kind("request timed out"), SubmitErrorKind::Timeout);
+        assert_eq!(kind("something else"), SubmitErrorKind::Unknown);
+
+        // the broadcast may have reached the node before the connection dropped, so these are only
+        // retried once the bid is known not to have landed
+        for message in [
+            "transport error: connection reset by peer",
+            "status: Unavailable, message: \"error trying to connect\"",
+        ] {
+            let kind = kind(message);
+            assert_eq!(kind, SubmitErrorKind::Disconnected, "{message}");
+            assert!(kind.is_retryable());
+            assert!(kind.may_have_landed());
+        }
+        assert!(!SubmitErrorKind::Transient.may_have_landed());
+        assert!(!SubmitErrorKind::Unknown.is_retryable());
+    }
+
+    #[test]
+    fn test_landed_bid() {
+        let coin = |amount: &str| {
+            Some(Coin {
+                denom: String::new(),
+                amount: amount.to_string(),
+            })
+        };
+        let bid = Bid {
+            auction_id: 1,
+            fee_token: Denom::USDC,
+            maximum_usomm_in: 100,
+            minimum_tokens_out: 10,
+        };
+        let placed = |id| BidResult {
+            id,
+            auction_id: 1,
+            bidder: "somm1bidder".to_string(),
+            max_bid_in_usomm: coin("100"),
+            sale_token_minimum_amount: coin("10"),
+            ..Default::default()
+        };
+
+        // an identical bid placed before this one was submitted isn't mistaken for it
+        assert_eq!(landed_bid(vec![placed(5)], "somm1bidder", &bid, 5), None);
+        assert_eq!(
+            landed_bid(vec![placed(5), placed(8)], "somm1bidder", &bid, 5),
+            Some(placed(8))
+        );
+        assert_eq!(landed_bid(vec![placed(8)], "somm1other", &bid, 5), None);
+    }
+}
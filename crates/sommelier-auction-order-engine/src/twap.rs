@@ -0,0 +1,80 @@
+use std::collections::{HashMap, VecDeque};
+
+use sommelier_auction::denom::Denom;
+
+/// Recent USD prices of each denom, for valuing tokens at a time-weighted average price rather than
+/// the latest one
+#[derive(Debug, Default, Clone)]
+pub struct PriceHistory {
+    // (unix time fetched, price) of each denom, oldest first
+    samples: HashMap<Denom, VecDeque<(u64, f64)>>,
+}
+
+impl PriceHistory {
+    /// Records a denom's price as fetched at `at`, dropping samples older than needed for a window
+    /// of `retain_secs`. A sample no newer than the last one recorded is ignored.
+    pub fn record(&mut self, denom: Denom, price: f64, at: u64, retain_secs: u64) {
+        let samples = self.samples.entry(denom).or_default();
+        if samples.back().is_some_and(|(last, _)| *last >= at) {
+            return;
+        }
+        samples.push_back((at, price));
+
+        // the newest sample before the window is kept, since it's the price at the window's start
+        let cutoff = at.saturating_sub(retain_secs);
+        while samples.len() > 1 && samples[1].0 <= cutoff {
+            samples.pop_front();
+        }
+    }
+
+    /// The average USD price of a denom over the `window_secs` before `now`, each price weighted by
+    /// how long it held. With less history than the window, the average covers what there is. None
+    /// if the denom has never been priced.
+    pub fn twap(&self, denom: &Denom, window_secs: u64, now: u64) -> Option<f64> {
+        let samples = self.samples.get(denom)?;
+        let (_, latest) = samples.back()?;
+        let start = now.saturating_sub(window_secs);
+
+        let mut weighted = 0.0;
+        let mut duration = 0;
+        for (i, (at, price)) in samples.iter().enumerate() {
+            let until = samples.get(i + 1).map_or(now, |(next, _)| *next).min(now);
+            let from = (*at).max(start);
+            if until > from {
+                weighted += price * (until - from) as f64;
+                duration += until - from;
+            }
+        }
+
+        if duration == 0 {
+            return Some(*latest);
+        }
+
+        Some(weighted / duration as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_twap() {
+        let mut history = PriceHistory::default();
+        assert_eq!(history.twap(&Denom::WETH, 60, 1_000), None);
+
+        history.record(Denom::WETH, 2_000.0, 1_000, 120);
+        assert_eq!(history.twap(&Denom::WETH, 60, 1_000), Some(2_000.0));
+
+        // a spike for the last 10 seconds of a 60 second window
+        history.record(Denom::WETH, 2_600.0, 1_050, 120);
+        assert_eq!(history.twap(&Denom::WETH, 60, 1_060), Some(2_100.0));
+        assert_eq!(history.twap(&Denom::WETH, 10, 1_060), Some(2_600.0));
+
+        // stale samples are ignored, and ones before the retention window dropped
+        history.record(Denom::WETH, 1.0, 1_040, 120);
+        history.record(Denom::WETH, 2_300.0, 1_300, 120);
+        assert_eq!(history.samples[&Denom::WETH].len(), 2);
+        assert_eq!(history.twap(&Denom::WETH, 300, 1_300), Some(2_600.0));
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use sommelier_auction::denom::Denom;
 
 pub fn denom_to_coingecko_id(denom: Denom) -> String {
@@ -16,3 +18,16 @@ pub fn denom_to_coingecko_id(denom: Denom) -> String {
         Denom::GoerliWETH => "weth".to_string(),
     }
 }
+
+/// The current unix time in seconds
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Compares without returning early, so the time taken doesn't reveal how much of a guess matched
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
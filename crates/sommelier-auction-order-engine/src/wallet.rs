@@ -0,0 +1,268 @@
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "ledger")]
+use sommelier_auction::ledger::LedgerSigner;
+use sommelier_auction::{
+    bid::Bid,
+    client::Client,
+    signer::{LocalSigner, Signer},
+    AccountInfo, BidResult,
+};
+
+use crate::{
+    budget::{Budget, BudgetConfig},
+    keystore::{self, Keystore},
+    remote_signer::{RemoteSigner, RemoteSignerConfig},
+};
+
+/// Environment variable the default wallet's mnemonic is read from
+pub const DEFAULT_MNEMONIC_ENV: &str = "SOMMELIER_AUCTION_MNEMONIC";
+
+/// A signing account the engine can bid from
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WalletConfig {
+    /// Name orders use to route their bids to this wallet
+    pub name: String,
+    /// Path to the wallet's key. If unset, the mnemonic is read from `mnemonic_env`.
+    pub key_path: Option<String>,
+    /// Environment variable holding the wallet's 24 word mnemonic
+    pub mnemonic_env: Option<String>,
+    /// Optional Ledger key to sign with instead of a key file or mnemonic. Requires the `ledger`
+    /// feature.
+    pub ledger: Option<LedgerConfig>,
+    /// Optional OS keyring entry holding the wallet's mnemonic
+    pub keyring: Option<KeyringConfig>,
+    /// Optional encrypted keystore file holding the wallet's mnemonic
+    pub keystore: Option<KeystoreConfig>,
+    /// Optional remote service that signs for the wallet, so its key never reaches the engine
+    pub remote_signer: Option<RemoteSignerConfig>,
+    /// Optional account the wallet bids on behalf of through an authz grant. Bids are paid for by
+    /// and credited to the granter, so the wallet's key only needs the grant.
+    pub granter: Option<String>,
+    /// Optional limits on usomm spent from this wallet, enforced alongside the engine's budget
+    pub budget: Option<BudgetConfig>,
+}
+
+/// A key on a Ledger device running the Cosmos app, at the HD path m/44'/118'/account'/0/index
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerConfig {
+    /// Defaults to 0
+    pub account: Option<u32>,
+    /// Defaults to 0
+    pub index: Option<u32>,
+}
+
+/// An entry in the OS keyring (macOS Keychain, Windows Credential Manager or the Secret Service
+/// on Linux) whose secret is the wallet's mnemonic
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyringConfig {
+    pub service: String,
+    pub user: String,
+}
+
+/// A keystore file created with `order-engine --create-keystore`
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeystoreConfig {
+    pub path: String,
+    /// Environment variable holding the keystore's password. Defaults to
+    /// SOMMELIER_AUCTION_KEYSTORE_PASSWORD.
+    pub password_env: Option<String>,
+}
+
+/// A loaded signing account and its spending limits
+pub struct Wallet {
+    pub name: String,
+    pub signer: WalletSigner,
+    pub budget: Budget,
+    pub granter: Option<String>,
+    /// When the wallet's authz grant expires, once checked. None if it never does.
+    pub grant_expires_at: Option<u64>,
+}
+
+impl Wallet {
+    pub fn load(config: WalletConfig) -> Result<Self> {
+        let signer = load_wallet_signer(&config)
+            .map_err(|err| eyre!("failed to load wallet {}: {err}", config.name))?;
+
+        Ok(Self {
+            name: config.name,
+            signer,
+            budget: Budget::new(config.budget.unwrap_or_default()),
+            granter: config.granter,
+            grant_expires_at: None,
+        })
+    }
+
+    /// The account the wallet's bids are made from: its granter if it has one, otherwise its own
+    pub fn bidder(&self) -> Result<String> {
+        match &self.granter {
+            Some(granter) => Ok(granter.clone()),
+            None => self.signer.address(),
+        }
+    }
+
+    /// Signs and submits a bid, on behalf of the granter if the wallet has one
+    pub async fn submit_bid(&self, client: &mut Client, bid: Bid) -> Result<BidResult> {
+        self.signer
+            .submit_bid(client, self.granter.as_deref(), bid)
+            .await
+    }
+}
+
+/// Loads a signer from a key file, or failing that from a mnemonic in the environment
+pub fn load_signer(key_path: Option<&str>, mnemonic_env: Option<&str>) -> Result<AccountInfo> {
+    let mnemonic_env = mnemonic_env.unwrap_or(DEFAULT_MNEMONIC_ENV);
+    if let Some(key_path) = key_path {
+        AccountInfo::from_pem(key_path)
+            .map_err(|err| eyre!("failed to load key from {key_path}: {err:?}"))
+    } else if let Ok(mnemonic) = std::env::var(mnemonic_env) {
+        AccountInfo::from_mnemonic(&mnemonic, "")
+            .map_err(|err| eyre!("failed to construct signer from mnemonic: {err:?}"))
+    } else {
+        Err(eyre!("no signer key provided and no mnemonic found in environment. either provide a key_path in the config or set {mnemonic_env} in the environment to a 24 word phrase."))
+    }
+}
+
+// Loads the wallet's signer from the first backend configured, falling back to a key file or
+// mnemonic
+fn load_wallet_signer(config: &WalletConfig) -> Result<WalletSigner> {
+    if let Some(ledger) = &config.ledger {
+        return load_ledger(ledger);
+    }
+    if let Some(keyring) = &config.keyring {
+        let mnemonic = keyring::Entry::new(&keyring.service, &keyring.user)
+            .and_then(|entry| entry.get_password())
+            .map_err(|err| {
+                eyre!(
+                    "failed to read keyring entry {}/{}: {err}",
+                    keyring.service,
+                    keyring.user
+                )
+            })?;
+
+        return local_signer(&mnemonic);
+    }
+    if let Some(config) = &config.keystore {
+        let password_env = config
+            .password_env
+            .as_deref()
+            .unwrap_or(keystore::DEFAULT_PASSWORD_ENV);
+        let password = std::env::var(password_env)
+            .map_err(|_| eyre!("keystore password not found in {password_env}"))?;
+        let mnemonic = Keystore::load(&config.path)?.decrypt(&password)?;
+
+        return local_signer(&mnemonic);
+    }
+    if let Some(remote) = &config.remote_signer {
+        return Ok(WalletSigner::Signer(Box::new(RemoteSigner::new(
+            remote.clone(),
+        )?)));
+    }
+
+    load_signer(config.key_path.as_deref(), config.mnemonic_env.as_deref()).map(WalletSigner::Key)
+}
+
+fn local_signer(mnemonic: &str) -> Result<WalletSigner> {
+    Ok(WalletSigner::Signer(Box::new(LocalSigner::from_mnemonic(
+        mnemonic, "somm",
+    )?)))
+}
+
+#[cfg(feature = "ledger")]
+fn load_ledger(config: &LedgerConfig) -> Result<WalletSigner> {
+    let signer = LedgerSigner::connect(
+        "somm",
+        config.account.unwrap_or(0),
+        config.index.unwrap_or(0),
+    )?;
+
+    Ok(WalletSigner::Signer(Box::new(signer)))
+}
+
+#[cfg(not(feature = "ledger"))]
+fn load_ledger(_config: &LedgerConfig) -> Result<WalletSigner> {
+    Err(eyre!(
+        "ledger wallets need the engine to be built with the ledger feature"
+    ))
+}
+
+/// What signs a wallet's bids: a key ocular holds, or any other [`Signer`] backend
+pub enum WalletSigner {
+    Key(AccountInfo),
+    Signer(Box<dyn Signer>),
+}
+
+impl WalletSigner {
+    pub fn address(&self) -> Result<String> {
+        match self {
+            WalletSigner::Key(key) => key.address("somm"),
+            WalletSigner::Signer(signer) => Ok(signer.address().to_string()),
+        }
+    }
+
+    /// Signs and submits a bid, through authz if there's a granter. Ledger bids wait for the
+    /// signature to be approved on the device.
+    pub async fn submit_bid(
+        &self,
+        client: &mut Client,
+        granter: Option<&str>,
+        bid: Bid,
+    ) -> Result<BidResult> {
+        match (self, granter) {
+            (WalletSigner::Key(key), None) => client.submit_bid(key, bid).await,
+            (WalletSigner::Key(key), Some(granter)) => {
+                client.submit_bid_as_grantee(key, granter, bid).await
+            }
+            (WalletSigner::Signer(signer), None) => {
+                client.submit_bid_with_signer(signer.as_ref(), bid).await
+            }
+            (WalletSigner::Signer(signer), Some(granter)) => {
+                client
+                    .submit_bid_with_signer_as_grantee(signer.as_ref(), granter, bid)
+                    .await
+            }
+        }
+    }
+}
+
+/// The wallets bids are routed to
+pub struct Wallets {
+    wallets: Vec<Wallet>,
+    // round-robin position for orders that don't name a wallet
+    next: usize,
+}
+
+impl Wallets {
+    pub fn new(wallets: Vec<Wallet>) -> Self {
+        Self { wallets, next: 0 }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Wallet> {
+        self.wallets.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Wallet> {
+        self.wallets.iter_mut()
+    }
+
+    /// The wallet named `name`, or if no name is given the next wallet in round-robin order
+    pub fn select(&mut self, name: Option<&str>) -> Result<&mut Wallet> {
+        if self.wallets.is_empty() {
+            return Err(eyre!("no wallets configured"));
+        }
+
+        match name {
+            Some(name) => self
+                .wallets
+                .iter_mut()
+                .find(|w| w.name == name)
+                .ok_or_else(|| eyre!("no wallet named {name}")),
+            None => {
+                let index = self.next % self.wallets.len();
+                self.next = index + 1;
+
+                Ok(&mut self.wallets[index])
+            }
+        }
+    }
+}
@@ -1,12 +1,53 @@
-use std::collections::HashMap;
-use std::str::FromStr;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
 use eyre::{eyre, Result};
-use sommelier_auction::{auction::Auction, bid::Bid, client::Client, denom::Denom};
-use tokio::sync::mpsc::Sender;
+use rust_decimal::{prelude::FromPrimitive, Decimal};
+use sommelier_auction::{auction::Auction, denom::Denom};
+use tokio::sync::{
+    mpsc::{Sender, UnboundedReceiver},
+    watch,
+};
 use tracing::{debug, error, info, warn};
 
-use crate::{order::Order, util};
+use crate::{
+    admin::{AdminCommand, AuctionSummary, EngineState},
+    allocation::{self, AllocationStrategy},
+    backtest::SECONDS_PER_BLOCK,
+    competition::SupplyTracker,
+    decision::{Decision, DecisionLog, Verdict, DEFAULT_DECISION_LOG_SIZE},
+    endpoints::{EndpointPool, DEFAULT_CALL_TIMEOUT_SECS},
+    evaluate,
+    filter::DenomFilter,
+    health::{self, Health},
+    metrics::Metrics,
+    notify::{Event, Notifications},
+    order::{Fill, Order, OrderBid},
+    pnl::{self, Pnl},
+    price_updater::PriceFeed,
+    projection::{self, DEFAULT_MAX_PROJECTED_WAIT_SECS},
+    strategy::Strategies,
+    twap::PriceHistory,
+    util::unix_now,
+};
+
+/// How long the watcher waits for the price updater's first prices before evaluating orders
+const FIRST_PRICES_TIMEOUT: Duration = Duration::from_secs(30);
+
+// How far a split order has got through bidding its parts on an auction
+#[derive(Debug, Default, Clone)]
+struct SplitProgress {
+    auction_id: u32,
+    part_size: u64,
+    parts_bid: u32,
+    // block height the last part filled at, if it was known
+    last_height: Option<u64>,
+    // whether the bid in flight has counted as a part. A grouped order's bid fills once per member
+    // but is only one part.
+    part_counted: bool,
+}
 
 // This is a temporary type to house the auction monitoring function so we can
 // spawn a thread to run it. In the future we should think about a generalized
@@ -15,203 +56,1094 @@ use crate::{order::Order, util};
 // and relay bids sent over the channel to a bidder service.
 pub struct Watcher {
     active_auctions: Vec<Auction>,
-    client: Option<Client>,
-    grpc_endpoint: String,
+    // the grpc endpoints queried, failing over between them
+    endpoints: EndpointPool,
     orders: HashMap<Denom, Vec<Order>>,
     prices: HashMap<Denom, f64>,
+    refresh_interval: Duration,
+    // prices refreshed by the engine's price updater
+    price_feed: PriceFeed,
+    fills: UnboundedReceiver<Fill>,
+    // how many bids each order has in flight. orders aren't evaluated again until every fill comes
+    // back.
+    pending: HashMap<usize, usize>,
+    // (auction id, unix time) of each DCA order's last filled bid
+    last_dca_bids: HashMap<usize, (u32, u64)>,
+    // unix time each denom's price was last refreshed
+    prices_updated_at: HashMap<Denom, u64>,
+    // the first USD price of SOMM seen, for stop conditions on SOMM price moves
+    somm_reference_price: Option<f64>,
+    consecutive_failures: HashMap<usize, u32>,
+    // commands from the admin API and the config file watcher, if either is enabled
+    commands: Option<UnboundedReceiver<AdminCommand>>,
+    // state published for the admin API, if it's enabled
+    state: Option<Arc<RwLock<EngineState>>>,
+    paused: bool,
+    next_order_id: usize,
+    last_evaluation: Option<u64>,
+    // the most recent order evaluations, for answering why an order did or didn't bid
+    decisions: DecisionLog,
+    allocation: AllocationStrategy,
+    // liveness reported by the health endpoint
+    health: Arc<RwLock<Health>>,
+    // set when the engine is shutting down
+    shutdown: Option<watch::Receiver<bool>>,
+    metrics: Arc<Metrics>,
+    notifications: Notifications,
+    // latest block height, refreshed each loop if any order needs it
+    height: Option<u64>,
+    // growth of the auction price decrease rate each interval, for projecting prices
+    acceleration_rate: Decimal,
+    // longest the watcher sleeps until an order is projected to qualify
+    max_projected_wait: Duration,
+    denom_filter: DenomFilter,
+    // how fast other bidders are buying each auction's supply, for orders that compete
+    supply: SupplyTracker,
+    // fills tracked by the engine, valued at the prices the watcher refreshes
+    pnl: Option<Arc<RwLock<Pnl>>>,
+    // active auctions with a denom the engine doesn't know, and denoms whose orders are ignored for
+    // lack of a USD price, so each is only warned about when it starts
+    unknown_denom_auctions: HashSet<u32>,
+    unpriced_denoms: HashSet<Denom>,
+    // modules that decide the bids of orders naming a strategy
+    strategies: Strategies,
+    // recent prices, for orders valued at a time-weighted average
+    price_history: PriceHistory,
+    // parts bid by each split order on its current auction
+    splits: HashMap<usize, SplitProgress>,
 }
 
 impl Watcher {
-    pub fn new(orders: HashMap<Denom, Vec<Order>>, grpc_endpoint: String) -> Self {
+    pub fn new(
+        orders: HashMap<Denom, Vec<Order>>,
+        rpc_endpoint: String,
+        grpc_endpoint: String,
+        refresh_interval: Duration,
+        price_feed: PriceFeed,
+        fills: UnboundedReceiver<Fill>,
+    ) -> Self {
+        let next_order_id = orders
+            .values()
+            .flatten()
+            .map(|o| o.id + 1)
+            .max()
+            .unwrap_or_default();
+
         Self {
             active_auctions: Vec::new(),
-            client: None,
-            grpc_endpoint,
+            endpoints: EndpointPool::new(
+                rpc_endpoint,
+                vec![grpc_endpoint],
+                Duration::from_secs(DEFAULT_CALL_TIMEOUT_SECS),
+            ),
             orders,
             prices: HashMap::new(),
+            refresh_interval,
+            price_feed,
+            fills,
+            pending: HashMap::new(),
+            last_dca_bids: HashMap::new(),
+            prices_updated_at: HashMap::new(),
+            somm_reference_price: None,
+            consecutive_failures: HashMap::new(),
+            commands: None,
+            state: None,
+            paused: false,
+            next_order_id,
+            last_evaluation: None,
+            decisions: DecisionLog::new(DEFAULT_DECISION_LOG_SIZE),
+            allocation: AllocationStrategy::default(),
+            health: Arc::new(RwLock::new(Health::new(unix_now()))),
+            shutdown: None,
+            metrics: Arc::new(Metrics::default()),
+            notifications: Notifications::default(),
+            height: None,
+            acceleration_rate: Decimal::ZERO,
+            max_projected_wait: Duration::from_secs(DEFAULT_MAX_PROJECTED_WAIT_SECS),
+            denom_filter: DenomFilter::default(),
+            supply: SupplyTracker::default(),
+            pnl: None,
+            unknown_denom_auctions: HashSet::new(),
+            unpriced_denoms: HashSet::new(),
+            strategies: Strategies::default(),
+            price_history: PriceHistory::default(),
+            splits: HashMap::new(),
         }
     }
 
-    // This will probably hit the per-minute query rate limit, so we just move on if we fail to get
-    // a price.
-    async fn refresh_prices(
-        &mut self,
-        coingecko_ids_reverse_lookup: HashMap<String, Denom>,
-    ) -> Result<()> {
-        debug!("refreshing prices");
+    /// Accepts commands from the admin API or the config file watcher and publishes state for the
+    /// admin API to read. With either enabled the watcher keeps running when it runs out of orders,
+    /// since more can be added.
+    pub fn with_admin(
+        mut self,
+        commands: UnboundedReceiver<AdminCommand>,
+        state: Arc<RwLock<EngineState>>,
+    ) -> Self {
+        self.commands = Some(commands);
+        self.state = Some(state);
 
-        let coingecko_ids = coingecko_ids_reverse_lookup
-            .keys()
-            .cloned()
-            .collect::<Vec<String>>();
-        match price_feed::get_usd_price_for_assets(None, coingecko_ids.clone()).await {
-            Ok(prices) => {
-                self.prices = prices
-                    .into_iter()
-                    .map(|(cid, p)| (coingecko_ids_reverse_lookup.get(&cid).unwrap().clone(), p))
-                    .collect()
+        self
+    }
+
+    /// Queries the endpoints in `endpoints` in place of the single gRPC endpoint it was created with
+    pub fn with_endpoints(mut self, endpoints: EndpointPool) -> Self {
+        self.endpoints = endpoints;
+
+        self
+    }
+
+    /// Sets how many of the most recent order decisions are kept for the admin API
+    pub fn with_decision_log_size(mut self, size: usize) -> Self {
+        self.decisions = DecisionLog::new(size);
+
+        self
+    }
+
+    /// Sets how auction supply is shared between orders that qualify for the same auction
+    pub fn with_allocation(mut self, allocation: AllocationStrategy) -> Self {
+        self.allocation = allocation;
+
+        self
+    }
+
+    /// Shares the watcher's liveness with the health endpoint
+    pub fn with_health(mut self, health: Arc<RwLock<Health>>) -> Self {
+        self.health = health;
+
+        self
+    }
+
+    /// Stops the watcher when `true` is sent, or the sender is dropped
+    pub fn with_shutdown(mut self, shutdown: watch::Receiver<bool>) -> Self {
+        self.shutdown = Some(shutdown);
+
+        self
+    }
+
+    /// Reports order and auction counts to the engine's metrics
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = metrics;
+
+        self
+    }
+
+    /// Keeps the prices the engine's PnL is valued at up to date
+    pub fn with_pnl(mut self, pnl: Arc<RwLock<Pnl>>) -> Self {
+        self.pnl = Some(pnl);
+
+        self
+    }
+
+    /// Sends a notification when an order spends its whole budget
+    pub fn with_notifications(mut self, notifications: Notifications) -> Self {
+        self.notifications = notifications;
+
+        self
+    }
+
+    /// Sets the longest the watcher sleeps when every order is projected to qualify no sooner. A
+    /// wait no longer than the refresh interval disables projected wake-ups.
+    pub fn with_max_projected_wait(mut self, wait: Duration) -> Self {
+        self.max_projected_wait = wait;
+
+        self
+    }
+
+    /// Restricts the denoms orders may bid on
+    pub fn with_denom_filter(mut self, filter: DenomFilter) -> Self {
+        self.denom_filter = filter;
+
+        self
+    }
+
+    /// Sets the strategy modules orders can name to decide their bids
+    pub fn with_strategies(mut self, strategies: Strategies) -> Self {
+        self.strategies = strategies;
+
+        self
+    }
+
+    // The longest window any order averages prices over
+    fn twap_retention(&self) -> u64 {
+        self.orders
+            .values()
+            .flatten()
+            .filter_map(|o| o.twap_window_secs)
+            .max()
+            .unwrap_or_default()
+    }
+
+    fn projections_enabled(&self) -> bool {
+        self.max_projected_wait > self.refresh_interval
+    }
+
+    fn is_shutting_down(&self) -> bool {
+        self.shutdown
+            .as_ref()
+            .is_some_and(|s| *s.borrow() || s.has_changed().is_err())
+    }
+
+    /// Applies any fills still waiting and returns the remaining orders, for saving on shutdown
+    pub fn into_orders(mut self) -> Vec<Order> {
+        self.apply_fills();
+        let mut orders = self.orders.into_values().flatten().collect::<Vec<Order>>();
+        orders.sort_by_key(|o| o.id);
+
+        orders
+    }
+
+    fn handle_command(&mut self, command: AdminCommand) {
+        match command {
+            AdminCommand::UpsertOrder {
+                id,
+                mut order,
+                reply,
+            } => {
+                let existing = id.and_then(|id| self.remove_order(id));
+                if id.is_some() && existing.is_none() {
+                    let _ = reply.send(None);
+
+                    return;
+                }
+
+                order.id = match id {
+                    Some(id) => id,
+                    None => {
+                        self.next_order_id += 1;
+
+                        self.next_order_id - 1
+                    }
+                };
+                info!("admin api upserted order {}: {order:?}", order.id);
+                self.orders
+                    .entry(order.fee_token)
+                    .or_default()
+                    .push(order.clone());
+                let _ = reply.send(Some(order));
+            }
+            AdminCommand::CancelOrder { id, reply } => {
+                let removed = self.remove_order(id);
+                if let Some(order) = &removed {
+                    info!("admin api cancelled order {id}: {order:?}");
+                }
+                let _ = reply.send(removed.is_some());
+            }
+            AdminCommand::Pause => {
+                info!("admin api paused order evaluation");
+                self.paused = true;
             }
-            Err(err) => return Err(eyre!("failed to get prices for {coingecko_ids:?}: {err:?}")),
+            AdminCommand::Resume => {
+                info!("admin api resumed order evaluation");
+                self.paused = false;
+            }
+            AdminCommand::ReloadOrders { changes, reply } => {
+                for id in changes.removed {
+                    self.remove_order(id);
+                }
+                // an edited order keeps what it has already spent, and one that has finished
+                // only comes back if its budget was raised
+                for edit in changes.edited {
+                    let spent = match self.remove_order(edit.id) {
+                        Some(running) => edit
+                            .previous
+                            .maximum_usomm_in
+                            .saturating_sub(running.maximum_usomm_in),
+                        None => edit.previous.maximum_usomm_in,
+                    };
+                    let mut order = edit.order;
+                    order.id = edit.id;
+                    order.spend(spent);
+                    if order.maximum_usomm_in > 0 {
+                        self.orders.entry(order.fee_token).or_default().push(order);
+                    }
+                }
+                let mut ids = Vec::new();
+                for mut order in changes.added {
+                    order.id = self.next_order_id;
+                    self.next_order_id += 1;
+                    ids.push(order.id);
+                    self.orders.entry(order.fee_token).or_default().push(order);
+                }
+                info!("applied order changes from the config file");
+                let _ = reply.send(ids);
+            }
+        }
+
+        self.publish_state();
+    }
+
+    fn remove_order(&mut self, id: usize) -> Option<Order> {
+        for orders in self.orders.values_mut() {
+            if let Some(index) = orders.iter().position(|o| o.id == id) {
+                return Some(orders.remove(index));
+            }
+        }
+
+        None
+    }
+
+    // Copies the current state to where the admin API can read it
+    fn publish_state(&self) {
+        let state = match &self.state {
+            Some(state) => state,
+            None => return,
         };
 
-        debug!("price cache {:?}", self.prices);
-        Ok(())
+        let mut orders = self
+            .orders
+            .values()
+            .flatten()
+            .cloned()
+            .collect::<Vec<Order>>();
+        orders.sort_by_key(|o| o.id);
+        let mut pending_orders = self.pending.keys().cloned().collect::<Vec<usize>>();
+        pending_orders.sort();
+
+        let mut state = state
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *state = EngineState {
+            paused: self.paused,
+            orders,
+            pending_orders,
+            active_auctions: self
+                .active_auctions
+                .iter()
+                .map(AuctionSummary::from)
+                .collect(),
+            prices: self.prices.clone(),
+            last_evaluation: self.last_evaluation,
+            decisions: self.decisions.decisions().cloned().collect(),
+        };
     }
 
-    async fn refresh_active_auctions(&mut self) -> Result<()> {
-        debug!("refreshing active auctions");
-        let active_auctions = self.client.as_mut().unwrap().active_auctions().await?;
-        self.active_auctions = active_auctions;
+    // Waits for the given duration, handling admin commands as they arrive
+    async fn idle(&mut self, duration: Duration) {
+        let deadline = tokio::time::Instant::now() + duration;
+        loop {
+            let command = tokio::select! {
+                _ = tokio::time::sleep_until(deadline) => return,
+                _ = shutdown_requested(self.shutdown.as_mut()) => return,
+                command = next_command(self.commands.as_mut()) => command,
+            };
 
-        Ok(())
+            match command {
+                Some(command) => self.handle_command(command),
+                // the admin API went away, so there's nothing left to wait on but the timer
+                None => self.commands = None,
+            }
+        }
     }
 
-    pub async fn monitor_auctions(&mut self, tx: Sender<Bid>) -> Result<()> {
-        self.client =
-            Some(Client::with_endpoints("".to_string(), self.grpc_endpoint.clone()).await?);
-        let mut count = 0;
-        let coingecko_ids = self
+    // Denoms to query prices for. Limit orders in USD and stop conditions on SOMM price moves need
+    // the price of SOMM as well.
+    fn price_denoms(&self) -> Vec<Denom> {
+        let mut denoms = self
             .orders
             .keys()
-            .map(|d| (util::denom_to_coingecko_id(*d), *d))
-            .collect::<HashMap<String, Denom>>();
+            .filter(|d| self.denom_filter.permits(d))
+            .cloned()
+            .collect::<Vec<Denom>>();
+        if self.orders.values().flatten().any(|o| {
+            o.max_usd_per_token.is_some()
+                || matches!(&o.stop, Some(s) if s.max_somm_price_change_pct.is_some())
+        }) && !denoms.contains(&Denom::USOMM)
+        {
+            denoms.push(Denom::USOMM);
+        }
+
+        denoms
+    }
+
+    // Applies the outcome of submitted bids to the orders they were placed for. An order stays in
+    // state until its usomm budget is used up, so a partial fill leaves the remainder to bid on.
+    fn apply_fills(&mut self) {
+        while let Ok(fill) = self.fills.try_recv() {
+            // whether this is the last fill of the order's bid
+            let mut bid_complete = true;
+            if let Some(in_flight) = self.pending.get_mut(&fill.order_id) {
+                *in_flight -= 1;
+                if *in_flight == 0 {
+                    self.pending.remove(&fill.order_id);
+                } else {
+                    bid_complete = false;
+                }
+            }
+            if fill.usomm_spent > 0 {
+                self.last_dca_bids
+                    .insert(fill.order_id, (fill.auction_id, unix_now()));
+            }
+            if fill.tokens_received > 0 {
+                self.supply
+                    .record_fill(fill.auction_id, fill.tokens_received);
+            }
+            if fill.failed {
+                *self.consecutive_failures.entry(fill.order_id).or_default() += 1;
+            } else if fill.usomm_spent > 0 {
+                self.consecutive_failures.remove(&fill.order_id);
+            }
+
+            for orders in self.orders.values_mut() {
+                if let Some(order) = orders.iter_mut().find(|o| o.id == fill.order_id) {
+                    if let Some(split) = &order.split {
+                        let counted = self.splits.get(&order.id).is_some_and(|p| p.part_counted);
+                        if fill.usomm_spent > 0 && !counted {
+                            let progress = self.splits.entry(order.id).or_default();
+                            if progress.auction_id != fill.auction_id
+                                || progress.parts_bid >= split.parts
+                            {
+                                *progress = SplitProgress {
+                                    auction_id: fill.auction_id,
+                                    part_size: split.part_size(order.bid_size()),
+                                    ..Default::default()
+                                };
+                            }
+                            progress.parts_bid += 1;
+                            progress.last_height = self.height;
+                            progress.part_counted = true;
+                        }
+                        if bid_complete {
+                            if let Some(progress) = self.splits.get_mut(&order.id) {
+                                progress.part_counted = false;
+                            }
+                        }
+                    }
+                    order.spend(u64::try_from(fill.usomm_spent).unwrap_or(u64::MAX));
+                    info!(
+                        "order {} spent {} usomm for {} tokens, {} usomm remaining",
+                        order.id, fill.usomm_spent, fill.tokens_received, order.maximum_usomm_in
+                    );
+                }
+
+                orders.retain(|o| {
+                    if o.maximum_usomm_in == 0 {
+                        info!("removing exhausted order from state: {o:?}");
+                        self.notifications
+                            .send(Event::OrderExhausted { order_id: o.id });
+                    }
+
+                    o.maximum_usomm_in > 0
+                });
+            }
+        }
+    }
+
+    // Updates the block height used for height-based expiry, sniping, competition, splits and price
+    // projections. It's only queried if one of them is in use, and is None otherwise or if the
+    // query fails.
+    async fn refresh_height(&mut self) {
+        self.height = None;
+        if !self.projections_enabled()
+            && !self.orders.values().flatten().any(|o| {
+                o.expires_at_height.is_some()
+                    || o.snipe.is_some()
+                    || o.compete.is_some()
+                    || o.split.is_some()
+            })
+        {
+            return;
+        }
+
+        let height = self
+            .endpoints
+            .call(|client| async move { client.latest_block_height().await })
+            .await;
+        match height {
+            Ok(h) => {
+                self.height = Some(h);
+                self.metrics.service.chain_height.set(h);
+            }
+            Err(err) => warn!(
+                "failed to query block height, skipping height-based order expiry and sniping: {err:?}"
+            ),
+        }
+    }
+
+    // Drops orders that have passed their expiry time or height so stale orders don't fire when a
+    // new auction for the same denom starts
+    fn remove_expired_orders(&mut self) {
+        let now = unix_now();
+        let height = self.height;
+        for orders in self.orders.values_mut() {
+            orders.retain(|o| {
+                let expired = o.is_expired(now, height);
+                if expired {
+                    info!("removing expired order from state: {o:?}");
+                }
+
+                !expired
+            });
+        }
+    }
+
+    // Deactivates orders whose stop conditions are met
+    fn apply_stop_conditions(&mut self) {
+        let now = unix_now();
+        let somm_price_change_pct =
+            match (self.somm_reference_price, self.prices.get(&Denom::USOMM)) {
+                (Some(reference), Some(current)) if reference > 0.0 => {
+                    Some((current - reference) / reference * 100.0)
+                }
+                _ => None,
+            };
+
+        for (denom, orders) in self.orders.iter_mut() {
+            let price_age_secs = self
+                .prices_updated_at
+                .get(denom)
+                .map(|t| now.saturating_sub(*t));
+            orders.retain(|o| {
+                let stop = match &o.stop {
+                    Some(stop) => stop,
+                    None => return true,
+                };
+                let failures = self
+                    .consecutive_failures
+                    .get(&o.id)
+                    .cloned()
+                    .unwrap_or_default();
+                match stop.triggered(price_age_secs, somm_price_change_pct, failures) {
+                    Some(reason) => {
+                        warn!("deactivating order {} because {reason}: {o:?}", o.id);
+
+                        false
+                    }
+                    None => true,
+                }
+            });
+        }
+    }
+
+    pub async fn monitor_auctions(&mut self, tx: Sender<OrderBid>) -> Result<()> {
+        if self.projections_enabled() {
+            let parameters = self
+                .endpoints
+                .call(|mut client| async move { client.auction_parameters().await })
+                .await;
+            match parameters {
+                Ok(parameters) => {
+                    self.acceleration_rate =
+                        Decimal::from_f64(parameters.auction_price_decrease_acceleration_rate)
+                            .unwrap_or_default()
+                }
+                Err(err) => warn!(
+                    "failed to query auction parameters, projecting prices without acceleration: {err:?}"
+                ),
+            }
+        }
+        // the first evaluation would skip every order without prices
+        self.price_feed.request(self.price_denoms());
+        self.price_feed.wait_for_first(FIRST_PRICES_TIMEOUT).await;
         loop {
-            debug!("orders in state: {:?}", self.orders);
-            info!("monitoring auctions");
-            if self.orders.iter().all(|(_, v)| v.is_empty()) {
-                info!("no more orders! shutting down");
+            self.apply_fills();
+            if self.is_shutting_down() {
+                info!("shutting down watcher");
 
                 return Ok(());
             }
+            self.refresh_height().await;
+            self.remove_expired_orders();
+            self.publish_state();
 
-            if let Err(err) = self.refresh_active_auctions().await {
-                error!("failed to refresh active auctions: {err:?}");
-                warn!("retrying auction refresh in 5 seconds");
-                tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+            debug!("orders in state: {:?}", self.orders);
+            info!("monitoring auctions");
+            let no_orders = self.orders.iter().all(|(_, v)| v.is_empty());
+            health::update(&self.health, |h| {
+                h.last_loop = Some(unix_now());
+                h.idle = no_orders;
+            });
+            let order_count = self.orders.values().map(|v| v.len() as u64).sum();
+            self.metrics.orders.set(order_count);
+            if no_orders {
+                if self.commands.is_none() {
+                    info!("no more orders! shutting down");
+
+                    return Ok(());
+                }
+
+                debug!("no orders, waiting for the admin api to add some");
+                self.idle(self.refresh_interval).await;
 
                 continue;
             }
 
+            self.price_feed.request(self.price_denoms());
+            let active_auctions = self
+                .endpoints
+                .call(|mut client| async move { client.active_auctions().await })
+                .await;
+            match active_auctions {
+                Ok(active_auctions) => {
+                    self.active_auctions = active_auctions;
+                    if let Some(height) = self.height {
+                        self.supply.record(&self.active_auctions, height);
+                    }
+                    self.metrics
+                        .active_auctions
+                        .set(self.active_auctions.len() as u64);
+                    health::update(&self.health, |h| h.last_chain_query = Some(unix_now()));
+                }
+                Err(err) => {
+                    error!("failed to refresh active auctions: {err:?}");
+                    warn!("retrying auction refresh in 5 seconds");
+                    self.idle(Duration::from_secs(5)).await;
+
+                    continue;
+                }
+            }
+
+            if let Some(snapshot) = self.price_feed.latest() {
+                let retain_secs = self.twap_retention();
+                for (denom, price) in &snapshot.prices {
+                    if let Some(at) = snapshot.updated_at.get(denom) {
+                        self.price_history.record(*denom, *price, *at, retain_secs);
+                    }
+                }
+                self.prices = snapshot.prices;
+                self.prices_updated_at = snapshot.updated_at;
+                let updated_at = self.prices_updated_at.values().max().copied();
+                health::update(&self.health, |h| h.prices_updated_at = updated_at);
+                if let Some(pnl) = &self.pnl {
+                    pnl::update(pnl, |p| p.update_prices(&self.prices));
+                }
+                if self.somm_reference_price.is_none() {
+                    self.somm_reference_price = self.prices.get(&Denom::USOMM).cloned();
+                }
+                debug!("price cache {:?}", self.prices);
+            }
+
+            self.apply_stop_conditions();
+            self.publish_state();
+
             if self.active_auctions.is_empty() {
                 info!("no active auctions, retrying in 5 minutes. auctions last a long time, you're not missing anything.");
-                tokio::time::sleep(tokio::time::Duration::from_secs(300)).await;
+                self.idle(Duration::from_secs(300)).await;
 
                 continue;
             }
 
-            // everything few loops so we don't hit the rate limit
-            if count % 4 == 0 {
-                self.refresh_prices(coingecko_ids.clone()).await?;
+            if self.paused {
+                debug!("order evaluation is paused");
+                self.idle(self.refresh_interval).await;
+
+                continue;
             }
 
-            count += 1;
+            info!("evaluating orders for auctions");
+            self.check_ignored_denoms();
+            let (order_bids, decisions) = self.candidate_bids();
+            let wait = self
+                .projected_wait(&decisions)
+                .unwrap_or(self.refresh_interval);
+            decisions.into_iter().for_each(|d| self.decisions.record(d));
+            for order_bid in order_bids {
+                // sent orders wait for their fill before being evaluated again
+                *self.pending.entry(order_bid.order_id).or_default() += 1;
 
-            let mut sent_orders = Vec::new();
+                if let Err(err) = tx.send(order_bid).await {
+                    // the engine stops receiving bids when it shuts down
+                    if self.is_shutting_down() {
+                        return Ok(());
+                    }
 
-            // for each active auction, check if any orders qualify for a bid
-            info!("evaluating orders for auctions");
-            for auction in &self.active_auctions {
-                let auction_denom = match Denom::try_from(
-                    auction
-                        .starting_tokens_for_sale
-                        .clone()
-                        .unwrap()
-                        .denom
-                        .clone(),
-                ) {
-                    Ok(d) => d,
-                    Err(err) => {
-                        error!("failed to parse auction denom from auction object: {err:?}");
+                    panic!("bid sender errored unexpectedly: {err:?}");
+                }
+            }
+            self.last_evaluation = Some(unix_now());
+            self.publish_state();
+
+            self.idle(wait).await;
+        }
+    }
+
+    // How long to sleep until the first order is projected to qualify, if every order passed on
+    // every auction it was evaluated for. The wait ends a block early so the bid lands at the
+    // projected price, and is bounded by the refresh interval and the max projected wait.
+    fn projected_wait(&self, decisions: &[Decision]) -> Option<Duration> {
+        if !self.projections_enabled()
+            || decisions.is_empty()
+            || decisions.iter().any(|d| d.verdict != Verdict::Pass)
+        {
+            return None;
+        }
+        let height = self.height?;
+
+        let mut first_block: Option<u64> = None;
+        for decision in decisions {
+            let order = self
+                .orders
+                .values()
+                .flatten()
+                .find(|o| o.id == decision.order_id)?;
+            let auction = self
+                .active_auctions
+                .iter()
+                .find(|a| a.id == decision.auction_id)?;
+            match projection::first_qualifying_block(
+                order,
+                decision.usd_price?,
+                decision.somm_usd_price,
+                auction,
+                height,
+                self.acceleration_rate,
+            ) {
+                Ok(Some(block)) => {
+                    first_block = Some(first_block.map_or(block, |b| b.min(block)));
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    warn!(
+                        "failed to project prices of auction {}: {err:?}",
+                        auction.id
+                    );
+
+                    return None;
+                }
+            }
+        }
+
+        let wait = match first_block {
+            Some(block) => {
+                let blocks = block.saturating_sub(height).saturating_sub(1);
+                debug!(
+                    "first order is projected to qualify at block {block}, {blocks} blocks away"
+                );
+
+                Duration::from_secs(blocks.saturating_mul(SECONDS_PER_BLOCK))
+            }
+            None => self.max_projected_wait,
+        };
+
+        Some(wait.clamp(self.refresh_interval, self.max_projected_wait))
+    }
+
+    // Warns when auctions with unknown denoms or orders without a USD price start being ignored
+    fn check_ignored_denoms(&mut self) {
+        let mut unknown_denom_auctions = HashSet::new();
+        let mut unpriced_denoms = HashSet::new();
+        for auction in &self.active_auctions {
+            let denom = auction
+                .starting_tokens_for_sale
+                .as_ref()
+                .map(|c| c.denom.clone())
+                .unwrap_or_default();
+            let parsed = match Denom::try_from(&denom) {
+                Ok(d) => d,
+                Err(_) => {
+                    if !self.unknown_denom_auctions.contains(&auction.id) {
+                        warn!(
+                            auction_id = auction.id,
+                            denom = denom.as_str(),
+                            "ignoring auction with an unknown denom"
+                        );
+                    }
+                    unknown_denom_auctions.insert(auction.id);
+
+                    continue;
+                }
+            };
+
+            let orders = self
+                .orders
+                .get(&parsed)
+                .map(|o| o.len())
+                .unwrap_or_default();
+            if orders == 0
+                || !self.denom_filter.permits(&parsed)
+                || self.prices.contains_key(&parsed)
+            {
+                continue;
+            }
+            if !self.unpriced_denoms.contains(&parsed) {
+                warn!(
+                    auction_id = auction.id,
+                    denom = denom.as_str(),
+                    orders,
+                    "no USD price for the auction's denom, ignoring its orders until there is one"
+                );
+            }
+            unpriced_denoms.insert(parsed);
+        }
+
+        for denom in self.unpriced_denoms.difference(&unpriced_denoms) {
+            info!(
+                denom = denom.symbol().as_str(),
+                "USD price available again, evaluating its orders"
+            );
+        }
+        self.metrics
+            .denoms_unpriced
+            .set(unpriced_denoms.len() as u64);
+        self.unknown_denom_auctions = unknown_denom_auctions;
+        self.unpriced_denoms = unpriced_denoms;
+    }
+
+    // Evaluates every order against every active auction in a single pass, returning the bids to
+    // submit and a decision for each order considered. Orders with a bid in flight are skipped, an
+    // order bids on at most one auction, and orders competing for an auction are allocated by
+    // priority according to the allocation strategy.
+    fn candidate_bids(&self) -> (Vec<OrderBid>, Vec<Decision>) {
+        let mut order_bids = Vec::new();
+        let mut decisions = Vec::new();
+        let mut bidding_orders = HashSet::new();
+        let now = unix_now();
+        let somm_usd_price = self.prices.get(&Denom::USOMM).cloned();
+        for auction in &self.active_auctions {
+            let auction_denom = match auction
+                .starting_tokens_for_sale
+                .as_ref()
+                .ok_or_else(|| eyre!("auction {} has no tokens for sale", auction.id))
+                .and_then(|c| Denom::try_from(&c.denom))
+            {
+                Ok(d) => d,
+                Err(err) => {
+                    debug!("failed to parse auction denom from auction object: {err:?}");
+                    self.metrics.auctions_unknown_denom.inc();
+
+                    continue;
+                }
+            };
+            let orders = match self.orders.get(&auction_denom) {
+                Some(orders) => orders,
+                None => continue,
+            };
+            if let Some(reason) = self.denom_filter.rejects(&auction_denom) {
+                debug!(
+                    "{auction_denom:?} is {reason}, skipping auction {}",
+                    auction.id
+                );
+                decisions.extend(orders.iter().map(|o| {
+                    Decision::skip(o, auction.id, format!("{auction_denom:?} is {reason}"))
+                }));
+
+                continue;
+            }
+
+            // if we don't have a usd price for the token, move on
+            let usd_unit_value = match self.prices.get(&auction_denom) {
+                Some(p) => *p,
+                None => {
+                    debug!("no USD price for {auction_denom}, skipping bid evaluation");
+                    self.metrics.orders_unpriced.inc_by(orders.len() as u64);
+                    decisions.extend(orders.iter().map(|o| {
+                        Decision::skip(o, auction.id, format!("no USD price for {auction_denom}"))
+                    }));
+
+                    continue;
+                }
+            };
+
+            let mut orders = orders.iter().collect::<Vec<&Order>>();
+            allocation::sort_by_priority(&mut orders);
+            // with priority allocation, each order sees the supply left by higher priority bids
+            let mut available = auction.clone();
+            let mut auction_bids = Vec::new();
+            for order in orders {
+                if self.pending.contains_key(&order.id) {
+                    decisions.push(Decision::skip(
+                        order,
+                        auction.id,
+                        "a bid is already in flight",
+                    ));
+
+                    continue;
+                }
+                if bidding_orders.contains(&order.id) {
+                    decisions.push(Decision::skip(
+                        order,
+                        auction.id,
+                        "already bidding on another auction",
+                    ));
+
+                    continue;
+                }
+                if self.allocation == AllocationStrategy::FirstMatch && !auction_bids.is_empty() {
+                    decisions.push(Decision::skip(
+                        order,
+                        auction.id,
+                        "a higher priority order is bidding on this auction",
+                    ));
+
+                    continue;
+                }
+
+                // a split order keeps bidding its parts on an auction it has started on
+                let split = order.split.as_ref().map(|split| {
+                    let progress = self
+                        .splits
+                        .get(&order.id)
+                        .filter(|p| p.auction_id == auction.id && p.parts_bid < split.parts);
+                    (split, progress)
+                });
+                if let Some((split, Some(progress))) = split {
+                    if !split.ready(progress.last_height, self.height) {
+                        decisions.push(Decision::skip(
+                            order,
+                            auction.id,
+                            "waiting for the block of its next split bid",
+                        ));
+
+                        continue;
+                    }
+                }
+                let last_dca_bid = self.last_dca_bids.get(&order.id).cloned();
+                let splitting = matches!(split, Some((_, Some(_))));
+                if !splitting && !order.dca_ready(auction.id, now, last_dca_bid) {
+                    debug!("dca order {} is waiting for its next bid", order.id);
+                    decisions.push(Decision::skip(
+                        order,
+                        auction.id,
+                        "waiting for the next dca bid",
+                    ));
+
+                    continue;
+                }
+                let usd_value = match order.twap_window_secs {
+                    Some(window) => match self.price_history.twap(&auction_denom, window, now) {
+                        Some(twap) => {
+                            debug!(
+                                "valuing {auction_denom:?} at a {window}s twap of {twap} for order {}",
+                                order.id
+                            );
+                            twap
+                        }
+                        None => {
+                            decisions.push(Decision::skip(
+                                order,
+                                auction.id,
+                                format!("no price history for {auction_denom:?}"),
+                            ));
+
+                            continue;
+                        }
+                    },
+                    None => usd_unit_value,
+                };
+                // bid ahead of the price target if others are buying the supply up fast
+                let premium_bps = order.compete.as_ref().map_or(0, |compete| {
+                    compete.premium_bps(self.supply.blocks_until_sellout(auction.id))
+                });
+                let target = if premium_bps > 0 {
+                    debug!(
+                        "auction {} is selling out, relaxing order {}'s target by {premium_bps} bps",
+                        auction.id, order.id
+                    );
+                    Cow::Owned(order.with_premium(premium_bps))
+                } else {
+                    Cow::Borrowed(order)
+                };
+                let target = match split {
+                    Some((split, progress)) => {
+                        let part_size = progress
+                            .map_or_else(|| split.part_size(order.bid_size()), |p| p.part_size);
+                        Cow::Owned(target.part(part_size))
+                    }
+                    None => target,
+                };
+                if let Some(snipe) = order.snipe.as_ref().filter(|_| premium_bps == 0) {
+                    if !snipe.ready(auction, self.height) {
+                        debug!(
+                            "order {} is waiting to snipe auction {}",
+                            order.id, auction.id
+                        );
+                        decisions.push(Decision::skip(
+                            order,
+                            auction.id,
+                            "waiting for the end of the auction to snipe",
+                        ));
 
                         continue;
                     }
+                }
+
+                let (result, decision) = match &order.strategy {
+                    Some(_) => self.strategies.evaluate(
+                        &target,
+                        &available,
+                        usd_value,
+                        somm_usd_price,
+                        self.height,
+                        now,
+                    ),
+                    None => evaluate::evaluate(&target, usd_value, somm_usd_price, &available),
                 };
-                if let Some(orders) = self.orders.get(&auction_denom) {
-                    for order in orders {
-                        // if we don't have a usd price for the token, move on
-                        if let Some(usd_unit_value) = self.prices.get(&auction_denom) {
-                            if let Some(bid) = self.evaluate_bid(order, *usd_unit_value, auction) {
-                                // submit bid
-                                if let Err(err) = tx.send(bid).await {
-                                    panic!("bid sender errored unexpectedly: {err:?}");
-                                }
-
-                                sent_orders.push(order.clone());
+                decisions.push(decision);
+                match result {
+                    Ok(Some(bid)) => {
+                        if self.allocation == AllocationStrategy::Priority {
+                            if let Some(remaining) = available.remaining_tokens_for_sale.as_mut() {
+                                let left = evaluate::parse_amount(&remaining.amount)
+                                    .unwrap_or_default()
+                                    .saturating_sub(bid.minimum_tokens_out);
+                                remaining.amount = left.to_string();
                             }
-                        } else {
-                            warn!("no USD price for {auction_denom}, skipping bid evaluation");
                         }
+                        auction_bids.push(((order, usd_value), order.weight.unwrap_or(1), bid));
                     }
+                    Ok(None) => {}
+                    Err(err) => error!(
+                        "failed to evaluate order {} for auction {}: {err:?}",
+                        order.id, auction.id
+                    ),
                 }
             }
 
-            // remove sent orders from the orders map
-            for order in sent_orders {
-                info!("removing sent order from state: {order:?}");
-                let orders = self.orders.get_mut(&order.fee_token).unwrap();
-                orders.retain(|o| o != &order);
-            }
-
-            // roughly every block
-            tokio::time::sleep(std::time::Duration::from_secs(6)).await;
-        }
-    }
-
-    // Collin: Currently not checking USOMM price in USD and thus not guaranteeing a profitable
-    // arbitrage. We're simply checking how much USD value we can get out with the max possible
-    // USOMM offer.
-    fn evaluate_bid(&self, order: &Order, usd_unit_value: f64, auction: &Auction) -> Option<Bid> {
-        debug!("evaluating bid for order: {:?}", order);
-        let denom = order.fee_token.clone();
-        let usd_unit_value = usd_unit_value / 10u128.pow(denom.decimals() as u32) as f64;
-        let auction_unit_price_in_usomm =
-            f64::from_str(&auction.current_unit_price_in_usomm).unwrap();
-        // divide by 1e18 because sdk.Dec is just a BigInt and the exponent info is lost when
-        // serialized into a proto.
-        let auction_unit_price_in_usomm = auction_unit_price_in_usomm / 1_000_000_000_000_000_000.0;
-        let remaining_tokens_for_sale = auction
-            .remaining_tokens_for_sale
-            .clone()
-            .unwrap()
-            .amount
-            .parse::<u128>()
-            .unwrap();
-
-        // the auction will give us the best possible price which makes this simpler
-        let max_allowed_usomm_offer = order.maximum_usomm_in;
-        let min_possible_token_out = std::cmp::min(
-            (max_allowed_usomm_offer as f64 / auction_unit_price_in_usomm) as u128,
-            remaining_tokens_for_sale,
-        );
-        let usd_value_out = min_possible_token_out as f64 * usd_unit_value;
-
-        debug!(
-            "usd_unit_value = {}, auction_unit_price_in_usomm = {}, remaining_tokens_for_sale = {}, max_allowed_usomm_offer = {}, min_possible_token_out = {}, usd_value_out = {}",
-            usd_unit_value, auction_unit_price_in_usomm, remaining_tokens_for_sale, max_allowed_usomm_offer, min_possible_token_out, usd_value_out
-        );
-
-        if order.minimum_usd_value_out <= usd_value_out {
-            info!(
-                "order qualifies for bid. usomm offer = {}, minimum token out = {}, usd value out = {} for {}",
-                max_allowed_usomm_offer,
-                min_possible_token_out,
-                usd_value_out,
-                order.fee_token
-            );
+            let auction_bids = if self.allocation == AllocationStrategy::ProRata {
+                let remaining = evaluate::remaining_tokens_for_sale(auction).unwrap_or_default();
+                allocation::pro_rata(auction_bids, remaining)
+            } else {
+                auction_bids
+                    .into_iter()
+                    .map(|(order, _, bid)| (order, bid))
+                    .collect()
+            };
+            // each bid records the price its order was evaluated at, e.g. a twap
+            for ((order, usd_value), bid) in auction_bids {
+                bidding_orders.insert(order.id);
+                // a grouped order's bid is split between its members' wallets
+                let wallets = match &order.group {
+                    Some(group) => allocation::split(
+                        &bid,
+                        group
+                            .iter()
+                            .map(|m| (Some(m.wallet.clone()), m.weight.unwrap_or(1)))
+                            .collect(),
+                    ),
+                    None => vec![(order.wallet.clone(), bid)],
+                };
+                for (wallet, bid) in wallets {
+                    order_bids.push(OrderBid {
+                        order_id: order.id,
+                        bid,
+                        wallet,
+                        usd_price: Some(usd_value),
+                        somm_usd_price,
+                        tx_fee: order.tx_fee.clone(),
+                    });
+                }
+            }
+        }
 
-            return Some(Bid {
-                auction_id: auction.id,
-                fee_token: order.fee_token,
-                maximum_usomm_in: max_allowed_usomm_offer as u128,
-                minimum_tokens_out: min_possible_token_out,
-            });
-        } else {
-            info!(
-                " usd value out = {} does not meet minimum usd value out = {} for {}",
-                usd_value_out, order.minimum_usd_value_out, order.fee_token
-            );
+        (order_bids, decisions)
+    }
+}
+
+// Resolves once the engine starts shutting down, or never if there's no shutdown signal
+async fn shutdown_requested(shutdown: Option<&mut watch::Receiver<bool>>) {
+    match shutdown {
+        Some(shutdown) => {
+            let _ = shutdown.wait_for(|s| *s).await;
         }
+        None => std::future::pending().await,
+    }
+}
 
-        None
+// The next admin command, or None once the admin API goes away. Never resolves if it's disabled.
+async fn next_command(
+    commands: Option<&mut UnboundedReceiver<AdminCommand>>,
+) -> Option<AdminCommand> {
+    match commands {
+        Some(commands) => commands.recv().await,
+        None => std::future::pending().await,
     }
 }
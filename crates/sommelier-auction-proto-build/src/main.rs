@@ -92,6 +92,11 @@ fn compile_protos(out_dir: &Path, tmp_dir: &Path) {
 
     let mut proto_include_dir = root.clone();
     proto_include_dir.push(format!("{}/proto", proto_root));
+    // the order engine's own protos live in the proto crate
+    let mut admin_proto_include_dir = root.clone();
+    admin_proto_include_dir.push("sommelier-auction-proto/proto");
+    let mut admin_proto_dir = admin_proto_include_dir.clone();
+    admin_proto_dir.push("admin/v1");
     let mut third_party_proto_include_dir = root;
     third_party_proto_include_dir.push(format!("{}/third_party/proto", proto_root));
 
@@ -140,6 +145,32 @@ fn compile_protos(out_dir: &Path, tmp_dir: &Path) {
         .compile_with_config(config, &protos, &proto_include_paths)
         .unwrap();
 
+    // The admin service is served by the order engine, so it gets a server as well as a client
+    println!("[info ] Compiling admin service client and server!");
+    let admin_protos: Vec<PathBuf> = WalkDir::new(&admin_proto_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            e.file_type().is_file()
+                && e.path().extension().is_some()
+                && e.path().extension().unwrap() == "proto"
+        })
+        .map(|e| e.into_path())
+        .collect();
+    let mut admin_config = prost_build::Config::default();
+    admin_config.out_dir(tmp_dir);
+    tonic_build::configure()
+        .build_client(true)
+        .build_server(true)
+        .format(true)
+        .out_dir(tmp_dir)
+        .compile_with_config(
+            admin_config,
+            &admin_protos,
+            &[admin_proto_include_dir, proto_include_paths[1].clone()],
+        )
+        .unwrap();
+
     copy_generated_files(tmp_dir, out_dir);
 
     println!("[info ] => Done!");
@@ -0,0 +1,493 @@
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Order {
+    /// Assigned by the engine. Ignored in UpsertOrder requests.
+    #[prost(uint64, tag = "1")]
+    pub id: u64,
+    #[prost(string, tag = "2")]
+    pub fee_token: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "3")]
+    pub maximum_usomm_in: u64,
+    #[prost(double, tag = "4")]
+    pub minimum_usd_value_out: f64,
+    #[prost(message, optional, tag = "5")]
+    pub max_somm_per_token: ::core::option::Option<f64>,
+    #[prost(message, optional, tag = "6")]
+    pub max_usd_per_token: ::core::option::Option<f64>,
+    #[prost(message, optional, tag = "7")]
+    pub expires_at: ::core::option::Option<u64>,
+    #[prost(message, optional, tag = "8")]
+    pub expires_at_height: ::core::option::Option<u64>,
+    #[prost(message, optional, tag = "9")]
+    pub dca: ::core::option::Option<Dca>,
+    #[prost(message, optional, tag = "10")]
+    pub stop: ::core::option::Option<StopConditions>,
+    /// Name of the wallet to bid from. Bids rotate through the wallets if empty.
+    #[prost(string, tag = "11")]
+    pub wallet: ::prost::alloc::string::String,
+    /// Margin in basis points taken off the computed minimum tokens out
+    #[prost(message, optional, tag = "12")]
+    pub slippage_margin_bps: ::core::option::Option<u32>,
+    /// Priority when orders compete for an auction. Higher goes first.
+    #[prost(message, optional, tag = "13")]
+    pub priority: ::core::option::Option<i32>,
+    /// Share of an auction's supply under pro-rata allocation
+    #[prost(message, optional, tag = "14")]
+    pub weight: ::core::option::Option<u32>,
+    /// Holds the order's bids until late in the auction
+    #[prost(message, optional, tag = "15")]
+    pub snipe: ::core::option::Option<Snipe>,
+    /// Bids ahead of the price target when others are buying up an auction's supply fast
+    #[prost(message, optional, tag = "16")]
+    pub compete: ::core::option::Option<Compete>,
+    /// Fixed transaction fee for the order's bids. Chosen from gas prices and balances if unset.
+    #[prost(message, optional, tag = "17")]
+    pub tx_fee: ::core::option::Option<TxFee>,
+    /// Wallets that share the order's bids. Each bid is split between them by weight.
+    #[prost(message, repeated, tag = "18")]
+    pub group: ::prost::alloc::vec::Vec<GroupMember>,
+    /// Name of the strategy module that decides the order's bids. Built-in evaluation if empty.
+    #[prost(string, tag = "19")]
+    pub strategy: ::prost::alloc::string::String,
+    /// Values the token at its time-weighted average price over this many seconds
+    #[prost(message, optional, tag = "20")]
+    pub twap_window_secs: ::core::option::Option<u64>,
+    /// Splits each bid into smaller ones over consecutive blocks
+    #[prost(message, optional, tag = "21")]
+    pub split: ::core::option::Option<Split>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Dca {
+    #[prost(uint64, tag = "1")]
+    pub usomm_per_bid: u64,
+    #[prost(message, optional, tag = "2")]
+    pub interval_secs: ::core::option::Option<u64>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Split {
+    #[prost(uint32, tag = "1")]
+    pub parts: u32,
+    #[prost(message, optional, tag = "2")]
+    pub blocks_between: ::core::option::Option<u64>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Snipe {
+    #[prost(message, optional, tag = "1")]
+    pub blocks_before_end: ::core::option::Option<u64>,
+    #[prost(message, optional, tag = "2")]
+    pub max_remaining_decline_bps: ::core::option::Option<u32>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Compete {
+    #[prost(uint64, tag = "1")]
+    pub sellout_blocks: u64,
+    #[prost(uint32, tag = "2")]
+    pub max_premium_bps: u32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TxFee {
+    /// Integer amount as a string, since it may not fit in 64 bits
+    #[prost(string, tag = "1")]
+    pub amount: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub denom: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GroupMember {
+    #[prost(string, tag = "1")]
+    pub wallet: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "2")]
+    pub weight: ::core::option::Option<u32>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct StopConditions {
+    #[prost(message, optional, tag = "1")]
+    pub max_price_age_secs: ::core::option::Option<u64>,
+    #[prost(message, optional, tag = "2")]
+    pub max_somm_price_change_pct: ::core::option::Option<f64>,
+    #[prost(message, optional, tag = "3")]
+    pub max_consecutive_failures: ::core::option::Option<u32>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AuctionSummary {
+    #[prost(uint32, tag = "1")]
+    pub id: u32,
+    #[prost(string, tag = "2")]
+    pub denom: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "3")]
+    pub end_block: u64,
+    #[prost(string, tag = "4")]
+    pub current_unit_price_in_usomm: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub remaining_tokens_for_sale: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListOrdersRequest {}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListOrdersResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub orders: ::prost::alloc::vec::Vec<Order>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpsertOrderRequest {
+    /// The order to replace. A new order is created if unset.
+    #[prost(message, optional, tag = "1")]
+    pub id: ::core::option::Option<u64>,
+    #[prost(message, optional, tag = "2")]
+    pub order: ::core::option::Option<Order>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpsertOrderResponse {
+    #[prost(message, optional, tag = "1")]
+    pub order: ::core::option::Option<Order>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CancelOrderRequest {
+    #[prost(uint64, tag = "1")]
+    pub id: u64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CancelOrderResponse {}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EngineStatusRequest {}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EngineStatusResponse {
+    #[prost(bool, tag = "1")]
+    pub paused: bool,
+    #[prost(message, repeated, tag = "2")]
+    pub orders: ::prost::alloc::vec::Vec<Order>,
+    /// Orders with a bid in flight
+    #[prost(uint64, repeated, tag = "3")]
+    pub pending_orders: ::prost::alloc::vec::Vec<u64>,
+    #[prost(message, repeated, tag = "4")]
+    pub active_auctions: ::prost::alloc::vec::Vec<AuctionSummary>,
+    /// USD price of one whole token by denom
+    #[prost(map = "string, double", tag = "5")]
+    pub prices: ::std::collections::HashMap<::prost::alloc::string::String, f64>,
+    /// Unix time of the last completed evaluation pass
+    #[prost(message, optional, tag = "6")]
+    pub last_evaluation: ::core::option::Option<u64>,
+}
+#[doc = r" Generated client implementations."]
+pub mod admin_client {
+    #![allow(unused_variables, dead_code, missing_docs)]
+    use tonic::codegen::*;
+    #[doc = " Admin manages the orders of a running order engine"]
+    pub struct AdminClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl AdminClient<tonic::transport::Channel> {
+        #[doc = r" Attempt to create a new client by connecting to a given endpoint."]
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: std::convert::TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
+    }
+    impl<T> AdminClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::ResponseBody: Body + HttpBody + Send + 'static,
+        T::Error: Into<StdError>,
+        <T::ResponseBody as HttpBody>::Error: Into<StdError> + Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_interceptor(inner: T, interceptor: impl Into<tonic::Interceptor>) -> Self {
+            let inner = tonic::client::Grpc::with_interceptor(inner, interceptor);
+            Self { inner }
+        }
+        #[doc = " ListOrders returns every active order"]
+        pub async fn list_orders(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListOrdersRequest>,
+        ) -> Result<tonic::Response<super::ListOrdersResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/admin.v1.Admin/ListOrders");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        #[doc = " UpsertOrder creates an order, or replaces the order with the given id"]
+        pub async fn upsert_order(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UpsertOrderRequest>,
+        ) -> Result<tonic::Response<super::UpsertOrderResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/admin.v1.Admin/UpsertOrder");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        #[doc = " CancelOrder removes an order"]
+        pub async fn cancel_order(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CancelOrderRequest>,
+        ) -> Result<tonic::Response<super::CancelOrderResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/admin.v1.Admin/CancelOrder");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        #[doc = " EngineStatus returns the engine's current evaluation state"]
+        pub async fn engine_status(
+            &mut self,
+            request: impl tonic::IntoRequest<super::EngineStatusRequest>,
+        ) -> Result<tonic::Response<super::EngineStatusResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/admin.v1.Admin/EngineStatus");
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+    }
+    impl<T: Clone> Clone for AdminClient<T> {
+        fn clone(&self) -> Self {
+            Self {
+                inner: self.inner.clone(),
+            }
+        }
+    }
+    impl<T> std::fmt::Debug for AdminClient<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "AdminClient {{ ... }}")
+        }
+    }
+}
+#[doc = r" Generated server implementations."]
+pub mod admin_server {
+    #![allow(unused_variables, dead_code, missing_docs)]
+    use tonic::codegen::*;
+    #[doc = "Generated trait containing gRPC methods that should be implemented for use with AdminServer."]
+    #[async_trait]
+    pub trait Admin: Send + Sync + 'static {
+        #[doc = " ListOrders returns every active order"]
+        async fn list_orders(
+            &self,
+            request: tonic::Request<super::ListOrdersRequest>,
+        ) -> Result<tonic::Response<super::ListOrdersResponse>, tonic::Status>;
+        #[doc = " UpsertOrder creates an order, or replaces the order with the given id"]
+        async fn upsert_order(
+            &self,
+            request: tonic::Request<super::UpsertOrderRequest>,
+        ) -> Result<tonic::Response<super::UpsertOrderResponse>, tonic::Status>;
+        #[doc = " CancelOrder removes an order"]
+        async fn cancel_order(
+            &self,
+            request: tonic::Request<super::CancelOrderRequest>,
+        ) -> Result<tonic::Response<super::CancelOrderResponse>, tonic::Status>;
+        #[doc = " EngineStatus returns the engine's current evaluation state"]
+        async fn engine_status(
+            &self,
+            request: tonic::Request<super::EngineStatusRequest>,
+        ) -> Result<tonic::Response<super::EngineStatusResponse>, tonic::Status>;
+    }
+    #[doc = " Admin manages the orders of a running order engine"]
+    #[derive(Debug)]
+    pub struct AdminServer<T: Admin> {
+        inner: _Inner<T>,
+    }
+    struct _Inner<T>(Arc<T>, Option<tonic::Interceptor>);
+    impl<T: Admin> AdminServer<T> {
+        pub fn new(inner: T) -> Self {
+            let inner = Arc::new(inner);
+            let inner = _Inner(inner, None);
+            Self { inner }
+        }
+        pub fn with_interceptor(inner: T, interceptor: impl Into<tonic::Interceptor>) -> Self {
+            let inner = Arc::new(inner);
+            let inner = _Inner(inner, Some(interceptor.into()));
+            Self { inner }
+        }
+    }
+    impl<T, B> Service<http::Request<B>> for AdminServer<T>
+    where
+        T: Admin,
+        B: HttpBody + Send + Sync + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = Never;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/admin.v1.Admin/ListOrders" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListOrdersSvc<T: Admin>(pub Arc<T>);
+                    impl<T: Admin> tonic::server::UnaryService<super::ListOrdersRequest> for ListOrdersSvc<T> {
+                        type Response = super::ListOrdersResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListOrdersRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).list_orders(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let interceptor = inner.1.clone();
+                        let inner = inner.0;
+                        let method = ListOrdersSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = if let Some(interceptor) = interceptor {
+                            tonic::server::Grpc::with_interceptor(codec, interceptor)
+                        } else {
+                            tonic::server::Grpc::new(codec)
+                        };
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/admin.v1.Admin/UpsertOrder" => {
+                    #[allow(non_camel_case_types)]
+                    struct UpsertOrderSvc<T: Admin>(pub Arc<T>);
+                    impl<T: Admin> tonic::server::UnaryService<super::UpsertOrderRequest> for UpsertOrderSvc<T> {
+                        type Response = super::UpsertOrderResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UpsertOrderRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).upsert_order(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let interceptor = inner.1.clone();
+                        let inner = inner.0;
+                        let method = UpsertOrderSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = if let Some(interceptor) = interceptor {
+                            tonic::server::Grpc::with_interceptor(codec, interceptor)
+                        } else {
+                            tonic::server::Grpc::new(codec)
+                        };
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/admin.v1.Admin/CancelOrder" => {
+                    #[allow(non_camel_case_types)]
+                    struct CancelOrderSvc<T: Admin>(pub Arc<T>);
+                    impl<T: Admin> tonic::server::UnaryService<super::CancelOrderRequest> for CancelOrderSvc<T> {
+                        type Response = super::CancelOrderResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CancelOrderRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).cancel_order(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let interceptor = inner.1.clone();
+                        let inner = inner.0;
+                        let method = CancelOrderSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = if let Some(interceptor) = interceptor {
+                            tonic::server::Grpc::with_interceptor(codec, interceptor)
+                        } else {
+                            tonic::server::Grpc::new(codec)
+                        };
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/admin.v1.Admin/EngineStatus" => {
+                    #[allow(non_camel_case_types)]
+                    struct EngineStatusSvc<T: Admin>(pub Arc<T>);
+                    impl<T: Admin> tonic::server::UnaryService<super::EngineStatusRequest> for EngineStatusSvc<T> {
+                        type Response = super::EngineStatusResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::EngineStatusRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).engine_status(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let interceptor = inner.1.clone();
+                        let inner = inner.0;
+                        let method = EngineStatusSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = if let Some(interceptor) = interceptor {
+                            tonic::server::Grpc::with_interceptor(codec, interceptor)
+                        } else {
+                            tonic::server::Grpc::new(codec)
+                        };
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => Box::pin(async move {
+                    Ok(http::Response::builder()
+                        .status(200)
+                        .header("grpc-status", "12")
+                        .header("content-type", "application/grpc")
+                        .body(tonic::body::BoxBody::empty())
+                        .unwrap())
+                }),
+            }
+        }
+    }
+    impl<T: Admin> Clone for AdminServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self { inner }
+        }
+    }
+    impl<T: Admin> Clone for _Inner<T> {
+        fn clone(&self) -> Self {
+            Self(self.0.clone(), self.1.clone())
+        }
+    }
+    impl<T: std::fmt::Debug> std::fmt::Debug for _Inner<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+    impl<T: Admin> tonic::transport::NamedService for AdminServer<T> {
+        const NAME: &'static str = "admin.v1.Admin";
+    }
+}
@@ -1,5 +1,11 @@
 pub use cosmos_sdk_proto;
 
+pub mod admin {
+    include!("gen/admin.v1.rs");
+}
+
+pub mod authz;
+
 pub mod auction {
     include!("gen/auction.v1.rs");
 }
@@ -7,3 +13,5 @@ pub mod auction {
 pub mod cellarfees {
     include!("gen/cellarfees.v1.rs");
 }
+
+pub mod node;
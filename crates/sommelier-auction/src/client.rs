@@ -1,20 +1,62 @@
 use std::str::FromStr;
 
 use eyre::Result;
-use ocular::{cosmrs::Any, tx::UnsignedTx, MsgClient, QueryClient};
+use ocular::{
+    cosmrs::{rpc::Client as _, Any},
+    tx::UnsignedTx,
+    MsgClient, QueryClient,
+};
 use prost::Message;
-use sommelier_auction_proto::cosmos_sdk_proto::cosmos::base::v1beta1::Coin;
+use sommelier_auction_proto::cosmos_sdk_proto::cosmos::{
+    auth::v1beta1::{
+        query_client::QueryClient as AuthQueryClient, BaseAccount, QueryAccountRequest,
+    },
+    bank::v1beta1::{query_client::QueryClient as BankQueryClient, QueryBalanceRequest},
+    base::{query::v1beta1::PageRequest, v1beta1::Coin},
+    crypto::secp256k1::PubKey,
+    tx::{
+        signing::v1beta1::SignMode as ProtoSignMode,
+        v1beta1::{mode_info, AuthInfo, Fee, ModeInfo, SignDoc, SignerInfo, TxBody, TxRaw},
+    },
+};
+use tracing::{debug, warn};
 
 use crate::{
-    auction::*, bid::Bid, cellarfees::*, denom::Denom, parameters::AuctionParameters, AccountInfo,
-    BidResult,
+    auction::*,
+    authz::{
+        query_client::QueryClient as AuthzQueryClient, Grant, MsgExec, MsgExecResponse,
+        QueryGrantsRequest,
+    },
+    bid::Bid,
+    cellarfees::*,
+    denom::Denom,
+    fee::{self, GasPrice, TxFee},
+    node::service_client::ServiceClient as NodeServiceClient,
+    parameters::AuctionParameters,
+    sequence::{self, SequenceManager, MAX_SEQUENCE_RETRIES, SEQUENCE_WAIT_TIMEOUT},
+    signer::{SignMode, Signer},
+    AccountInfo, BidResult,
 };
 
 pub type TxSyncResponse = ocular::cosmrs::rpc::endpoint::broadcast::tx_sync::Response;
+pub type TxCommitResponse = ocular::cosmrs::rpc::endpoint::broadcast::tx_commit::Response;
 
 pub const DEFAULT_GRPC_ENDPOINT: &str = "https://sommelier-grpc.polkachu.com:14190";
 pub const DEFAULT_RPC_ENDPOINT: &str = "https://sommelier-rpc.polkachu.com:443";
 
+/// The fee paid in usomm for each bid transaction, on top of the bid itself, unless another is set
+/// with [`Client::set_tx_fee`]
+pub const BID_FEE_USOMM: u64 = 0;
+
+/// Gas limit of bid transactions, which fees are paid on
+pub const BID_GAS_LIMIT: u64 = 200_000;
+
+/// Type URL of the bid message, which authz grants for bidding must authorize
+pub const SUBMIT_BID_TYPE_URL: &str = "/auction.v1.MsgSubmitBidRequest";
+const MSG_EXEC_TYPE_URL: &str = "/cosmos.authz.v1beta1.MsgExec";
+
+const CHAIN_ID: &str = "sommelier-3";
+
 #[derive(Debug, Clone)]
 /// A client for querying auction data and submitting bids.
 pub struct Client {
@@ -22,6 +64,12 @@ pub struct Client {
     grpc_endpoint: String,
     auction_client: crate::auction::query_client::QueryClient<tonic::transport::Channel>,
     cellarfees_client: crate::cellarfees::query_client::QueryClient<tonic::transport::Channel>,
+    auth_client: AuthQueryClient<tonic::transport::Channel>,
+    authz_client: AuthzQueryClient<tonic::transport::Channel>,
+    bank_client: BankQueryClient<tonic::transport::Channel>,
+    node_client: NodeServiceClient<tonic::transport::Channel>,
+    sequences: SequenceManager,
+    tx_fee: TxFee,
 }
 
 impl Client {
@@ -31,12 +79,22 @@ impl Client {
             crate::auction::query_client::QueryClient::connect(grpc.clone()).await?;
         let cellarfees_client =
             crate::cellarfees::query_client::QueryClient::connect(grpc.clone()).await?;
+        let auth_client = AuthQueryClient::connect(grpc.clone()).await?;
+        let authz_client = AuthzQueryClient::connect(grpc.clone()).await?;
+        let bank_client = BankQueryClient::connect(grpc.clone()).await?;
+        let node_client = NodeServiceClient::connect(grpc.clone()).await?;
 
         Ok(Self {
             rpc_endpoint: rpc,
             grpc_endpoint: grpc,
             auction_client,
             cellarfees_client,
+            auth_client,
+            authz_client,
+            bank_client,
+            node_client,
+            sequences: SequenceManager::default(),
+            tx_fee: TxFee::default(),
         })
     }
 
@@ -45,6 +103,32 @@ impl Client {
         &self.grpc_endpoint
     }
 
+    /// The fee paid for each bid transaction
+    pub fn tx_fee(&self) -> &TxFee {
+        &self.tx_fee
+    }
+
+    /// Sets the fee paid for bid transactions submitted from now on
+    pub fn set_tx_fee(&mut self, fee: TxFee) {
+        self.tx_fee = fee;
+    }
+
+    /// Query the minimum gas prices the node accepts transactions at. Empty if it accepts any.
+    pub async fn minimum_gas_prices(&mut self) -> Result<Vec<GasPrice>> {
+        let request = crate::node::ConfigRequest::default();
+        let response = self.node_client.config(request).await?;
+
+        fee::parse_gas_prices(&response.into_inner().minimum_gas_price)
+    }
+
+    /// Query the latest block height from the RPC endpoint
+    pub async fn latest_block_height(&self) -> Result<u64> {
+        let rpc_client = ocular::cosmrs::rpc::HttpClient::new(self.rpc_endpoint.as_str())?;
+        let response = rpc_client.latest_block().await?;
+
+        Ok(response.block.header.height.value())
+    }
+
     /// Query all active auctions
     pub async fn active_auctions(&mut self) -> Result<Vec<Auction>> {
         let request = QueryActiveAuctionsRequest::default();
@@ -53,12 +137,47 @@ impl Client {
         Ok(response.into_inner().auctions)
     }
 
-    /// Query all ended auctions
+    /// Query all ended auctions, following pagination until every page has been read
     pub async fn ended_auctions(&mut self) -> Result<Vec<Auction>> {
-        let request = QueryEndedAuctionsRequest::default();
-        let response = self.auction_client.query_ended_auctions(request).await?;
+        let mut auctions = Vec::new();
+        let mut next_key = Vec::new();
+        loop {
+            let request = QueryEndedAuctionsRequest {
+                pagination: Some(PageRequest {
+                    key: next_key,
+                    ..Default::default()
+                }),
+            };
+            let response = self
+                .auction_client
+                .query_ended_auctions(request)
+                .await?
+                .into_inner();
+            auctions.extend(response.auctions);
+
+            match response.pagination {
+                Some(page) if !page.next_key.is_empty() => next_key = page.next_key,
+                _ => break,
+            }
+        }
 
-        Ok(response.into_inner().auctions)
+        Ok(auctions)
+    }
+
+    /// Query an active auction by it's ID. Returns `None` if there is no active auction with that ID,
+    /// for example because it has ended.
+    pub async fn active_auction(&mut self, auction_id: u32) -> Result<Option<Auction>> {
+        let request = QueryActiveAuctionRequest { auction_id };
+        match self.auction_client.query_active_auction(request).await {
+            Ok(response) => Ok(response.into_inner().auction),
+            Err(err) => {
+                if err.to_string().contains("No active auction found for id") {
+                    return Ok(None);
+                }
+
+                Err(err.into())
+            }
+        }
     }
 
     /// Query an auction by it's ID
@@ -86,15 +205,32 @@ impl Client {
         }
     }
 
-    /// Query all bids for an auction
+    /// Query all bids for an auction, following pagination until every page has been read
     pub async fn auction_bids(&mut self, auction_id: u32) -> Result<Vec<BidResult>> {
-        let request = QueryBidsByAuctionRequest {
-            auction_id,
-            pagination: None,
-        };
-        let response = self.auction_client.query_bids_by_auction(request).await?;
+        let mut bids = Vec::new();
+        let mut next_key = Vec::new();
+        loop {
+            let request = QueryBidsByAuctionRequest {
+                auction_id,
+                pagination: Some(PageRequest {
+                    key: next_key,
+                    ..Default::default()
+                }),
+            };
+            let response = self
+                .auction_client
+                .query_bids_by_auction(request)
+                .await?
+                .into_inner();
+            bids.extend(response.bids);
+
+            match response.pagination {
+                Some(page) if !page.next_key.is_empty() => next_key = page.next_key,
+                _ => break,
+            }
+        }
 
-        Ok(response.into_inner().bids)
+        Ok(bids)
     }
 
     /// Query bid by bid ID and auction ID
@@ -168,52 +304,224 @@ impl Client {
             auction_interval: cp.auction_interval,
             fee_accrual_auction_threshold: cp.fee_accrual_auction_threshold,
             price_decrease_block_interval: cp.price_decrease_block_interval,
-            initial_price_decrease_rate: cp.initial_price_decrease_rate.parse().unwrap(),
+            initial_price_decrease_rate: cp.initial_price_decrease_rate.parse()?,
             auction_price_decrease_acceleration_rate: ap
                 .auction_price_decrease_acceleration_rate
-                .parse()
-                .unwrap(),
+                .parse()?,
             minimum_bid_in_usomm: ap.minimum_bid_in_usomm,
-            minimum_sale_tokens_usd_value: ap.minimum_sale_tokens_usd_value.parse().unwrap(),
+            minimum_sale_tokens_usd_value: ap.minimum_sale_tokens_usd_value.parse()?,
         };
 
         Ok(auction_parameters)
     }
 
-    /// Submit a bid to an auction
+    /// Query an account's current sequence
+    pub async fn account_sequence(&mut self, address: &str) -> Result<u64> {
+        Ok(self.account(address).await?.sequence)
+    }
+
+    async fn account(&mut self, address: &str) -> Result<BaseAccount> {
+        let request = QueryAccountRequest {
+            address: address.to_string(),
+        };
+        let account = self
+            .auth_client
+            .account(request)
+            .await?
+            .into_inner()
+            .account
+            .ok_or_else(|| eyre::eyre!("account {address} not found"))?;
+
+        Ok(BaseAccount::decode(account.value.as_slice())?)
+    }
+
+    /// Query an account's balance of a denom
+    pub async fn balance(&mut self, address: &str, denom: Denom) -> Result<u128> {
+        self.denom_balance(address, &denom.to_string()).await
+    }
+
+    /// Query an account's balance of any denom by its on-chain name, such as a fee denom that isn't
+    /// a [`Denom`]
+    pub async fn denom_balance(&mut self, address: &str, denom: &str) -> Result<u128> {
+        let request = QueryBalanceRequest {
+            address: address.to_string(),
+            denom: denom.to_string(),
+        };
+        let balance = self
+            .bank_client
+            .balance(request)
+            .await?
+            .into_inner()
+            .balance;
+
+        match balance {
+            Some(coin) => Ok(coin.amount.parse()?),
+            None => Ok(0),
+        }
+    }
+
+    /// Query the authz grants letting `grantee` bid on behalf of `granter`, following pagination
+    /// until every page has been read
+    pub async fn bid_grants(&mut self, granter: &str, grantee: &str) -> Result<Vec<Grant>> {
+        let mut grants = Vec::new();
+        let mut next_key = Vec::new();
+        loop {
+            let request = QueryGrantsRequest {
+                granter: granter.to_string(),
+                grantee: grantee.to_string(),
+                msg_type_url: SUBMIT_BID_TYPE_URL.to_string(),
+                pagination: Some(PageRequest {
+                    key: next_key,
+                    ..Default::default()
+                }),
+            };
+            let response = self.authz_client.grants(request).await?.into_inner();
+            grants.extend(response.grants);
+
+            match response.pagination {
+                Some(page) if !page.next_key.is_empty() => next_key = page.next_key,
+                _ => break,
+            }
+        }
+
+        Ok(grants)
+    }
+
+    // Polls until the account's sequence reaches `sequence`, returning whether it did before the
+    // timeout
+    async fn wait_for_sequence(&mut self, address: &str, sequence: u64) -> Result<bool> {
+        let deadline = tokio::time::Instant::now() + SEQUENCE_WAIT_TIMEOUT;
+        while tokio::time::Instant::now() < deadline {
+            if self.account_sequence(address).await? >= sequence {
+                return Ok(true);
+            }
+
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        }
+
+        Ok(false)
+    }
+
+    /// Submit a bid to an auction. Transactions from the same sender are serialized so each is
+    /// signed with a fresh sequence, and a bid rejected for a sequence mismatch is re-signed once
+    /// the chain reaches the expected sequence.
     pub async fn submit_bid(&mut self, sender: &AccountInfo, bid: Bid) -> Result<BidResult> {
+        self.submit_bid_from(Sender::Key(sender), None, bid).await
+    }
+
+    /// Submit a bid on behalf of `granter`, which must have granted the sender permission to bid
+    /// through authz. The bid is paid for from and credited to the granter's account.
+    pub async fn submit_bid_as_grantee(
+        &mut self,
+        sender: &AccountInfo,
+        granter: &str,
+        bid: Bid,
+    ) -> Result<BidResult> {
+        self.submit_bid_from(Sender::Key(sender), Some(granter), bid)
+            .await
+    }
+
+    /// Submit a bid to an auction, signed by a [`Signer`] such as a Ledger device or a remote
+    /// signing service. Otherwise behaves like [`Client::submit_bid`].
+    pub async fn submit_bid_with_signer(
+        &mut self,
+        sender: &dyn Signer,
+        bid: Bid,
+    ) -> Result<BidResult> {
+        self.submit_bid_from(Sender::Signer(sender), None, bid)
+            .await
+    }
+
+    /// Submit a bid on behalf of `granter`, signed by a [`Signer`]. See
+    /// [`Client::submit_bid_as_grantee`].
+    pub async fn submit_bid_with_signer_as_grantee(
+        &mut self,
+        sender: &dyn Signer,
+        granter: &str,
+        bid: Bid,
+    ) -> Result<BidResult> {
+        self.submit_bid_from(Sender::Signer(sender), Some(granter), bid)
+            .await
+    }
+
+    async fn submit_bid_from(
+        &mut self,
+        sender: Sender<'_>,
+        granter: Option<&str>,
+        bid: Bid,
+    ) -> Result<BidResult> {
+        let address = sender.address()?;
+        let mut account = self.sequences.lock(&address).await;
+
+        // a previous transaction with an unknown outcome may still be in the mempool. signing
+        // before it commits would reuse its sequence.
+        if let Some(pending) = account.pending.take() {
+            if !self.wait_for_sequence(&address, pending + 1).await? {
+                warn!(
+                    "transaction with sequence {pending} from {address} did not commit, assuming it \
+                     was dropped"
+                );
+            }
+        }
+
+        let mut retries = 0;
+        loop {
+            let sequence = self.account_sequence(&address).await?;
+            let result = match sender {
+                Sender::Key(sender) => self.sign_and_broadcast_bid(sender, granter, &bid).await,
+                Sender::Signer(sender) => {
+                    self.sign_and_broadcast_bid_with_signer(sender, granter, &bid)
+                        .await
+                }
+            };
+            match result {
+                Ok(result) => return Ok(result),
+                Err(err) => {
+                    let expected = sequence::expected_sequence(&err.to_string());
+                    match expected {
+                        Some(expected) if retries < MAX_SEQUENCE_RETRIES => {
+                            retries += 1;
+                            self.wait_for_sequence(&address, expected).await?;
+                        }
+                        Some(_) => return Err(err),
+                        None => {
+                            // unless check_tx rejected it, the transaction may be in the mempool,
+                            // so the next one waits for it to commit or time out
+                            if !err.to_string().contains("rejected by check_tx") {
+                                account.pending = Some(sequence);
+                            }
+
+                            return Err(err);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    async fn sign_and_broadcast_bid(
+        &mut self,
+        sender: &AccountInfo,
+        granter: Option<&str>,
+        bid: &Bid,
+    ) -> Result<BidResult> {
         let mut unsigned_tx = UnsignedTx::new();
-        let request = MsgSubmitBidRequest {
-            auction_id: bid.auction_id,
-            signer: sender.address("somm")?,
-            max_bid_in_usomm: Some(Coin {
-                amount: bid.maximum_usomm_in.to_string(),
-                denom: "usomm".to_string(),
-            }),
-            sale_token_minimum_amount: Some(Coin {
-                amount: bid.minimum_tokens_out.to_string(),
-                denom: bid.fee_token.to_string(),
-            }),
-        };
+        let address = sender.address("somm")?;
+        let request = bid_request(bid, granter.unwrap_or(&address));
 
         // most of this is just getting things into a form ocular's API will accept.
         // kind of clunky when using modules that aren't part of ocular.
-        let mut bytes = vec![];
-        request.encode(&mut bytes)?;
-
-        let any = Any {
-            type_url: "/auction.v1.MsgSubmitBidRequest".to_string(),
-            value: bytes,
-        };
-        unsigned_tx.add_msg(any);
+        let (type_url, value) = bid_message(&request, granter.map(|_| address.as_str()))?;
+        unsigned_tx.add_msg(Any { type_url, value });
 
         let mut q_client = QueryClient::new(&self.grpc_endpoint)?;
-        let fee_info = ocular::prelude::FeeInfo::new(ocular::cosmrs::Coin {
-            amount: 0,
-            denom: ocular::cosmrs::Denom::from_str("usomm")?,
+        let mut fee_info = ocular::prelude::FeeInfo::new(ocular::cosmrs::Coin {
+            amount: self.tx_fee.amount,
+            denom: ocular::cosmrs::Denom::from_str(&self.tx_fee.denom)?,
         });
+        fee_info.gas_limit(BID_GAS_LIMIT);
         let chain_context = ocular::chain::ChainContext {
-            id: "sommelier-3".to_string(),
+            id: CHAIN_ID.to_string(),
             prefix: "somm".to_string(),
         };
         let signed_tx = unsigned_tx
@@ -222,28 +530,225 @@ impl Client {
         let mut m_client = MsgClient::new(&self.rpc_endpoint)?;
         let response = signed_tx.broadcast_commit(&mut m_client).await?;
 
-        if response.deliver_tx.code.value() != 0 {
-            return Err(eyre::eyre!(
-                "error submitting bid. tx_hash = {}, log = {}",
-                response.hash,
-                response.deliver_tx.log
-            ));
-        }
+        bid_from_response(response, granter.is_some())
+    }
 
-        println!("response data: {:?}", response.deliver_tx.data);
+    // Builds and signs the transaction by hand, since ocular only signs with keys it holds
+    async fn sign_and_broadcast_bid_with_signer(
+        &mut self,
+        sender: &dyn Signer,
+        granter: Option<&str>,
+        bid: &Bid,
+    ) -> Result<BidResult> {
+        let address = sender.address().to_string();
+        let account = self.account(&address).await?;
+        let request = bid_request(bid, granter.unwrap_or(&address));
+        let grantee = granter.map(|_| address.as_str());
+
+        let (type_url, value) = bid_message(&request, grantee)?;
+        let body = TxBody {
+            messages: vec![prost_types::Any { type_url, value }],
+            ..Default::default()
+        };
 
-        // extract the Bid from the response. the rust implementation of BroadcastCommit can be
-        // flaky. it may error due to a client timeout even if the transaction ultimately lands in
-        // a block. consumers should query bids to see definitively if theirs settled.
-        if let Some(data) = response.clone().deliver_tx.data {
-            let msg_response = MsgSubmitBidResponse::decode(data.value().as_ref())?;
+        let mut public_key = vec![];
+        PubKey {
+            key: sender.public_key().to_vec(),
+        }
+        .encode(&mut public_key)?;
+        let mode = match sender.sign_mode() {
+            SignMode::Direct => ProtoSignMode::Direct,
+            SignMode::LegacyAminoJson => ProtoSignMode::LegacyAminoJson,
+        };
+        let auth_info = AuthInfo {
+            signer_infos: vec![SignerInfo {
+                public_key: Some(prost_types::Any {
+                    type_url: "/cosmos.crypto.secp256k1.PubKey".to_string(),
+                    value: public_key,
+                }),
+                mode_info: Some(ModeInfo {
+                    sum: Some(mode_info::Sum::Single(mode_info::Single {
+                        mode: mode as i32,
+                    })),
+                }),
+                sequence: account.sequence,
+            }],
+            fee: Some(Fee {
+                amount: vec![Coin {
+                    amount: self.tx_fee.amount.to_string(),
+                    denom: self.tx_fee.denom.clone(),
+                }],
+                gas_limit: BID_GAS_LIMIT,
+                ..Default::default()
+            }),
+        };
+
+        let mut raw = TxRaw::default();
+        body.encode(&mut raw.body_bytes)?;
+        auth_info.encode(&mut raw.auth_info_bytes)?;
+
+        let sign_doc = match sender.sign_mode() {
+            SignMode::Direct => {
+                let mut bytes = vec![];
+                SignDoc {
+                    body_bytes: raw.body_bytes.clone(),
+                    auth_info_bytes: raw.auth_info_bytes.clone(),
+                    chain_id: CHAIN_ID.to_string(),
+                    account_number: account.account_number,
+                }
+                .encode(&mut bytes)?;
 
-            // if the bid is not present, return an error
-            if let Some(bid) = msg_response.bid {
-                return Ok(bid);
+                bytes
             }
+            SignMode::LegacyAminoJson => amino_sign_doc(&request, grantee, &account, &self.tx_fee)?,
+        };
+        raw.signatures.push(sender.sign(sign_doc).await?);
+
+        let mut tx_bytes = vec![];
+        raw.encode(&mut tx_bytes)?;
+        let rpc_client = ocular::cosmrs::rpc::HttpClient::new(self.rpc_endpoint.as_str())?;
+        let response = rpc_client.broadcast_tx_commit(tx_bytes.into()).await?;
+
+        bid_from_response(response, granter.is_some())
+    }
+}
+
+fn bid_request(bid: &Bid, bidder: &str) -> MsgSubmitBidRequest {
+    MsgSubmitBidRequest {
+        auction_id: bid.auction_id,
+        signer: bidder.to_string(),
+        max_bid_in_usomm: Some(Coin {
+            amount: bid.maximum_usomm_in.to_string(),
+            denom: "usomm".to_string(),
+        }),
+        sale_token_minimum_amount: Some(Coin {
+            amount: bid.minimum_tokens_out.to_string(),
+            denom: bid.fee_token.to_string(),
+        }),
+    }
+}
+
+// The type URL and encoding of the message submitting a bid, wrapped in an authz MsgExec if a
+// grantee is bidding on the bidder's behalf
+fn bid_message(request: &MsgSubmitBidRequest, grantee: Option<&str>) -> Result<(String, Vec<u8>)> {
+    let mut bytes = vec![];
+    request.encode(&mut bytes)?;
+    let grantee = match grantee {
+        Some(grantee) => grantee,
+        None => return Ok((SUBMIT_BID_TYPE_URL.to_string(), bytes)),
+    };
+
+    let exec = MsgExec {
+        grantee: grantee.to_string(),
+        msgs: vec![prost_types::Any {
+            type_url: SUBMIT_BID_TYPE_URL.to_string(),
+            value: bytes,
+        }],
+    };
+    let mut bytes = vec![];
+    exec.encode(&mut bytes)?;
+
+    Ok((MSG_EXEC_TYPE_URL.to_string(), bytes))
+}
+
+// The amino JSON sign doc for a bid. Amino JSON is serialized with sorted keys and no whitespace,
+// which serde_json's default map gives us.
+fn amino_sign_doc(
+    request: &MsgSubmitBidRequest,
+    grantee: Option<&str>,
+    account: &BaseAccount,
+    fee: &TxFee,
+) -> Result<Vec<u8>> {
+    let coin = |coin: &Option<Coin>| {
+        coin.as_ref()
+            .map(|c| serde_json::json!({ "amount": c.amount, "denom": c.denom }))
+    };
+    let mut msg = serde_json::json!({
+        "type": "auction/MsgSubmitBidRequest",
+        "value": {
+            "auction_id": request.auction_id,
+            "max_bid_in_usomm": coin(&request.max_bid_in_usomm),
+            "sale_token_minimum_amount": coin(&request.sale_token_minimum_amount),
+            "signer": request.signer,
+        },
+    });
+    if let Some(grantee) = grantee {
+        msg = serde_json::json!({
+            "type": "cosmos-sdk/MsgExec",
+            "value": { "grantee": grantee, "msgs": [msg] },
+        });
+    }
+    let sign_doc = serde_json::json!({
+        "account_number": account.account_number.to_string(),
+        "chain_id": CHAIN_ID,
+        "fee": {
+            "amount": [{ "amount": fee.amount.to_string(), "denom": fee.denom }],
+            "gas": BID_GAS_LIMIT.to_string(),
+        },
+        "memo": "",
+        "msgs": [msg],
+        "sequence": account.sequence.to_string(),
+    });
+
+    Ok(serde_json::to_vec(&sign_doc)?)
+}
+
+// The account signing a bid
+#[derive(Clone, Copy)]
+enum Sender<'a> {
+    Key(&'a AccountInfo),
+    Signer(&'a dyn Signer),
+}
+
+impl Sender<'_> {
+    fn address(&self) -> Result<String> {
+        match self {
+            Sender::Key(sender) => sender.address("somm"),
+            Sender::Signer(sender) => Ok(sender.address().to_string()),
         }
+    }
+}
+
+// Extracts the bid from a transaction's response. A bid executed through authz has its response
+// nested in the MsgExec response.
+fn bid_from_response(response: TxCommitResponse, via_authz: bool) -> Result<BidResult> {
+    if response.check_tx.code.value() != 0 {
+        return Err(eyre::eyre!(
+            "bid rejected by check_tx. tx_hash = {}, log = {}",
+            response.hash,
+            response.check_tx.log
+        ));
+    }
 
-        Err(eyre::eyre!("no data in response: {:?}", response))
+    if response.deliver_tx.code.value() != 0 {
+        return Err(eyre::eyre!(
+            "error submitting bid. tx_hash = {}, log = {}",
+            response.hash,
+            response.deliver_tx.log
+        ));
     }
+
+    debug!("response data: {:?}", response.deliver_tx.data);
+
+    // extract the Bid from the response. the rust implementation of BroadcastCommit can be
+    // flaky. it may error due to a client timeout even if the transaction ultimately lands in
+    // a block. consumers should query bids to see definitively if theirs settled.
+    if let Some(data) = response.clone().deliver_tx.data {
+        let msg_response = if via_authz {
+            let exec_response = MsgExecResponse::decode(data.value().as_ref())?;
+            match exec_response.results.first() {
+                Some(result) => MsgSubmitBidResponse::decode(result.as_slice())?,
+                None => MsgSubmitBidResponse::default(),
+            }
+        } else {
+            MsgSubmitBidResponse::decode(data.value().as_ref())?
+        };
+
+        // if the bid is not present, return an error
+        if let Some(bid) = msg_response.bid {
+            return Ok(bid);
+        }
+    }
+
+    Err(eyre::eyre!("no data in response: {:?}", response))
 }
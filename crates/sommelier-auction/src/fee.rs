@@ -0,0 +1,90 @@
+//! Transaction fees for bids, and choosing one the signer can pay from the chain's minimum gas
+//! prices
+
+use std::str::FromStr;
+
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+
+/// The fee paid for a bid transaction, on top of the bid itself
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxFee {
+    pub amount: u128,
+    pub denom: String,
+}
+
+impl Default for TxFee {
+    /// The default bid fee, `BID_FEE_USOMM` usomm
+    fn default() -> Self {
+        Self {
+            amount: crate::client::BID_FEE_USOMM.into(),
+            denom: "usomm".to_string(),
+        }
+    }
+}
+
+/// A price per unit of gas, e.g. `0.025usomm`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GasPrice {
+    pub amount: f64,
+    pub denom: String,
+}
+
+impl GasPrice {
+    /// The fee for `gas_limit` units of gas at this price, rounded up
+    pub fn fee(&self, gas_limit: u64) -> TxFee {
+        TxFee {
+            amount: (self.amount * gas_limit as f64).ceil() as u128,
+            denom: self.denom.clone(),
+        }
+    }
+}
+
+impl FromStr for GasPrice {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let split = s
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .ok_or_else(|| eyre!("gas price {s} has no denom"))?;
+        let (amount, denom) = s.split_at(split);
+        let amount = amount
+            .parse::<f64>()
+            .map_err(|err| eyre!("gas price {s} has an invalid amount: {err}"))?;
+
+        Ok(Self {
+            amount,
+            denom: denom.to_string(),
+        })
+    }
+}
+
+/// Parses a comma separated list of gas prices in the format nodes report their minimum gas prices
+/// in, e.g. `0.025usomm,0.1ibc/27394FB0`. An empty string has none.
+pub fn parse_gas_prices(s: &str) -> Result<Vec<GasPrice>> {
+    s.split(',')
+        .filter(|p| !p.trim().is_empty())
+        .map(GasPrice::from_str)
+        .collect()
+}
+
+/// The fee for `gas_limit` gas at the first of `gas_prices` whose denom the signer holds enough of,
+/// in the order given. `balance` is the signer's balance of a denom, and `reserved` how much of it
+/// must be left over, such as the usomm the signer is bidding. With no gas prices the default fee
+/// is returned, and None if none of them can be paid.
+pub fn select_fee(
+    gas_prices: &[GasPrice],
+    gas_limit: u64,
+    balance: impl Fn(&str) -> u128,
+    reserved: impl Fn(&str) -> u128,
+) -> Option<TxFee> {
+    if gas_prices.is_empty() {
+        return Some(TxFee::default());
+    }
+
+    gas_prices
+        .iter()
+        .map(|price| price.fee(gas_limit))
+        .find(|fee| balance(&fee.denom) >= fee.amount.saturating_add(reserved(&fee.denom)))
+}
@@ -0,0 +1,143 @@
+//! Signing with a Ledger device running the Cosmos app. Requires the `ledger` feature.
+//!
+//! The Cosmos app only signs amino JSON, so [`LedgerSigner`] uses
+//! [`SignMode::LegacyAminoJson`]. Every signature must be approved on the device.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use eyre::{eyre, Result};
+use ledger_transport::APDUCommand;
+use ledger_transport_hid::{hidapi::HidApi, TransportNativeHID};
+
+use crate::signer::{SignMode, Signer};
+
+const CLA: u8 = 0x55;
+const INS_SIGN_SECP256K1: u8 = 0x02;
+const INS_GET_ADDR_SECP256K1: u8 = 0x04;
+const P1_INIT: u8 = 0x00;
+const P1_ADD: u8 = 0x01;
+const P1_LAST: u8 = 0x02;
+const SW_OK: u16 = 0x9000;
+const CHUNK_SIZE: usize = 250;
+const HARDENED: u32 = 0x8000_0000;
+const COSMOS_COIN_TYPE: u32 = 118;
+
+/// A secp256k1 key held on a Ledger device, at the HD path m/44'/118'/account'/0/index
+pub struct LedgerSigner {
+    transport: Arc<TransportNativeHID>,
+    path: [u32; 5],
+    public_key: Vec<u8>,
+    address: String,
+}
+
+impl LedgerSigner {
+    /// Connects to the first Ledger found and reads the key's public key and address. The Cosmos
+    /// app must be open on the device.
+    pub fn connect(prefix: &str, account: u32, index: u32) -> Result<Self> {
+        let api = HidApi::new().map_err(|err| eyre!("failed to access HID devices: {err}"))?;
+        let transport = TransportNativeHID::new(&api)
+            .map_err(|err| eyre!("failed to connect to ledger: {err}"))?;
+        let path = [
+            44 | HARDENED,
+            COSMOS_COIN_TYPE | HARDENED,
+            account | HARDENED,
+            0,
+            index,
+        ];
+
+        let mut data = vec![prefix.len() as u8];
+        data.extend_from_slice(prefix.as_bytes());
+        data.extend_from_slice(&serialize_path(&path));
+        let response = exchange(&transport, INS_GET_ADDR_SECP256K1, P1_INIT, data)?;
+        if response.len() < 33 {
+            return Err(eyre!(
+                "unexpected address response from ledger: {response:?}"
+            ));
+        }
+        let (public_key, address) = response.split_at(33);
+
+        Ok(Self {
+            transport: Arc::new(transport),
+            path,
+            public_key: public_key.to_vec(),
+            address: String::from_utf8(address.to_vec())?,
+        })
+    }
+}
+
+#[async_trait]
+impl Signer for LedgerSigner {
+    fn address(&self) -> &str {
+        &self.address
+    }
+
+    fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    fn sign_mode(&self) -> SignMode {
+        SignMode::LegacyAminoJson
+    }
+
+    /// Waits for the signature to be approved on the device
+    async fn sign(&self, sign_doc: Vec<u8>) -> Result<Vec<u8>> {
+        let transport = self.transport.clone();
+        let path = self.path;
+
+        // the exchange blocks until the user responds on the device
+        let der = tokio::task::spawn_blocking(move || sign_blocking(&transport, &path, &sign_doc))
+            .await??;
+        let signature = k256::ecdsa::Signature::from_der(&der)
+            .map_err(|err| eyre!("invalid signature from ledger: {err}"))?;
+        let signature = signature.normalize_s().unwrap_or(signature);
+
+        Ok(signature.as_ref().to_vec())
+    }
+}
+
+fn sign_blocking(
+    transport: &TransportNativeHID,
+    path: &[u32; 5],
+    message: &[u8],
+) -> Result<Vec<u8>> {
+    exchange(transport, INS_SIGN_SECP256K1, P1_INIT, serialize_path(path))?;
+
+    let chunks = message.chunks(CHUNK_SIZE).collect::<Vec<&[u8]>>();
+    let mut response = Vec::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let p1 = if i + 1 == chunks.len() {
+            P1_LAST
+        } else {
+            P1_ADD
+        };
+        response = exchange(transport, INS_SIGN_SECP256K1, p1, chunk.to_vec())?;
+    }
+
+    Ok(response)
+}
+
+fn exchange(transport: &TransportNativeHID, ins: u8, p1: u8, data: Vec<u8>) -> Result<Vec<u8>> {
+    let command = APDUCommand {
+        cla: CLA,
+        ins,
+        p1,
+        p2: 0,
+        data,
+    };
+    let answer = transport
+        .exchange(&command)
+        .map_err(|err| eyre!("ledger exchange failed: {err}"))?;
+    if answer.retcode() != SW_OK {
+        return Err(eyre!(
+            "ledger returned error code {:#06x}. is the Cosmos app open and was the request approved?",
+            answer.retcode()
+        ));
+    }
+
+    Ok(answer.data().to_vec())
+}
+
+fn serialize_path(path: &[u32; 5]) -> Vec<u8> {
+    path.iter().flat_map(|p| p.to_le_bytes()).collect()
+}
@@ -6,14 +6,23 @@ pub use ocular::account::AccountInfo;
 
 /// Re-export of the Sommelier `auction` module proto bindings
 pub use sommelier_auction_proto::auction;
+/// Re-export of the Cosmos SDK `authz` types used to bid on behalf of another account
+pub use sommelier_auction_proto::authz;
 /// Re-export of the Sommelier `cellarfees` module proto bindings
 pub use sommelier_auction_proto::cellarfees;
 pub use sommelier_auction_proto::cosmos_sdk_proto;
+/// Re-export of the Cosmos SDK node service, for the node's minimum gas prices
+pub use sommelier_auction_proto::node;
 
 pub mod bid;
 pub mod client;
 pub mod denom;
+pub mod fee;
+#[cfg(feature = "ledger")]
+pub mod ledger;
 pub mod parameters;
+pub mod sequence;
+pub mod signer;
 
 pub type BidResult = crate::auction::Bid;
 
@@ -0,0 +1,62 @@
+//! Per-account sequence tracking for transactions. Signing queries the account's sequence from the
+//! chain, so two transactions from the same account that are signed before the first one commits
+//! get the same sequence and the second is rejected. The [`SequenceManager`] serializes signing per
+//! account, remembers the sequence of a transaction whose outcome is unknown, and recovers the
+//! expected sequence from mismatch errors.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+/// How long to wait for the chain to reach an account sequence before giving up on it
+pub const SEQUENCE_WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How many times a transaction is re-signed after a sequence mismatch
+pub const MAX_SEQUENCE_RETRIES: u32 = 3;
+
+/// Sequence state of a single account
+#[derive(Debug, Default)]
+pub struct AccountSequence {
+    /// Sequence of a broadcast transaction that hasn't been seen to commit. The next transaction
+    /// waits for the chain to move past it.
+    pub pending: Option<u64>,
+}
+
+/// Tracks sequences of every account that submits transactions through a client. Clones share
+/// state.
+#[derive(Debug, Clone, Default)]
+pub struct SequenceManager {
+    accounts: Arc<Mutex<HashMap<String, Arc<AsyncMutex<AccountSequence>>>>>,
+}
+
+impl SequenceManager {
+    /// Locks an account for signing and broadcasting. Transactions from the account are serialized
+    /// until the guard is dropped.
+    pub async fn lock(&self, address: &str) -> OwnedMutexGuard<AccountSequence> {
+        let account = self
+            .accounts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entry(address.to_string())
+            .or_default()
+            .clone();
+
+        account.lock_owned().await
+    }
+}
+
+/// The sequence the chain expected, if the error is a sequence mismatch
+pub fn expected_sequence(error: &str) -> Option<u64> {
+    error
+        .split("account sequence mismatch, expected ")
+        .nth(1)?
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .parse()
+        .ok()
+}
@@ -0,0 +1,84 @@
+//! Signing backends for [`crate::client::Client::submit_bid_with_signer`]
+
+use async_trait::async_trait;
+use bip32::{DerivationPath, Language, Mnemonic, XPrv};
+use eyre::{eyre, Result};
+use ocular::cosmrs::crypto::secp256k1::SigningKey;
+
+/// The format of the sign doc a [`Signer`] is given
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignMode {
+    /// The protobuf encoded `SignDoc`
+    Direct,
+    /// The legacy amino JSON sign doc, for signers such as the Ledger Cosmos app that can't
+    /// parse protobuf
+    LegacyAminoJson,
+}
+
+/// Something that holds a secp256k1 key and signs transactions with it
+#[async_trait]
+pub trait Signer: Send + Sync {
+    /// The key's bech32 account address
+    fn address(&self) -> &str;
+
+    /// The key's compressed public key
+    fn public_key(&self) -> &[u8];
+
+    fn sign_mode(&self) -> SignMode {
+        SignMode::Direct
+    }
+
+    /// Signs a sign doc in this signer's [`SignMode`], returning the 64 byte signature the chain
+    /// expects
+    async fn sign(&self, sign_doc: Vec<u8>) -> Result<Vec<u8>>;
+}
+
+/// A key held in memory
+pub struct LocalSigner {
+    key: SigningKey,
+    public_key: Vec<u8>,
+    address: String,
+}
+
+impl LocalSigner {
+    /// Derives the key at m/44'/118'/0'/0/0 from a 24 word mnemonic
+    pub fn from_mnemonic(mnemonic: &str, prefix: &str) -> Result<Self> {
+        let mnemonic = Mnemonic::new(mnemonic.trim(), Language::English)
+            .map_err(|err| eyre!("invalid mnemonic: {err}"))?;
+        let path: DerivationPath = "m/44'/118'/0'/0/0".parse()?;
+        let xprv = XPrv::derive_from_path(mnemonic.to_seed(""), &path)?;
+        let key = SigningKey::from_bytes(&xprv.private_key().to_bytes())
+            .map_err(|err| eyre!("invalid key: {err}"))?;
+        let public_key = key.public_key();
+        let address = public_key
+            .account_id(prefix)
+            .map_err(|err| eyre!("failed to derive address: {err}"))?
+            .to_string();
+
+        Ok(Self {
+            key,
+            public_key: public_key.to_bytes(),
+            address,
+        })
+    }
+}
+
+#[async_trait]
+impl Signer for LocalSigner {
+    fn address(&self) -> &str {
+        &self.address
+    }
+
+    fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    async fn sign(&self, sign_doc: Vec<u8>) -> Result<Vec<u8>> {
+        let signature = self
+            .key
+            .sign(&sign_doc)
+            .map_err(|err| eyre!("failed to sign: {err}"))?;
+
+        Ok(signature.as_ref().to_vec())
+    }
+}